@@ -0,0 +1,240 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+use crate::model::error::RsrqError;
+use crate::model::job::array::{get_array_job_ids, get_next_array_id};
+use crate::model::job::options::JobOptions;
+use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+use crate::model::process::orphan_policy::OrphanPolicy;
+use crate::model::process::reaper::{reap_stale_processes, ReapResult};
+use crate::model::process::rsrq_process::Process;
+use crate::model::queue::paused::{get_paused_queues, pause_queue, resume_queue};
+use crate::model::queue::queue_info::QueueInfo;
+use crate::model::queue::queue_type::QueueType;
+use crate::model::queue::rsrq_queue::Queue;
+use crate::model::queue::status_group::StatusGroup;
+use crate::model::schedule::rsrq_schedule::Schedule;
+use crate::model::types::RsrqResult;
+use crate::model::worker::control::WorkerControl;
+use crate::util::redis::{redis_con_manager, redis_con_manager_from_url};
+
+/// A typed asynchronous client for interacting with rsrq.
+#[derive(Clone)]
+pub struct RsrqClient {
+    con: ConnectionManager,
+}
+
+impl RsrqClient {
+    /// Connect to Redis using the connection string in the `REDIS_URL` environment variable.
+    pub async fn new() -> RsrqResult<RsrqClient> {
+        let con = redis_con_manager().await?;
+        Ok(RsrqClient { con })
+    }
+
+    /// Connect to Redis using the specified connection string.
+    pub async fn from_url(url: &str) -> RsrqResult<RsrqClient> {
+        let con = redis_con_manager_from_url(url).await?;
+        Ok(RsrqClient { con })
+    }
+
+    /// Wrap an existing connection manager.
+    pub fn from_connection(con: ConnectionManager) -> RsrqClient {
+        RsrqClient { con }
+    }
+
+    /// Returns a handle to the underlying connection manager.
+    pub fn connection(&self) -> ConnectionManager {
+        self.con.clone()
+    }
+
+    /// Enqueue a single command to the target queue.
+    pub async fn enqueue(&mut self, queue: &str, cmd: &str) -> RsrqResult<Job> {
+        Job::new(queue, cmd, &JobOptions::default(), &mut self.con).await
+    }
+
+    /// Enqueue a single command to the target queue with additional options (e.g. a timeout).
+    pub async fn enqueue_with_options(&mut self, queue: &str, cmd: &str, options: &JobOptions) -> RsrqResult<Job> {
+        Job::new(queue, cmd, options, &mut self.con).await
+    }
+
+    /// Enqueue a collection of commands to the target queue, in order.
+    pub async fn enqueue_many<S: AsRef<str>>(&mut self, queue: &str, cmds: &[S], options: &JobOptions) -> RsrqResult<Vec<Job>> {
+        let mut jobs: Vec<Job> = Vec::with_capacity(cmds.len());
+        for cmd in cmds {
+            let job = Job::new(queue, cmd.as_ref(), options, &mut self.con).await?;
+            jobs.push(job);
+        }
+        Ok(jobs)
+    }
+
+    /// Enqueue a collection of commands to the target queue as a single array, in order, returning the id of the array.
+    pub async fn enqueue_array<S: AsRef<str>>(&mut self, queue: &str, cmds: &[S], options: &JobOptions) -> RsrqResult<(usize, Vec<Job>)> {
+        let array_id = get_next_array_id(&mut self.con).await?;
+        let options = JobOptions { array_id: Some(array_id), ..options.clone() };
+        let jobs = self.enqueue_many(queue, cmds, &options).await?;
+        Ok((array_id, jobs))
+    }
+
+    /// The ids of the jobs in an array, sorted.
+    pub async fn array_job_ids(&mut self, array_id: usize) -> RsrqResult<Vec<usize>> {
+        get_array_job_ids(array_id, &mut self.con).await
+    }
+
+    /// Load all attributes of a job.
+    pub async fn load_job(&mut self, id: usize) -> RsrqResult<Job> {
+        Job::load(id, &mut self.con).await
+    }
+
+    /// Load only the status of a job.
+    pub async fn job_status(&mut self, id: usize) -> RsrqResult<JobStatus> {
+        Job::get_status(id, &mut self.con).await
+    }
+
+    /// Load the jobs that are waiting to run or running (in all queues), sorted by id.
+    pub async fn active_jobs(&mut self) -> RsrqResult<Vec<Job>> {
+        let queues: Vec<Queue> = Queue::find_all(&mut self.con).await?.into_iter()
+            .filter(|x| !matches!(x.q_type, QueueType::Finished | QueueType::Failed))
+            .collect();
+        if queues.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = redis::pipe();
+        for queue in &queues {
+            queue.pipe_job_ids(&mut pipe);
+        }
+        let job_ids: Vec<Vec<usize>> = pipe.query_async(&mut self.con).await.map_err(RsrqError::RedisOpError)?;
+        let mut job_ids: Vec<usize> = job_ids.into_iter().flatten().collect();
+        job_ids.sort();
+        job_ids.dedup();
+
+        // A job may have finished (or been purged) since its id was read
+        let mut jobs = Vec::with_capacity(job_ids.len());
+        for job_id in job_ids {
+            match Job::load(job_id, &mut self.con).await {
+                Ok(job) if job.status.is_cancellable() => jobs.push(job),
+                Ok(_) | Err(RsrqError::JobNotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Cancel the specified jobs, returning the ids of those that were cancelled.
+    pub async fn cancel(&mut self, ids: &[usize]) -> RsrqResult<Vec<usize>> {
+        Job::cancel_many(ids, &mut self.con).await
+    }
+
+    /// Cancel the jobs of an array, returning the ids of those that were cancelled.
+    pub async fn cancel_array(&mut self, array_id: usize) -> RsrqResult<Vec<usize>> {
+        let ids = self.array_job_ids(array_id).await?;
+        Job::cancel_many(&ids, &mut self.con).await
+    }
+
+    /// Move failed, cancelled, or timed out jobs back into their queue (keeping their ids), returning the ids
+    /// of those that were re-queued. If no ids are given, all jobs in the failed queue(s) are considered.
+    /// Only jobs with one of the statuses are re-queued (default: failed, cancelled, and timed out).
+    pub async fn requeue(&mut self, ids: &[usize], queue: Option<&str>, statuses: &[JobStatus]) -> RsrqResult<Vec<usize>> {
+        let statuses = if statuses.is_empty() {
+            vec![JobStatus::Failed, JobStatus::Cancelled, JobStatus::TimedOut]
+        } else {
+            statuses.to_vec()
+        };
+        if let Some(status) = statuses.iter().find(|x| !x.is_requeueable()) {
+            return Err(RsrqError::ParserError(format!("Jobs with the status {} cannot be re-queued.", status)));
+        }
+
+        let mut ids = ids.to_vec();
+        if ids.is_empty() {
+            for cur_queue in Queue::find_all(&mut self.con).await? {
+                if matches!(cur_queue.q_type, QueueType::Failed) && queue.map_or(true, |x| x == cur_queue.name) {
+                    let cur_ids: Vec<usize> = self.con.smembers(&cur_queue.key).await.map_err(RsrqError::RedisOpError)?;
+                    ids.extend(cur_ids);
+                }
+            }
+            ids.sort();
+        }
+        Job::requeue_many(&ids, queue, &statuses, &mut self.con).await
+    }
+
+    /// Summarise the number of jobs in each state for all queues (or a specific queue).
+    pub async fn status(&mut self, queue: Option<&str>) -> RsrqResult<Vec<QueueInfo>> {
+        QueueInfo::load(queue, StatusGroup::Queue, &mut self.con).await
+    }
+
+    /// Summarise the number of jobs in each state for each Snakemake rule, in all queues (or a specific queue).
+    pub async fn status_by_rule(&mut self, queue: Option<&str>) -> RsrqResult<Vec<QueueInfo>> {
+        QueueInfo::load(queue, StatusGroup::Rule, &mut self.con).await
+    }
+
+    /// Summarise the number of jobs in each state for each array, in all queues (or a specific queue).
+    pub async fn status_by_array(&mut self, queue: Option<&str>) -> RsrqResult<Vec<QueueInfo>> {
+        QueueInfo::load(queue, StatusGroup::Array, &mut self.con).await
+    }
+
+    /// Stop workers from claiming jobs from the queue, returns false if it was already paused.
+    pub async fn pause_queue(&mut self, queue: &str) -> RsrqResult<bool> {
+        pause_queue(queue, &mut self.con).await
+    }
+
+    /// Allow workers to claim jobs from the queue again, returns false if it was not paused.
+    pub async fn resume_queue(&mut self, queue: &str) -> RsrqResult<bool> {
+        resume_queue(queue, &mut self.con).await
+    }
+
+    /// The names of all queues that are paused, sorted by name.
+    pub async fn paused_queues(&mut self) -> RsrqResult<Vec<String>> {
+        let mut names: Vec<String> = get_paused_queues(&mut self.con).await?.into_iter().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Remove processes that have not sent a heartbeat within the specified number of seconds
+    /// and release the jobs they were running according to the policy.
+    pub async fn reap(&mut self, stale_after_secs: u64, policy: OrphanPolicy) -> RsrqResult<ReapResult> {
+        reap_stale_processes(stale_after_secs, policy, &mut self.con).await
+    }
+
+    /// List all worker processes that are registered in Redis.
+    pub async fn list_workers(&mut self) -> RsrqResult<Vec<Process>> {
+        Process::load_all(&mut self.con).await
+    }
+
+    /// Add a recurring schedule that enqueues the command to the queue each time the cron expression is due.
+    pub async fn add_schedule(&mut self, queue: &str, cron: &str, cmd: &str, options: &JobOptions) -> RsrqResult<Schedule> {
+        Schedule::new(queue, cron, cmd, options, &mut self.con).await
+    }
+
+    /// List all recurring schedules.
+    pub async fn list_schedules(&mut self) -> RsrqResult<Vec<Schedule>> {
+        Schedule::load_all(&mut self.con).await
+    }
+
+    /// Remove the specified schedules, returning the ids of those that were removed.
+    pub async fn remove_schedules(&mut self, ids: &[usize]) -> RsrqResult<Vec<usize>> {
+        let mut removed = Vec::new();
+        for id in ids {
+            if Schedule::remove(*id, &mut self.con).await? {
+                removed.push(*id);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Send a control command to a worker process (by id), or all worker processes on a host (by hostname),
+    /// returning the ids of the processes the command was sent to.
+    pub async fn control_workers(&mut self, target: &str, control: WorkerControl) -> RsrqResult<Vec<usize>> {
+        let proc_ids: Vec<usize> = match target.parse::<usize>() {
+            Ok(proc_id) => Process::load(proc_id, &mut self.con).await?.map(|x| x.id).into_iter().collect(),
+            Err(_) => Process::load_all(&mut self.con).await?
+                .into_iter()
+                .filter(|x| x.hostname == target)
+                .map(|x| x.id)
+                .collect(),
+        };
+        for proc_id in &proc_ids {
+            control.send(*proc_id, &mut self.con).await?;
+        }
+        Ok(proc_ids)
+    }
+}
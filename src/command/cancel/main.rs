@@ -0,0 +1,24 @@
+use log::{info, warn};
+
+use crate::client::RsrqClient;
+use crate::model::types::RsrqResult;
+
+/// Cancel the specified jobs (and those of the array), those that have already finished are left untouched.
+pub async fn cancel_jobs(job_ids: &[usize], array: Option<usize>) -> RsrqResult<Vec<usize>> {
+    let mut client = RsrqClient::new().await?;
+
+    let mut job_ids = job_ids.to_vec();
+    if let Some(array_id) = array {
+        job_ids.extend(client.array_job_ids(array_id).await?);
+        job_ids.sort();
+        job_ids.dedup();
+    }
+    let cancelled = client.cancel(&job_ids).await?;
+    if cancelled.is_empty() {
+        warn!("No jobs were cancelled, they may have already finished or do not exist.");
+    } else {
+        let cancel_str = cancelled.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ");
+        info!("Cancelled {} jobs: {}", cancelled.len(), cancel_str);
+    }
+    Ok(cancelled)
+}
@@ -0,0 +1,63 @@
+use log::warn;
+
+use crate::client::RsrqClient;
+use crate::model::compat::tool::CompatTool;
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_id_list;
+
+/// Options that don't change which jobs are cancelled.
+const IGNORED_FLAGS: &[&str] = &["-Q", "--quiet", "-v", "--verbose", "-b", "--batch", "-f", "--full"];
+
+/// The job ids given to scancel or qdel, as separate arguments or comma separated.
+fn parse_job_ids(args: &[String]) -> RsrqResult<Vec<usize>> {
+    let mut out = Vec::new();
+    for arg in args {
+        if arg.starts_with('-') {
+            if !IGNORED_FLAGS.contains(&arg.as_str()) {
+                return Err(RsrqError::ParserError(format!("Unsupported option: {}", arg)));
+            }
+            continue;
+        }
+        out.extend(parse_id_list(arg)?);
+    }
+    if out.is_empty() {
+        return Err(RsrqError::ParserError("No job ids were given.".to_string()));
+    }
+    Ok(out)
+}
+
+/// Cancel jobs as scancel or qdel does, it is an error if any of the jobs do not exist.
+pub async fn compat_cancel(tool: CompatTool, args: &[String]) -> RsrqResult<()> {
+    let job_ids = parse_job_ids(args)?;
+    let mut client = RsrqClient::new().await?;
+    let cancelled = client.cancel(&job_ids).await?;
+
+    let user = std::env::var("USER").unwrap_or("rsrq".to_string());
+    let mut missing = Vec::new();
+    for job_id in job_ids {
+        if cancelled.contains(&job_id) {
+            if tool == CompatTool::Qdel {
+                println!("{} has deleted job {}", user, job_id);
+            }
+            continue;
+        }
+        match client.job_status(job_id).await {
+            Ok(_) => warn!("Job {} has already finished.", job_id),
+            Err(RsrqError::JobNotFound(_)) => missing.push(job_id.to_string()),
+            Err(err) => return Err(err),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(RsrqError::GeneralError(format!("Invalid job id specified: {}", missing.join(", "))));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_job_ids() {
+    let args: Vec<String> = ["--quiet", "1,2", "3"].iter().map(|x| x.to_string()).collect();
+    assert_eq!(parse_job_ids(&args).unwrap(), vec![1, 2, 3]);
+    assert!(parse_job_ids(&["-u".to_string(), "alice".to_string()]).is_err());
+    assert!(parse_job_ids(&[]).is_err());
+}
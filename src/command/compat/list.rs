@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use crate::client::RsrqClient;
+use crate::model::compat::job_row::JobRow;
+use crate::model::compat::qstat::{format_qstat_details, format_qstat_header, format_qstat_row, parse_qstat_args};
+use crate::model::compat::squeue::{format_squeue, parse_squeue_args};
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::util::time::get_timestamp_s;
+
+/// Load the jobs that are waiting to run or running, with the hostname of the worker running each job.
+async fn load_rows() -> RsrqResult<Vec<JobRow>> {
+    let mut client = RsrqClient::new().await?;
+    let hosts: HashMap<usize, String> = client.list_workers().await?.into_iter().map(|x| (x.id, x.hostname)).collect();
+    let jobs = client.active_jobs().await?;
+    Ok(jobs.iter().map(|job| JobRow::from_job(job, job.process_id.and_then(|x| hosts.get(&x).cloned()))).collect())
+}
+
+/// List the jobs as squeue does.
+pub async fn compat_squeue(args: &[String]) -> RsrqResult<()> {
+    let args = parse_squeue_args(args)?;
+    let rows = load_rows().await?;
+    let now = get_timestamp_s()?;
+    if args.header {
+        println!("{}", format_squeue(&args.format, None, now));
+    }
+    for row in rows.iter().filter(|x| args.includes(x)) {
+        println!("{}", format_squeue(&args.format, Some(row), now));
+    }
+    Ok(())
+}
+
+/// List the jobs (or show the details of specific jobs) as qstat does, nothing is printed if there are no jobs.
+pub async fn compat_qstat(args: &[String]) -> RsrqResult<()> {
+    let args = parse_qstat_args(args)?;
+    let rows = load_rows().await?;
+
+    if !args.details.is_empty() {
+        let mut missing = Vec::new();
+        for job_id in &args.details {
+            match rows.iter().find(|x| x.id == *job_id) {
+                Some(row) => println!("{}", format_qstat_details(row)),
+                None => missing.push(job_id.to_string()),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(RsrqError::GeneralError(format!("Following jobs do not exist: {}", missing.join(", "))));
+        }
+        return Ok(());
+    }
+
+    let rows: Vec<&JobRow> = rows.iter().filter(|x| args.includes(x)).collect();
+    if !rows.is_empty() {
+        println!("{}", format_qstat_header());
+        for row in rows {
+            println!("{}", format_qstat_row(row));
+        }
+    }
+    Ok(())
+}
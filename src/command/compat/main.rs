@@ -0,0 +1,15 @@
+use crate::command::compat::cancel::compat_cancel;
+use crate::command::compat::list::{compat_qstat, compat_squeue};
+use crate::command::compat::submit::compat_submit;
+use crate::model::compat::tool::CompatTool;
+use crate::model::types::RsrqResult;
+
+/// Act as the SLURM or SGE command, given its arguments.
+pub async fn run_compat(tool: CompatTool, args: &[String]) -> RsrqResult<()> {
+    match tool {
+        CompatTool::Sbatch | CompatTool::Qsub => compat_submit(tool, args).await,
+        CompatTool::Squeue => compat_squeue(args).await,
+        CompatTool::Qstat => compat_qstat(args).await,
+        CompatTool::Scancel | CompatTool::Qdel => compat_cancel(tool, args).await,
+    }
+}
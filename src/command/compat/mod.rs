@@ -0,0 +1,4 @@
+pub mod main;
+pub mod submit;
+pub mod list;
+pub mod cancel;
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use crate::client::RsrqClient;
+use crate::model::compat::qsub::{apply_sge_defaults, parse_qsub_args, sge_env, QSUB_DIRECTIVE, SGE_JOB_ID_VARS};
+use crate::model::compat::request::{read_directives, read_shebang, SubmitRequest, META_USER};
+use crate::model::compat::sbatch::{apply_slurm_defaults, parse_sbatch_args, slurm_env, SBATCH_DIRECTIVE, SLURM_JOB_ID_VARS};
+use crate::model::compat::tool::CompatTool;
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// Submit a job as sbatch or qsub would, the options given as arguments take precedence over those in the script.
+/// If neither a script nor a command is given, the script is read from stdin.
+/// The script is copied when the job is submitted, and run by the interpreter of its shebang (or the one given by -S).
+pub async fn compat_submit(tool: CompatTool, args: &[String]) -> RsrqResult<()> {
+    let slurm = match tool {
+        CompatTool::Sbatch => true,
+        CompatTool::Qsub => false,
+        _ => return Err(RsrqError::GeneralError(format!("{} does not submit jobs.", tool))),
+    };
+    let parse = if slurm { parse_sbatch_args } else { parse_qsub_args };
+    let mut request = parse(args, false)?;
+
+    // Read the directives and the interpreter from the script
+    let contents = match (&request.wrap, &request.script) {
+        (Some(_), _) => String::new(),
+        (None, Some(script)) => std::fs::read_to_string(script).map_err(RsrqError::FileNotFound)?,
+        (None, None) => {
+            let mut contents = String::new();
+            std::io::stdin().read_to_string(&mut contents).map_err(RsrqError::FileReadError)?;
+            contents
+        }
+    };
+    let prefix = if slurm { SBATCH_DIRECTIVE } else { QSUB_DIRECTIVE };
+    request.merge(parse(&read_directives(&contents, prefix)?, true)?);
+    if request.interpreter.is_none() {
+        request.interpreter = read_shebang(&contents);
+    }
+
+    // Jobs run in the directory they were submitted from (unless another is given), and relative paths are resolved against it
+    let submit_dir = std::env::current_dir().map_err(RsrqError::IOError)?;
+    request.cwd = Some(match &request.cwd {
+        Some(cwd) => submit_dir.join(cwd).to_string_lossy().to_string(),
+        None => submit_dir.to_string_lossy().to_string(),
+    });
+    if slurm {
+        apply_slurm_defaults(&mut request);
+    } else {
+        apply_sge_defaults(&mut request);
+    }
+
+    // The job runs a copy of the script, it can be changed or deleted once submitted
+    if request.wrap.is_none() {
+        request.contents = Some(contents);
+    }
+
+    // Create the job, the environment variables of the scheduler are set for the command
+    let submit_dir = submit_dir.to_string_lossy();
+    let (scheduler_env, job_id_vars) = if slurm {
+        (slurm_env(&request, &submit_dir), SLURM_JOB_ID_VARS)
+    } else {
+        (sge_env(&request, &submit_dir), SGE_JOB_ID_VARS)
+    };
+
+    // The submitting environment is exported as the scheduler would (sbatch exports all of it by default, qsub none of it)
+    let submit_env: BTreeMap<String, String> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    let mut env = request.exported_env(&submit_env, slurm);
+    env.extend(scheduler_env);
+    let mut metadata = BTreeMap::new();
+    if let Ok(user) = std::env::var("USER") {
+        metadata.insert(META_USER.to_string(), user);
+    }
+    let cmd = request.to_command(job_id_vars)?;
+    let queue = request.queue.clone().unwrap_or("default".to_string());
+    let mut client = RsrqClient::new().await?;
+    let job = client.enqueue_with_options(&queue, &cmd, &request.to_options(env, metadata)).await?;
+
+    println!("{}", submitted_message(tool, &request, job.id));
+    Ok(())
+}
+
+/// The line that is printed once the job has been submitted.
+fn submitted_message(tool: CompatTool, request: &SubmitRequest, job_id: usize) -> String {
+    if request.parsable {
+        return job_id.to_string();
+    }
+    match tool {
+        CompatTool::Qsub => format!("Your job {} (\"{}\") has been submitted", job_id, request.name.as_deref().unwrap_or_default()),
+        _ => format!("Submitted batch job {}", job_id),
+    }
+}
@@ -0,0 +1,37 @@
+use log::info;
+
+use crate::client::RsrqClient;
+use crate::model::array::params::load_array_params;
+use crate::model::array::template::CommandTemplate;
+use crate::model::cli::JobArgs;
+use crate::model::job::rsrq_job::Job;
+use crate::model::types::RsrqResult;
+
+/// Expand the template into one command for each set of parameters, and enqueue them as a single array.
+/// All commands are rendered before any job is created, so a missing placeholder does not leave a partial array.
+pub async fn enqueue_array(
+    queue: &str,
+    template: &str,
+    params: &Option<String>,
+    range: &Option<String>,
+    glob: &Option<String>,
+    raw: bool,
+    job_args: &JobArgs,
+) -> RsrqResult<Vec<Job>> {
+    // Parse the options that will be applied to each job
+    let options = job_args.to_options()?;
+
+    // Render the command of each job
+    let template = CommandTemplate::parse(template)?;
+    let param_sets = load_array_params(params.as_deref(), range.as_deref(), glob.as_deref())?;
+    let cmds = param_sets.iter()
+        .map(|x| template.render(x, raw))
+        .collect::<RsrqResult<Vec<String>>>()?;
+
+    // Connect to Redis and create the jobs
+    let mut client = RsrqClient::new().await?;
+    let (array_id, jobs) = client.enqueue_array(queue, &cmds, &options).await?;
+    info!("Enqueued array {} with {} jobs to queue: {}", array_id, jobs.len(), queue);
+
+    Ok(jobs)
+}
@@ -1,29 +1,34 @@
 use log::info;
 
+use crate::client::RsrqClient;
 use crate::model::enqueue_file::EnqueueFile;
+use crate::model::enqueue_format::EnqueueFormat;
+use crate::model::cli::JobArgs;
 use crate::model::job::rsrq_job::Job;
 use crate::model::queue::queue_type::QueueType;
 use crate::model::queue::rsrq_queue::Queue;
 use crate::model::types::RsrqResult;
-use crate::util::redis::redis_con_manager;
 
 /// Main method called to enqueue a collection of commands into a given queue
-pub async fn enqueue_file(path: &str, queue: &str) -> RsrqResult<Vec<Job>> {
+pub async fn enqueue_file(path: &str, queue: &str, format: EnqueueFormat, job_args: &JobArgs) -> RsrqResult<Vec<Job>> {
+    // Parse the options that will be applied to each job
+    let options = job_args.to_options()?;
+
     // Read the file
     info!("Reading jobs from file: {}", &path);
-    let enqueue_file = EnqueueFile::load(path);
+    let enqueue_file = EnqueueFile::load(path, format, &options)?;
 
     // Enqueue jobs in redis
     let q_target = Queue::new(QueueType::Queued, queue);
     info!("Enqueuing {} jobs to queue: {}", enqueue_file.jobs.len(), q_target.key);
 
     // Connect to Redis and execute
-    let mut con = redis_con_manager().await?;
+    let mut client = RsrqClient::new().await?;
 
-    // Create the pipeline of commands
+    // Create each of the jobs
     let mut created_jobs: Vec<Job> = Vec::with_capacity(enqueue_file.jobs.len());
-    for job_cmd in &enqueue_file.jobs {
-        let job = Job::new(queue, job_cmd, &mut con).await?;
+    for entry in &enqueue_file.jobs {
+        let job = client.enqueue_with_options(queue, &entry.cmd, &entry.options).await?;
         created_jobs.push(job);
     }
 
@@ -0,0 +1,99 @@
+use crate::client::RsrqClient;
+use crate::model::format::{tsv_value, OutputFormat};
+use crate::model::job::rsrq_job::Job;
+use crate::model::process::rsrq_process::Process;
+use crate::model::types::RsrqResult;
+use crate::util::time::{format_duration_ms, format_timestamp};
+
+/// Print the attributes of a single job.
+pub async fn job_show(job_id: usize, format: OutputFormat) -> RsrqResult<()> {
+    let mut client = RsrqClient::new().await?;
+    let job = client.load_job(job_id).await?;
+
+    // The worker may have since exited
+    let process = match job.process_id {
+        Some(process_id) => Process::load(process_id, &mut client.connection()).await?,
+        None => None,
+    };
+
+    let to_json = || {
+        let mut json = job.to_json();
+        if let Some(process) = &process {
+            json["hostname"] = process.hostname.clone().into();
+            json["pid"] = process.pid.into();
+        }
+        json
+    };
+
+    match format {
+        OutputFormat::Human => print_human(&job, process.as_ref()),
+        OutputFormat::Json => println!("{}", to_json()),
+        OutputFormat::Tsv => {
+            if let Some(fields) = to_json().as_object() {
+                for (key, value) in fields {
+                    println!("{}\t{}", key, tsv_value(value));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_human(job: &Job, process: Option<&Process>) {
+    let fmt_ts = |ts: Option<u64>| ts.map(format_timestamp).unwrap_or("-".to_string());
+    let worker = match (job.process_id, process) {
+        (Some(id), Some(process)) => format!("{} ({}:{})", id, process.hostname, process.pid),
+        (Some(id), None) => format!("{} (exited)", id),
+        (None, _) => "-".to_string(),
+    };
+
+    println!("Job:        {}", job.id);
+    if let Some(name) = &job.name {
+        println!("Name:       {}", name);
+    }
+    println!("Command:    {}", job.cmd);
+    println!("Status:     {}", job.status);
+    println!("Queue:      {}", job.queue);
+    println!("Priority:   {}", job.priority);
+    if let Some(array_id) = job.array_id {
+        println!("Array:      {}", array_id);
+    }
+    if !job.tags.is_empty() {
+        println!("Tags:       {}", job.tags.join(", "));
+    }
+    if job.mem_mb > 0 {
+        println!("Resources:  {} CPU(s), {} MB", job.cpus, job.mem_mb);
+    } else {
+        println!("Resources:  {} CPU(s)", job.cpus);
+    }
+    for (key, value) in &job.metadata {
+        println!("{:<12}{}", format!("{}:", key), value);
+    }
+    if let Some(cwd) = &job.cwd {
+        println!("Directory:  {}", cwd);
+    }
+    if !job.env.is_empty() {
+        let names = job.env.keys().cloned().collect::<Vec<String>>().join(", ");
+        println!("Env:        {}", names);
+    }
+    println!("Created:    {}", format_timestamp(job.created));
+    if let Some(run_at) = job.run_at {
+        println!("Run at:     {}", format_timestamp(run_at));
+    }
+    println!("Started:    {}", fmt_ts(job.started));
+    println!("Finished:   {}", fmt_ts(job.finished));
+    println!("Duration:   {}", job.duration_ms.map(format_duration_ms).unwrap_or("-".to_string()));
+    println!("Exit code:  {}", job.exit_code.map(|x| x.to_string()).unwrap_or("-".to_string()));
+    println!("Worker:     {}", worker);
+    println!("Attempt:    {}/{}", job.attempt, job.max_retries + 1);
+    if !job.depends_on.is_empty() {
+        let ids = job.depends_on.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ");
+        println!("Depends on: {}", ids);
+    }
+    if let Some(path) = &job.stdout_path {
+        println!("Stdout:     {}", path);
+    }
+    if let Some(path) = &job.stderr_path {
+        println!("Stderr:     {}", path);
+    }
+}
@@ -0,0 +1,35 @@
+use std::io::Write;
+
+use log::warn;
+
+use crate::client::RsrqClient;
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// Print the captured stdout (or stderr) of a job. Output that was written to a log file is read
+/// from the file, falling back to the tail stored in Redis if it can't be read (e.g. on another host).
+pub async fn print_logs(job_id: usize, stderr: bool) -> RsrqResult<()> {
+    let mut client = RsrqClient::new().await?;
+    let job = client.load_job(job_id).await?;
+
+    let (path, captured) = if stderr {
+        (job.stderr_path, job.stderr)
+    } else {
+        (job.stdout_path, job.stdout)
+    };
+
+    if let Some(path) = path {
+        match std::fs::File::open(&path) {
+            Ok(mut file) => {
+                std::io::copy(&mut file, &mut std::io::stdout()).map_err(RsrqError::IOError)?;
+                return Ok(());
+            }
+            Err(err) => warn!("Unable to read {}, showing the stored tail instead: {}", path, err)
+        }
+    }
+
+    let mut out = std::io::stdout();
+    out.write_all(captured.unwrap_or_default().as_bytes()).map_err(RsrqError::IOError)?;
+    out.flush().map_err(RsrqError::IOError)?;
+    Ok(())
+}
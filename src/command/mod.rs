@@ -1,5 +1,13 @@
 pub mod snakemake;
 pub mod purge;
+pub mod queue;
+pub mod reap;
+pub mod requeue;
+pub mod schedule;
 pub mod worker;
 pub mod enqueue;
 pub mod status;
+pub mod cancel;
+pub mod job;
+pub mod logs;
+pub mod compat;
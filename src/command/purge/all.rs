@@ -21,7 +21,7 @@ pub fn purge_all() -> RsrqResult<()> {
     for key in &keys {
         pipe.del(key);
     }
-    pipe.query(&mut con).map_err(RsrqError::RedisOpError)?;
+    let _: () = pipe.query(&mut con).map_err(RsrqError::RedisOpError)?;
     info!("Successfully removed {} keys.", keys.len());
     Ok(())
 }
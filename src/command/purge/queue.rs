@@ -2,6 +2,9 @@ use log::info;
 use redis::Commands;
 
 use crate::model::error::RsrqError;
+use crate::model::job::array::get_array_key;
+use crate::model::job::dependency::get_dependents_key;
+use crate::model::job::key::JobKey;
 use crate::model::job::rsrq_job::Job;
 use crate::model::queue::queue_type::QueueType;
 use crate::model::queue::rsrq_queue::Queue;
@@ -29,23 +32,43 @@ pub fn purge_queue(queue: &Option<String>, queue_type: QueueType) -> RsrqResult<
     pipe.atomic();
     for key in &keys_to_search {
         let values: Vec<usize> = match queue_obj.q_type {
-            QueueType::Queued => con.lrange(key, 0, -1).map_err(RsrqError::RedisOpError)?,
+            // Queued lists (created before jobs had priorities) may not have been converted into sorted sets yet
+            QueueType::Queued => match con.key_type::<_, String>(key).map_err(RsrqError::RedisOpError)?.as_str() {
+                "list" => con.lrange(key, 0, -1).map_err(RsrqError::RedisOpError)?,
+                _ => con.zrange(key, 0, -1).map_err(RsrqError::RedisOpError)?,
+            },
             QueueType::Running => con.lrange(key, 0, -1).map_err(RsrqError::RedisOpError)?,
             QueueType::Finished => con.smembers(key).map_err(RsrqError::RedisOpError)?,
             QueueType::Failed => con.smembers(key).map_err(RsrqError::RedisOpError)?,
+            QueueType::Delayed => con.zrange(key, 0, -1).map_err(RsrqError::RedisOpError)?,
+            QueueType::Deferred => con.smembers(key).map_err(RsrqError::RedisOpError)?,
+            QueueType::Scheduled => con.zrange(key, 0, -1).map_err(RsrqError::RedisOpError)?,
+        };
+
+        // Find the array (if any) of each job
+        let array_ids: Vec<Option<String>> = {
+            let mut read_pipe = redis::pipe();
+            for value in &values {
+                read_pipe.hget(Job::get_redis_key(*value), JobKey::ArrayId);
+            }
+            read_pipe.query(&mut con).map_err(RsrqError::RedisOpError)?
         };
 
         // Delete each id
-        for value in &values {
+        for (value, array_id) in values.iter().zip(array_ids) {
             let job_key = Job::get_redis_key(*value);
             pipe.del(&job_key);
+            pipe.del(get_dependents_key(*value));
+            if let Some(array_id) = array_id.and_then(|x| x.parse::<usize>().ok()) {
+                pipe.srem(get_array_key(array_id), *value);
+            }
         }
         info!("Removed {} jobs from queue {}", values.len(), key);
 
         // Delete the queue
         pipe.del(key);
     }
-    pipe.query(&mut con).map_err(RsrqError::RedisOpError)?;
+    let _: () = pipe.query(&mut con).map_err(RsrqError::RedisOpError)?;
 
     Ok(())
 }
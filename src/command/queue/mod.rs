@@ -0,0 +1,2 @@
+pub mod pause;
+pub mod resume;
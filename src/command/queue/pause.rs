@@ -0,0 +1,18 @@
+use log::{info, warn};
+
+use crate::client::RsrqClient;
+use crate::model::types::RsrqResult;
+
+/// Stop workers from starting new jobs from the specified queues.
+pub async fn pause_queues(queues: &[String]) -> RsrqResult<()> {
+    let mut client = RsrqClient::new().await?;
+
+    for queue in queues {
+        if client.pause_queue(queue).await? {
+            info!("Paused queue: {}", queue);
+        } else {
+            warn!("Queue {} is already paused.", queue);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use log::{info, warn};
+
+use crate::client::RsrqClient;
+use crate::model::types::RsrqResult;
+
+/// Allow workers to start jobs from the specified queues again.
+pub async fn resume_queues(queues: &[String]) -> RsrqResult<()> {
+    let mut client = RsrqClient::new().await?;
+
+    for queue in queues {
+        if client.resume_queue(queue).await? {
+            info!("Resumed queue: {}", queue);
+        } else {
+            warn!("Queue {} is not paused.", queue);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use log::info;
+
+use crate::client::RsrqClient;
+use crate::model::process::orphan_policy::OrphanPolicy;
+use crate::model::process::reaper::ReapResult;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::format_id_list;
+use crate::util::time::parse_duration;
+
+/// Remove dead worker processes and release their jobs according to the policy.
+pub async fn reap(stale_after: &str, policy: OrphanPolicy) -> RsrqResult<ReapResult> {
+    let stale_after_secs = parse_duration(stale_after)?;
+    let mut client = RsrqClient::new().await?;
+
+    let reaped = client.reap(stale_after_secs, policy).await?;
+    if reaped.proc_ids.is_empty() {
+        info!("No dead processes were found.");
+    } else {
+        info!("Reaped {} processes: {}", reaped.proc_ids.len(), format_id_list(&reaped.proc_ids));
+    }
+    if !reaped.requeued.is_empty() {
+        info!("Re-queued {} jobs: {}", reaped.requeued.len(), format_id_list(&reaped.requeued));
+    }
+    if !reaped.failed.is_empty() {
+        info!("Failed {} jobs: {}", reaped.failed.len(), format_id_list(&reaped.failed));
+    }
+    Ok(reaped)
+}
@@ -0,0 +1,19 @@
+use log::{info, warn};
+
+use crate::client::RsrqClient;
+use crate::model::job::status::JobStatus;
+use crate::model::types::RsrqResult;
+
+/// Requeue the specified jobs (or all failed jobs matching the filters), keeping their ids.
+pub async fn requeue_jobs(job_ids: &[usize], queue: &Option<String>, statuses: &[JobStatus]) -> RsrqResult<Vec<usize>> {
+    let mut client = RsrqClient::new().await?;
+
+    let requeued = client.requeue(job_ids, queue.as_deref(), statuses).await?;
+    if requeued.is_empty() {
+        warn!("No jobs were re-queued, they may not have failed or do not exist.");
+    } else {
+        let requeue_str = requeued.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ");
+        info!("Re-queued {} jobs: {}", requeued.len(), requeue_str);
+    }
+    Ok(requeued)
+}
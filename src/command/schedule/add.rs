@@ -0,0 +1,24 @@
+use log::info;
+
+use crate::client::RsrqClient;
+use crate::model::cli::JobArgs;
+use crate::model::error::RsrqError;
+use crate::model::schedule::rsrq_schedule::Schedule;
+use crate::model::types::RsrqResult;
+use crate::util::time::{format_timestamp, get_timestamp_s};
+
+/// Add a recurring schedule that is enqueued by running workers.
+pub async fn schedule_add(queue: &str, cron: &str, cmd: &str, args: &JobArgs) -> RsrqResult<Schedule> {
+    if !args.after.is_empty() || args.at.is_some() || args.delay.is_some() {
+        return Err(RsrqError::ParserError("The --after, --at, and --delay options cannot be used with schedules.".to_string()));
+    }
+    let options = args.to_options()?;
+    let mut client = RsrqClient::new().await?;
+
+    let schedule = client.add_schedule(queue, cron, cmd, &options).await?;
+    match schedule.next_run(get_timestamp_s()?) {
+        Some(next_run) => info!("Added schedule {}, next run at {}.", schedule.id, format_timestamp(next_run)),
+        None => info!("Added schedule {}, it will never run.", schedule.id),
+    }
+    Ok(schedule)
+}
@@ -0,0 +1,39 @@
+use log::info;
+use serde_json::Value;
+
+use crate::client::RsrqClient;
+use crate::model::format::{print_tsv, OutputFormat};
+use crate::model::schedule::rsrq_schedule::Schedule;
+use crate::model::types::RsrqResult;
+use crate::util::time::{format_timestamp, get_timestamp_s};
+
+/// Print all recurring schedules.
+pub async fn schedule_list(format: OutputFormat) -> RsrqResult<()> {
+    let mut client = RsrqClient::new().await?;
+    let schedules = client.list_schedules().await?;
+    let now = get_timestamp_s()?;
+
+    match format {
+        OutputFormat::Human => {
+            if schedules.is_empty() {
+                info!("No schedules found.");
+            }
+            let fmt_ts = |ts: Option<u64>| ts.map(format_timestamp).unwrap_or("-".to_string());
+            for schedule in &schedules {
+                info!("[Schedule {}] {} ({})", schedule.id, schedule.cron, schedule.queue);
+                info!("  Command:  {}", schedule.cmd);
+                info!("  Last run: {}", fmt_ts(schedule.last_run));
+                info!("  Next run: {}", fmt_ts(schedule.next_run(now)));
+            }
+        }
+        OutputFormat::Json => {
+            let rows: Vec<Value> = schedules.iter().map(|x| x.to_json(now)).collect();
+            println!("{}", Value::Array(rows));
+        }
+        OutputFormat::Tsv => {
+            let rows: Vec<Value> = schedules.iter().map(|x| x.to_json(now)).collect();
+            print_tsv(Schedule::TSV_COLUMNS, &rows);
+        }
+    }
+    Ok(())
+}
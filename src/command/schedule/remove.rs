@@ -0,0 +1,18 @@
+use log::{info, warn};
+
+use crate::client::RsrqClient;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::format_id_list;
+
+/// Remove the specified schedules.
+pub async fn schedule_remove(schedule_ids: &[usize]) -> RsrqResult<Vec<usize>> {
+    let mut client = RsrqClient::new().await?;
+
+    let removed = client.remove_schedules(schedule_ids).await?;
+    if removed.is_empty() {
+        warn!("No schedules were removed, they may not exist.");
+    } else {
+        info!("Removed {} schedules: {}", removed.len(), format_id_list(&removed));
+    }
+    Ok(removed)
+}
@@ -5,21 +5,58 @@ use std::path::Path;
 use log::info;
 
 use crate::model::error::RsrqError;
+use crate::model::snakemake::version::SnakemakeVersion;
 use crate::model::types::RsrqResult;
+use crate::util::system::get_absolute_path;
 
-pub fn snakemake_config(directory: &str) -> RsrqResult<()> {
-    let cancel_name = "cancel.sh";
-    let jobscript_name = "jobscript.sh";
-    let status_name = "status.sh";
-    let submit_name = "submit.sh";
-
-    let config_lines: [&str; 4] = [
-        &format!("jobscript: {}", jobscript_name),
-        &format!("cluster: {}", submit_name),
-        &format!("cluster-status: {}", status_name),
-        &format!("cluster-cancel: {}", cancel_name)
+const CANCEL_NAME: &str = "cancel.sh";
+const JOBSCRIPT_NAME: &str = "jobscript.sh";
+const SIDECAR_NAME: &str = "sidecar.sh";
+const STATUS_NAME: &str = "status.sh";
+const SUBMIT_NAME: &str = "submit.sh";
+
+/// A file that is written to the profile directory.
+struct ProfileFile {
+    name: &'static str,
+    contents: String,
+    executable: bool,
+}
+
+/// The settings in "config.yaml", Snakemake 8 runs the commands with the cluster-generic executor.
+/// Snakemake 8 does not resolve commands relative to the profile, so their absolute paths are used.
+fn config_lines(version: SnakemakeVersion, directory: &str) -> Vec<String> {
+    let path = |name: &str| Path::new(directory).join(name).to_string_lossy().to_string();
+    match version {
+        SnakemakeVersion::V7 => vec![
+            format!("jobscript: {}", JOBSCRIPT_NAME),
+            format!("cluster: {}", SUBMIT_NAME),
+            format!("cluster-status: {}", STATUS_NAME),
+            format!("cluster-cancel: {}", CANCEL_NAME),
+            format!("cluster-sidecar: {}", SIDECAR_NAME),
+        ],
+        SnakemakeVersion::V8 => vec![
+            "executor: cluster-generic".to_string(),
+            format!("jobscript: {}", path(JOBSCRIPT_NAME)),
+            format!("cluster-generic-submit-cmd: {}", path(SUBMIT_NAME)),
+            format!("cluster-generic-status-cmd: {}", path(STATUS_NAME)),
+            format!("cluster-generic-cancel-cmd: {}", path(CANCEL_NAME)),
+            format!("cluster-generic-sidecar-cmd: {}", path(SIDECAR_NAME)),
+        ],
+    }
+}
+
+/// The contents of each file in the profile, for the version of Snakemake.
+fn profile_files(version: SnakemakeVersion, directory: &str) -> Vec<ProfileFile> {
+    let script = |subcommand: &str| ["#!/bin/bash", &format!("rsrq snakemake {} \"$@\"", subcommand), ""].join("\n");
+
+    // The sidecar keeps running until Snakemake exits, exec ensures that it receives the signal to stop
+    let sidecar_lines = [
+        "#!/bin/bash",
+        "exec rsrq snakemake sidecar",
+        ""
     ];
 
+    // The properties line is read by "rsrq snakemake submit", both versions fill in the same placeholders
     let jobscript_lines = [
         "#!/bin/bash",
         "# properties = {properties}",
@@ -29,32 +66,19 @@ pub fn snakemake_config(directory: &str) -> RsrqResult<()> {
         ""
     ];
 
-    let cancel_lines = [
-        "#!/bin/bash",
-        "rsrq snakemake cancel \"$@\"",
-        ""
-    ];
-
-    let status_lines = [
-        "#!/bin/bash",
-        "rsrq snakemake status \"$@\"",
-        ""
-    ];
-
-    let submit_lines = [
-        "#!/bin/bash",
-        "rsrq snakemake submit \"$@\"",
-        ""
-    ];
+    vec![
+        ProfileFile { name: "config.yaml", contents: config_lines(version, directory).join("\n"), executable: false },
+        ProfileFile { name: CANCEL_NAME, contents: script("cancel"), executable: true },
+        ProfileFile { name: JOBSCRIPT_NAME, contents: jobscript_lines.join("\n"), executable: false },
+        ProfileFile { name: SIDECAR_NAME, contents: sidecar_lines.join("\n"), executable: true },
+        ProfileFile { name: STATUS_NAME, contents: script("status"), executable: true },
+        ProfileFile { name: SUBMIT_NAME, contents: script("submit"), executable: true },
+    ]
+}
 
+pub fn snakemake_config(directory: &str, version: SnakemakeVersion) -> RsrqResult<()> {
     let dir = Path::new(directory);
 
-    let config = dir.join("config.yaml");
-    let cancel = dir.join(cancel_name);
-    let jobscript = dir.join(jobscript_name);
-    let status = dir.join(status_name);
-    let submit = dir.join(submit_name);
-
     // Create the directory if it does not exist
     if dir.exists() {
         return Err(RsrqError::GeneralError(format!("Directory {} already exists", directory)));
@@ -62,19 +86,70 @@ pub fn snakemake_config(directory: &str) -> RsrqResult<()> {
         fs::create_dir_all(directory).map_err(RsrqError::IOError)?;
     }
 
-    // Write the files
-    fs::write(config, config_lines.join("\n")).map_err(RsrqError::IOError)?;
-    fs::write(&cancel, cancel_lines.join("\n")).map_err(RsrqError::IOError)?;
-    fs::write(jobscript, jobscript_lines.join("\n")).map_err(RsrqError::IOError)?;
-    fs::write(&status, status_lines.join("\n")).map_err(RsrqError::IOError)?;
-    fs::write(&submit, submit_lines.join("\n")).map_err(RsrqError::IOError)?;
-
-    // Set the permissions to be executable
-    fs::set_permissions(cancel, fs::Permissions::from_mode(0o755)).map_err(RsrqError::IOError)?;
-    fs::set_permissions(status, fs::Permissions::from_mode(0o755)).map_err(RsrqError::IOError)?;
-    fs::set_permissions(submit, fs::Permissions::from_mode(0o755)).map_err(RsrqError::IOError)?;
+    // Write the files, the scripts are set to be executable
+    for file in profile_files(version, &get_absolute_path(directory)?) {
+        let path = dir.join(file.name);
+        fs::write(&path, file.contents).map_err(RsrqError::IOError)?;
+        if file.executable {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).map_err(RsrqError::IOError)?;
+        }
+    }
 
-    info!("Wrote files to: {}", directory);
+    info!("Wrote Snakemake {} profile to: {}", version, directory);
+    if version == SnakemakeVersion::V8 {
+        info!("The cluster-generic executor is required: pip install snakemake-executor-plugin-cluster-generic");
+    }
     info!("Run Snakemake as: snakemake --profile {}", directory);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_profile_files_v7() {
+    let files = profile_files(SnakemakeVersion::V7, "/profiles/rsrq");
+    let names: Vec<&str> = files.iter().map(|x| x.name).collect();
+    assert_eq!(names, vec!["config.yaml", "cancel.sh", "jobscript.sh", "sidecar.sh", "status.sh", "submit.sh"]);
+    assert_eq!(files[0].contents, "jobscript: jobscript.sh\ncluster: submit.sh\ncluster-status: status.sh\ncluster-cancel: cancel.sh\ncluster-sidecar: sidecar.sh");
+    assert_eq!(files[1].contents, "#!/bin/bash\nrsrq snakemake cancel \"$@\"\n");
+    assert_eq!(files[2].contents, "#!/bin/bash\n# properties = {properties}\n\nset -o errexit\n{exec_job}\n");
+    assert_eq!(files[3].contents, "#!/bin/bash\nexec rsrq snakemake sidecar\n");
+    assert_eq!(files[4].contents, "#!/bin/bash\nrsrq snakemake status \"$@\"\n");
+    assert_eq!(files[5].contents, "#!/bin/bash\nrsrq snakemake submit \"$@\"\n");
+    assert_eq!(files.iter().filter(|x| x.executable).count(), 4);
+}
+
+#[test]
+fn test_profile_files_v8() {
+    let files = profile_files(SnakemakeVersion::V8, "/profiles/rsrq");
+    assert_eq!(files[0].name, "config.yaml");
+    assert_eq!(files[0].contents, [
+        "executor: cluster-generic",
+        "jobscript: /profiles/rsrq/jobscript.sh",
+        "cluster-generic-submit-cmd: /profiles/rsrq/submit.sh",
+        "cluster-generic-status-cmd: /profiles/rsrq/status.sh",
+        "cluster-generic-cancel-cmd: /profiles/rsrq/cancel.sh",
+        "cluster-generic-sidecar-cmd: /profiles/rsrq/sidecar.sh",
+    ].join("\n"));
+
+    // The scripts are the same for both versions
+    let v7 = profile_files(SnakemakeVersion::V7, "/profiles/rsrq");
+    for (a, b) in files.iter().zip(&v7).skip(1) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.contents, b.contents);
+        assert_eq!(a.executable, b.executable);
+    }
+}
+
+#[test]
+fn test_snakemake_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let profile = dir.path().join("profile");
+    snakemake_config(profile.to_str().unwrap(), SnakemakeVersion::V8).unwrap();
+
+    let config = fs::read_to_string(profile.join("config.yaml")).unwrap();
+    assert!(config.contains(&format!("cluster-generic-submit-cmd: {}", profile.join("submit.sh").display())));
+    let mode = fs::metadata(profile.join("submit.sh")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+
+    // Existing profiles are not overwritten
+    assert!(snakemake_config(profile.to_str().unwrap(), SnakemakeVersion::V7).is_err());
+}
@@ -2,3 +2,4 @@ pub mod cancel;
 pub mod status;
 pub mod submit;
 pub mod config;
+pub mod sidecar;
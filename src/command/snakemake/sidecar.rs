@@ -0,0 +1,199 @@
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+use redis::aio::ConnectionManager;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Mutex;
+
+use crate::command::snakemake::status::SnakemakeStatus;
+use crate::model::error::RsrqError;
+use crate::model::job::key::JobKey;
+use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+use crate::model::snakemake::status_cache::StatusCache;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_string;
+use crate::util::redis::redis_con_manager;
+use crate::util::system::get_pid;
+
+/// How often the statuses of jobs that may still change are refreshed.
+const REFRESH_MS: u64 = 1000;
+
+/// The status script stops waiting for the sidecar (and connects to Redis instead) after this long.
+const QUERY_TIMEOUT_SECS: u64 = 10;
+
+/// Responses that start with this prefix are errors (e.g. the job does not exist).
+const ERROR_PREFIX: &str = "error: ";
+
+type SharedCache = Arc<Mutex<StatusCache>>;
+
+/// The path of the socket that the sidecar listens on.
+fn get_socket_path() -> PathBuf {
+    std::env::temp_dir().join(format!("rsrq-sidecar-{}.sock", get_pid()))
+}
+
+/// Runs until Snakemake terminates it, answering status checks from a cache that is refreshed in batches.
+/// The path of the socket is written to stdout, Snakemake passes it to the status script via SNAKEMAKE_CLUSTER_SIDECAR_VARS.
+pub async fn snakemake_sidecar() -> RsrqResult<()> {
+    let con = redis_con_manager().await?;
+
+    // Remove the socket of a previous process that had the same PID
+    let path = get_socket_path();
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).map_err(RsrqError::IOError)?;
+    println!("{}", path.display());
+    std::io::stdout().flush().map_err(RsrqError::IOError)?;
+    info!("Listening for status checks on: {}", path.display());
+
+    let cache: SharedCache = Arc::default();
+    let refresh_thread = tokio::spawn(refresh_statuses(cache.clone(), con.clone()));
+
+    let mut sigterm = signal(SignalKind::terminate()).map_err(RsrqError::IOError)?;
+    loop {
+        tokio::select! {
+            res = listener.accept() => match res {
+                Ok((stream, _)) => {
+                    let con = con.clone();
+                    tokio::spawn(handle_client(stream, cache.clone(), move |job_id| load_status(job_id, con.clone())));
+                }
+                Err(err) => warn!("Unable to accept a status check: {}", err),
+            },
+            _ = sigterm.recv() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    refresh_thread.abort();
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Update the statuses of all jobs that may still change with a single pipeline.
+async fn refresh_statuses(cache: SharedCache, mut con: ConnectionManager) {
+    let mut interval = tokio::time::interval(Duration::from_millis(REFRESH_MS));
+    loop {
+        interval.tick().await;
+        let job_ids = cache.lock().await.pending();
+        if job_ids.is_empty() {
+            continue;
+        }
+        let mut pipe = redis::pipe();
+        for job_id in &job_ids {
+            pipe.hget(Job::get_redis_key(*job_id), JobKey::Status);
+        }
+        match pipe.query_async::<_, Vec<Option<JobStatus>>>(&mut con).await {
+            Ok(statuses) => cache.lock().await.update(&job_ids, statuses),
+            Err(err) => warn!("Unable to refresh the job statuses: {}", err),
+        }
+    }
+}
+
+/// Load the status of a job from Redis.
+async fn load_status(job_id: usize, mut con: ConnectionManager) -> RsrqResult<JobStatus> {
+    Job::get_status(job_id, &mut con).await
+}
+
+/// Each line sent by the client is a Job ID, the response is its Snakemake status (or an error).
+/// Jobs that are not in the cache are loaded with the function (i.e. from Redis).
+async fn handle_client<L, F>(stream: UnixStream, cache: SharedCache, load: L)
+where
+    L: Fn(usize) -> F,
+    F: Future<Output = RsrqResult<JobStatus>>,
+{
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match lookup_status(&line, &cache, &load).await {
+            Ok(status) => SnakemakeStatus::from_job_status(status).to_string(),
+            Err(err) => format!("{}{}", ERROR_PREFIX, err),
+        };
+        if writer.write_all(format!("{}\n", response).as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Jobs are only loaded from Redis the first time they are checked, they are kept up to date by the refresh.
+async fn lookup_status<L, F>(line: &str, cache: &SharedCache, load: &L) -> RsrqResult<JobStatus>
+where
+    L: Fn(usize) -> F,
+    F: Future<Output = RsrqResult<JobStatus>>,
+{
+    let job_id: usize = parse_string(line.trim())?;
+    if let Some(status) = cache.lock().await.get(job_id) {
+        return Ok(status);
+    }
+    let status = load(job_id).await?;
+    cache.lock().await.insert(job_id, status);
+    Ok(status)
+}
+
+/// Ask the sidecar listening on the socket for the status of a job.
+pub async fn query_sidecar(socket: &str, job_id: usize) -> RsrqResult<SnakemakeStatus> {
+    let query = async {
+        let stream = UnixStream::connect(socket).await?;
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(format!("{}\n", job_id).as_bytes()).await?;
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await?;
+        Ok::<String, std::io::Error>(line.trim().to_string())
+    };
+    let response = tokio::time::timeout(Duration::from_secs(QUERY_TIMEOUT_SECS), query).await
+        .map_err(|_| RsrqError::GeneralError("Timed out waiting for the sidecar.".to_string()))?
+        .map_err(RsrqError::IOError)?;
+    match response.strip_prefix(ERROR_PREFIX) {
+        Some(err) => Err(RsrqError::GeneralError(err.to_string())),
+        None => SnakemakeStatus::from_string(&response),
+    }
+}
+
+#[tokio::test]
+async fn test_query_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sidecar.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+
+    // Job 1 is cached, job 2 is loaded (then cached), and job 3 does not exist
+    let cache: SharedCache = Arc::default();
+    cache.lock().await.insert(1, JobStatus::Finished);
+    let server = {
+        let cache = cache.clone();
+        tokio::spawn(async move {
+            for _ in 0..4 {
+                let (stream, _) = listener.accept().await.unwrap();
+                handle_client(stream, cache.clone(), |job_id| async move {
+                    match job_id {
+                        2 => Ok(JobStatus::Running),
+                        _ => Err(RsrqError::JobNotFound(job_id)),
+                    }
+                }).await;
+            }
+        })
+    };
+
+    let socket = path.to_str().unwrap();
+    assert!(matches!(query_sidecar(socket, 1).await.unwrap(), SnakemakeStatus::Success));
+    assert!(matches!(query_sidecar(socket, 2).await.unwrap(), SnakemakeStatus::Running));
+    assert_eq!(cache.lock().await.get(2), Some(JobStatus::Running));
+    assert!(query_sidecar(socket, 3).await.is_err());
+    assert_eq!(cache.lock().await.get(3), None);
+
+    // Lines that are not Job IDs are errors
+    let stream = UnixStream::connect(socket).await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(b"abc\n").await.unwrap();
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await.unwrap();
+    assert!(line.starts_with(ERROR_PREFIX));
+    drop(writer);
+    server.await.unwrap();
+
+    // Nothing is listening
+    assert!(query_sidecar(socket, 1).await.is_err());
+}
@@ -1,12 +1,14 @@
+use std::env;
 use std::fmt;
-use redis::Commands;
 
+use log::debug;
+
+use crate::client::RsrqClient;
+use crate::command::snakemake::sidecar::query_sidecar;
+use crate::config::SNAKEMAKE_SIDECAR_ENV;
 use crate::model::error::RsrqError;
-use crate::model::job::rsrq_job::Job;
-use crate::model::job::key::JobKey;
 use crate::model::job::status::JobStatus;
 use crate::model::types::RsrqResult;
-use crate::util::redis::redis_conn;
 
 pub enum SnakemakeStatus {
     Running,
@@ -15,6 +17,15 @@ pub enum SnakemakeStatus {
 }
 
 impl SnakemakeStatus {
+    pub fn from_string(value: &str) -> RsrqResult<SnakemakeStatus> {
+        match value {
+            "running" => Ok(SnakemakeStatus::Running),
+            "failed" => Ok(SnakemakeStatus::Failed),
+            "success" => Ok(SnakemakeStatus::Success),
+            _ => Err(RsrqError::ParserError(format!("Invalid Snakemake status: {}", value))),
+        }
+    }
+
     pub fn from_job_status(status: JobStatus) -> SnakemakeStatus {
         match status {
             JobStatus::Queued => SnakemakeStatus::Running,
@@ -22,6 +33,10 @@ impl SnakemakeStatus {
             JobStatus::Finished => SnakemakeStatus::Success,
             JobStatus::Failed => SnakemakeStatus::Failed,
             JobStatus::Cancelled => SnakemakeStatus::Failed,
+            JobStatus::TimedOut => SnakemakeStatus::Failed,
+            JobStatus::Delayed => SnakemakeStatus::Running,
+            JobStatus::Deferred => SnakemakeStatus::Running,
+            JobStatus::Scheduled => SnakemakeStatus::Running,
         }
     }
 }
@@ -37,19 +52,22 @@ impl fmt::Display for SnakemakeStatus {
 }
 
 
-pub fn snakemake_status(job_id: usize) -> RsrqResult<SnakemakeStatus> {
-    let mut con = redis_conn()?;
-
-    let job_key = Job::get_redis_key(job_id);
-    let result: Option<JobStatus> = con.hget(job_key, JobKey::Status).map_err(RsrqError::RedisOpError)?;
-
-    if let Some(status) = result {
-        let snakemake_status = SnakemakeStatus::from_job_status(status);
-        println!("{}", snakemake_status);
-        Ok(snakemake_status)
-    } else {
-        Err(RsrqError::JobNotFound(job_id))
+pub async fn snakemake_status(job_id: usize) -> RsrqResult<SnakemakeStatus> {
+    // Ask the sidecar (if Snakemake started one) to avoid connecting to Redis for every check
+    if let Ok(socket) = env::var(SNAKEMAKE_SIDECAR_ENV) {
+        match query_sidecar(&socket, job_id).await {
+            Ok(snakemake_status) => {
+                println!("{}", snakemake_status);
+                return Ok(snakemake_status);
+            }
+            Err(err) => debug!("Unable to query the sidecar, checking Redis: {}", err),
+        }
     }
-}
 
+    let mut client = RsrqClient::new().await?;
 
+    let status = client.job_status(job_id).await?;
+    let snakemake_status = SnakemakeStatus::from_job_status(status);
+    println!("{}", snakemake_status);
+    Ok(snakemake_status)
+}
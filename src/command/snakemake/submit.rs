@@ -3,10 +3,10 @@ use std::io::{BufRead, BufReader};
 
 use serde_json::Value;
 
+use crate::client::RsrqClient;
 use crate::model::error::RsrqError;
-use crate::model::job::rsrq_job::Job;
+use crate::model::snakemake::properties::SnakemakeProperties;
 use crate::model::types::RsrqResult;
-use crate::util::redis::redis_con_manager;
 
 fn read_properties_from_jobscript(path: &str) -> RsrqResult<Value> {
     let file = File::open(path).map_err(RsrqError::FileNotFound)?;
@@ -24,20 +24,16 @@ fn read_properties_from_jobscript(path: &str) -> RsrqResult<Value> {
 pub async fn snakemake_submit(path: &str) -> Result<(), RsrqError> {
 
     // Parse the properties from the file
-    let properties = read_properties_from_jobscript(path)?;
+    let properties = SnakemakeProperties::from_json(&read_properties_from_jobscript(path)?)?;
 
     // Get the queue from the properties (if none are provided, then use default)
-    let property_queue = &properties["resources"]["queue"];
-    let queue = match &properties["resources"]["queue"] {
-        Value::Null => "default".to_string(),
-        _ => property_queue.as_str().unwrap_or("default").to_string(),
-    };
+    let queue = properties.queue.clone().unwrap_or("default".to_string());
 
     // Connect to redis
-    let mut con = redis_con_manager().await?;
+    let mut client = RsrqClient::new().await?;
 
-    // Create the job
-    let job = Job::new(&queue, path, &mut con).await?;
+    // Create the job, the threads, memory, and runtime are used for scheduling, and the rule is kept as metadata
+    let job = client.enqueue_with_options(&queue, path, &properties.to_options()).await?;
 
     // Display the job id to the user
     println!("{}", job.id);
@@ -1,152 +1,113 @@
-use std::collections::HashMap;
 use log::info;
-use redis::aio::ConnectionManager;
-use redis::AsyncCommands;
-use crate::model::error::RsrqError;
-use crate::model::job::key::JobKey;
-use crate::model::job::rsrq_job::Job;
-use crate::model::job::status::JobStatus;
-use crate::model::queue::queue_type::QueueType;
-use crate::model::queue::rsrq_queue::Queue;
-use crate::model::types::RsrqResult;
-use crate::util::redis::redis_con_manager;
-
-
-async fn get_queue_names(con: &mut ConnectionManager) -> RsrqResult<Vec<Queue>> {
-    let mut out = Vec::new();
-    let mut keys: redis::AsyncIter<String> = con.scan_match("rsrq:*").await.map_err(RsrqError::RedisOpError)?;
-    while let Some(key) = keys.next_item().await {
-        let queue = Queue::from_key(&key);
-        if let Ok(queue) = queue {
-            out.push(queue);
-        }
-    }
-    Ok(out)
-}
+use serde_json::{json, Value};
 
-struct MinimalJob {
-    status: JobStatus,
-    queue: String,
-}
+use crate::client::RsrqClient;
+use crate::model::format::{print_tsv, OutputFormat};
+use crate::model::process::rsrq_process::Process;
+use crate::model::queue::queue_info::QueueInfo;
+use crate::model::queue::status_group::StatusGroup;
+use crate::model::types::RsrqResult;
+use crate::util::time::get_timestamp_s;
 
-pub async fn check_status(queue_name: &Option<String>) -> RsrqResult<()> {
-    let mut con = redis_con_manager().await?;
+/// If an array is given, only the job counts of that array are shown.
+pub async fn check_status(queue_name: &Option<String>, format: OutputFormat, group: StatusGroup, array: Option<usize>) -> RsrqResult<()> {
+    let mut client = RsrqClient::new().await?;
 
-    // Obtain either all queues, or those belonging to the specified
-    let queues = match queue_name {
-        None => {
-            get_queue_names(&mut con).await?
-        }
-        Some(queue) => {
-            let mut out = Vec::new();
-            for queue_type in QueueType::get_types() {
-                let cur_queue = Queue::new(queue_type, queue);
-                out.push(cur_queue);
-            }
-            out
+    // Check that the array exists, its jobs are counted together
+    let group = match array {
+        Some(array_id) => {
+            client.array_job_ids(array_id).await?;
+            StatusGroup::Array
         }
+        None => group,
     };
 
-    // Iterate over each queue and collect the job ids
-    let job_ids: Vec<Vec<usize>> = {
-        let mut pipe = redis::pipe();
-        for queue in &queues {
-            match queue.q_type {
-                QueueType::Queued => pipe.lrange(&queue.key, 0, -1),
-                QueueType::Running => pipe.lrange(&queue.key, 0, -1),
-                QueueType::Finished => pipe.smembers(&queue.key),
-                QueueType::Failed => pipe.smembers(&queue.key),
-            };
-        }
-        pipe.query_async(&mut con).await.map_err(RsrqError::RedisOpError)?
+    // Collect the job counts for each queue, Snakemake rule, or array (sorted by name)
+    let mut queue_infos = match group {
+        StatusGroup::Queue => client.status(queue_name.as_deref()).await?,
+        StatusGroup::Rule => client.status_by_rule(queue_name.as_deref()).await?,
+        StatusGroup::Array => client.status_by_array(queue_name.as_deref()).await?,
+    };
+    if let Some(array_id) = array {
+        queue_infos.retain(|x| x.name == array_id.to_string());
+    }
+    let group_key = match group {
+        StatusGroup::Queue => "queues",
+        StatusGroup::Rule => "rules",
+        StatusGroup::Array => "arrays",
     };
 
-    // Flatten the job ids into a single vector
-    let job_ids: Vec<usize> = job_ids.into_iter().flatten().collect();
+    // Collect the workers processing these queues
+    let workers: Vec<Process> = client.list_workers().await?
+        .into_iter()
+        .filter(|x| queue_name.as_ref().map_or(true, |name| x.queues.contains(name)))
+        .collect();
+    let now = get_timestamp_s()?;
 
-    // Iterate over each job id and print the job details
-    let keys_to_get = vec![JobKey::Status, JobKey::Queue];
-    let job_details: Vec<String> = {
-        let mut pipe = redis::pipe();
-        for job_id in job_ids {
-            let job_key = Job::get_redis_key(job_id);
-            for key_to_get in &keys_to_get {
-                pipe.hget(&job_key, key_to_get);
-            }
+    match format {
+        OutputFormat::Human => print_human(&queue_infos, &workers, group, now),
+        OutputFormat::Json => {
+            let out = json!({
+                group_key: queue_infos.iter().map(|x| x.to_json()).collect::<Vec<Value>>(),
+                "workers": workers.iter().map(|x| x.to_json(now)).collect::<Vec<Value>>(),
+            });
+            println!("{}", out);
         }
-        pipe.query_async(&mut con).await.map_err(RsrqError::RedisOpError)?
-    };
-
-    // Associate the retrieved values with each job
-    let jobs: Vec<MinimalJob> = {
-        let mut out = Vec::new();
-        let mut job_details_iter = job_details.into_iter();
-        while let Some(status) = job_details_iter.next() {
-            let queue = job_details_iter.next().unwrap();
-            let status = JobStatus::from_string(&status)?;
-            let job = MinimalJob {
-                status,
-                queue,
-            };
-            out.push(job);
+        OutputFormat::Tsv => {
+            print_tsv(QueueInfo::TSV_COLUMNS, &queue_infos.iter().map(|x| x.to_json()).collect::<Vec<Value>>());
+            println!();
+            print_tsv(Process::TSV_COLUMNS, &workers.iter().map(|x| x.to_json(now)).collect::<Vec<Value>>());
         }
-        out
-    };
-
-    let mut queue_infos: HashMap<String, QueueInfo> = HashMap::new();
-    for job in jobs {
-        let queue_info = queue_infos.entry(job.queue.clone()).or_insert(QueueInfo::new(&job.queue));
-        match job.status {
-            JobStatus::Queued => queue_info.n_queued += 1,
-            JobStatus::Running => queue_info.n_running += 1,
-            JobStatus::Finished => queue_info.n_finished += 1,
-            JobStatus::Failed => queue_info.n_failed += 1,
-            JobStatus::Cancelled => queue_info.n_cancelled += 1,
-        };
     }
+    Ok(())
+}
 
-    // Get the sorted keys
-    let queue_keys = {
-        let mut out = queue_infos.keys().map(|x| x.to_string()).collect::<Vec<String>>();
-        out.sort();
-        out
+fn print_human(queue_infos: &[QueueInfo], workers: &[Process], group: StatusGroup, now: u64) {
+    let label = match group {
+        StatusGroup::Queue => "Queue",
+        StatusGroup::Rule => "Rule",
+        StatusGroup::Array => "Array",
     };
-
-    // Iterate over each value
-    for queue_key in &queue_keys {
-        let queue_info = queue_infos.get(queue_key).unwrap();
+    for queue_info in queue_infos {
+        // Only show the queued jobs by priority if priorities are used
+        let by_priority = if queue_info.n_queued_by_priority.keys().any(|x| *x != 0) {
+            format!(" [By priority {}]", queue_info.format_queued_by_priority())
+        } else {
+            "".to_string()
+        };
+        let paused = if queue_info.paused { " [Paused]" } else { "" };
         info!(
-            "Queue: {:<10} [Queued {:<5}] [Running {:<5}] [Finished {:<5}] [Failed {:<5}] [Cancelled {:<5}]",
+            "{}: {:<10}{} [Scheduled {:<5}] [Deferred {:<5}] [Queued {:<5}] [Delayed {:<5}] [Running {:<5}] [Finished {:<5}] [Failed {:<5}] [Cancelled {:<5}] [Timed out {:<5}]{}",
+            label,
             queue_info.name,
+            paused,
+            queue_info.n_scheduled,
+            queue_info.n_deferred,
             queue_info.n_queued,
+            queue_info.n_delayed,
             queue_info.n_running,
             queue_info.n_finished,
             queue_info.n_failed,
-            queue_info.n_cancelled
+            queue_info.n_cancelled,
+            queue_info.n_timed_out,
+            by_priority
         );
     }
 
-    Ok(())
-}
-
-struct QueueInfo {
-    name: String,
-    n_finished: usize,
-    n_failed: usize,
-    n_queued: usize,
-    n_running: usize,
-    n_cancelled: usize,
-}
-
-impl QueueInfo {
-    pub fn new(name: &str) -> QueueInfo {
-        QueueInfo {
-            name: name.to_string(),
-            n_finished: 0,
-            n_failed: 0,
-            n_queued: 0,
-            n_running: 0,
-            n_cancelled: 0,
-        }
+    for worker in workers {
+        let fmt_opt = |x: Option<String>| x.unwrap_or("-".to_string());
+        info!(
+            "Worker: {:<5} {}:{} [Queue {}] [State {}] [Running {}/{}] [Heartbeat {}s ago] [Max jobs {}] [Max duration {}]",
+            worker.id,
+            worker.hostname,
+            worker.pid,
+            worker.queues.join(","),
+            worker.state,
+            worker.n_running,
+            worker.workers,
+            now.saturating_sub(worker.last_heartbeat),
+            fmt_opt(worker.max_jobs.map(|x| x.to_string())),
+            fmt_opt(worker.max_duration_sec.map(|x| format!("{}s", x))),
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,20 @@
+use log::{info, warn};
+
+use crate::client::RsrqClient;
+use crate::model::cli::WorkerCtlCommands;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::format_id_list;
+
+/// Send a control command to a worker process (by id), or all worker processes on a host (by hostname).
+pub async fn worker_ctl(target: &str, action: &WorkerCtlCommands) -> RsrqResult<Vec<usize>> {
+    let mut client = RsrqClient::new().await?;
+
+    let control = action.to_control();
+    let proc_ids = client.control_workers(target, control).await?;
+    if proc_ids.is_empty() {
+        warn!("No worker processes matched: {}", target);
+    } else {
+        info!("Sent {} to {} worker processes: {}", control, proc_ids.len(), format_id_list(&proc_ids));
+    }
+    Ok(proc_ids)
+}
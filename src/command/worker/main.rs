@@ -1,43 +1,97 @@
-use log::{info, warn};
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+use nix::sys::signal::Signal;
+use redis::aio::ConnectionManager;
 use tokio::sync::mpsc;
 
-use crate::command::worker::util::{create_wake_thread, get_max_runtime, parse_num_workers};
+use crate::command::worker::util::{create_control_thread, create_notify_thread, create_timer_thread, parse_num_workers};
+use crate::model::cli::WorkerArgs;
+use crate::model::error::RsrqError;
+use crate::model::process::orphan_policy::OrphanPolicy;
+use crate::model::process::reaper::{reap_stale_processes, release_jobs};
 use crate::model::process::rsrq_process::Process;
+use crate::model::process::rsrq_process_state::ProcessState;
+use crate::model::schedule::rsrq_schedule::enqueue_due_schedules;
 use crate::model::shutdown_handler::ShutdownHandler;
 use crate::model::types::{RsrqResult, WorkerMsgRec, WorkerMsgSend};
+use crate::model::worker::children::parse_signal;
+use crate::model::worker::control::WorkerControl;
+use crate::model::worker::job_defaults::{parse_queue_modes, JobDefaults};
 use crate::model::worker::message::{WorkerMessage, WorkerMessageReason};
 use crate::model::worker::pool::WorkerPool;
+use crate::model::worker::queue_selector::parse_queue_list;
+use crate::model::worker::resources::ResourcePool;
+use crate::util::parsing::parse_memory_mb;
 use crate::util::redis::redis_con_manager;
+use crate::util::system::get_absolute_path;
+use crate::util::time::parse_duration;
+
+/// How often to check for jobs without being notified, in case a notification was missed (e.g. while reconnecting).
+const CLAIM_SAFETY_NET_MS: u64 = 30_000;
 
 /// The main entry point for running the worker.
-pub async fn run_workers(queue: &str, workers: u16, max_secs: &Option<String>, max_jobs: Option<u32>, burst: bool, poll: u64) -> RsrqResult<()> {
+pub async fn run_workers(args: &WorkerArgs) -> RsrqResult<()> {
+    let queue = args.queue.as_deref().ok_or(RsrqError::ParserError("A queue is required.".to_string()))?;
+    let queues = parse_queue_list(queue)?;
+    let queue_names: Vec<String> = queues.iter().map(|x| x.name.clone()).collect();
+    let (workers, max_jobs, burst, poll) = (args.workers, args.max_jobs, args.burst, args.poll);
 
-    // Parse arguments
+    // Parse arguments, the number of workers defaults to the number of CPUs (if they are limited)
+    let mem_mb = args.mem.as_deref().map(parse_memory_mb).transpose()?;
+    let resources = ResourcePool::new(args.cpus, mem_mb);
+    let workers = workers.unwrap_or(args.cpus.map_or(1, |x| x.min(u16::MAX as u32) as u16));
     let max_workers = parse_num_workers(workers, max_jobs);
-    let max_runtime_secs = if let Some(max_secs) = max_secs {
-        Some(get_max_runtime(max_secs)?)
+    let max_runtime_secs = if let Some(max_secs) = &args.max_duration {
+        Some(parse_duration(max_secs)?)
+    } else {
+        None
+    };
+    let timeout_secs = if let Some(timeout) = &args.timeout {
+        Some(parse_duration(timeout)?)
     } else {
         None
     };
+    let defaults = JobDefaults {
+        timeout: timeout_secs,
+        mode: args.mode,
+        queue_modes: parse_queue_modes(&args.queue_mode, &queue_names)?,
+        shell: args.shell.clone(),
+        log_dir: args.log_dir.as_ref().map(|x| get_absolute_path(x)).transpose()?,
+        log_template: args.log_template.clone(),
+        log_tail: args.log_tail,
+    };
+    let stale_after_secs = parse_duration(&args.stale_after)?;
+    let mut last_reap: Option<Instant> = None;
+    let heartbeat_ms = parse_duration(&args.heartbeat)?.max(1) * 1000;
+    let shutdown_signal = parse_signal(&args.shutdown_signal)?;
+    let grace_period_secs = parse_duration(&args.grace_period)?;
+    let mut received_signal = false;
 
     // Display a message that the process is about to start
+    let queue_str = queue_names.join(", ");
     if max_workers == 1 {
-        info!("Starting 1 worker to process queue: {}", queue);
+        info!("Starting 1 worker to process queue: {}", queue_str);
     } else {
-        info!("Starting {} workers to process queue: {}", max_workers, queue);
+        info!("Starting {} workers to process queue: {}", max_workers, queue_str);
+    }
+    if resources.is_limited() {
+        let cpus = resources.cpus.map_or("unlimited".to_string(), |x| x.to_string());
+        let mem = resources.mem_mb.map_or("unlimited".to_string(), |x| format!("{} MB", x));
+        info!("Running jobs are limited to {} CPU(s) and {} of memory.", cpus, mem);
     }
 
     // Connect to the database
     let mut con = redis_con_manager().await?;
 
     // Register the spawning of this process
-    let mut proc = Process::new(queue, max_workers, max_runtime_secs, max_jobs, burst, poll, &mut con).await?;
+    let mut proc = Process::new(&queue_names, max_workers, max_runtime_secs, max_jobs, burst, poll, heartbeat_ms, &mut con).await?;
 
     // Finished jobs and exit handlers will communicate with the main loop via this channel
     let (tx, mut rx): (WorkerMsgSend, WorkerMsgRec) = mpsc::channel(workers as usize * 10);
 
     // Create a worker pool to start/end jobs
-    let mut pool = WorkerPool::new(proc.id, queue, max_jobs, max_runtime_secs, max_workers, poll, burst, &tx, &con).await?;
+    let mut pool = WorkerPool::new(proc.id, &queues, args.queue_order, max_jobs, max_runtime_secs, max_workers, resources, burst, defaults, &tx, &con).await?;
 
     /*
     The ShutdownHandler is responsible for stopping the program by sending a message to the channel.
@@ -50,10 +104,22 @@ pub async fn run_workers(queue: &str, workers: u16, max_secs: &Option<String>, m
         shutdown_thread.start_shutdown_timer(max_runtime_secs);
     }
 
-    // Asynchronously send a message to trigger a loop of the main loop every poll milliseconds
-    let wake_thread = create_wake_thread(&tx, poll);
+    // Wake the main loop as soon as jobs are enqueued, and move delayed or scheduled jobs that are now due into their
+    // queue every poll milliseconds (which wakes the main loop in the same way). Jobs are also checked for on a slow
+    // interval, in case a notification was missed.
+    let notify_thread = create_notify_thread(&pool.queues, &tx);
+    let promote_thread = create_timer_thread(&tx, poll, WorkerMessage::promote_due);
+    let wake_thread = create_timer_thread(&tx, CLAIM_SAFETY_NET_MS, WorkerMessage::check_for_jobs);
+
+    // Cancelled jobs and heartbeats are checked on their own timers
+    let cancel_thread = create_timer_thread(&tx, poll, WorkerMessage::check_cancelled);
+    let heartbeat_thread = create_timer_thread(&tx, heartbeat_ms, WorkerMessage::heartbeat);
+
+    // Control commands sent by other hosts (i.e. drain, stop, resize) are forwarded to the main loop
+    let control_thread = create_control_thread(proc.id, &tx, heartbeat_ms, &con);
 
-    // Instantaneously the main loop by sending a message to check for jobs
+    // Instantaneously the main loop by promoting due jobs, then sending a message to check for jobs
+    pool.promote_due().await?;
     let _ = tx.send(WorkerMessage::check_for_jobs()).await;
 
     // Actions to take when a message is received (main loop)
@@ -63,10 +129,10 @@ pub async fn run_workers(queue: &str, workers: u16, max_secs: &Option<String>, m
         match message.reason {
 
             // Exit conditions
-            WorkerMessageReason::Sigint => {
+            WorkerMessageReason::Sigint | WorkerMessageReason::Sigterm | WorkerMessageReason::Sighup => {
                 pool.progress.finish_and_clear();
-                warn!("Terminating program, the remaining {} jobs will be marked as failed.", pool.futures.len());
-                // Note: The jobs are marked as failed by the worker thread
+                warn!("Received {:?}, shutting down {} running jobs.", message.reason, pool.futures.len());
+                received_signal = true;
                 break;
             }
             WorkerMessageReason::TimeExceeded => {
@@ -85,7 +151,46 @@ pub async fn run_workers(queue: &str, workers: u16, max_secs: &Option<String>, m
                 break;
             }
 
-            // Sent by the wake thread or finished jobs
+            // Sent by "rsrq worker ctl"
+            WorkerMessageReason::Control => {
+                match message.control {
+                    Some(WorkerControl::Drain) => {
+                        warn!("Draining, waiting for {} running jobs to finish.", pool.futures.len());
+                        pool.draining = true;
+                        proc.state = ProcessState::Draining;
+                        proc.push(&mut con).await?;
+                        if pool.futures.is_empty() {
+                            pool.progress.finish_and_clear();
+                            break;
+                        }
+                    }
+                    Some(WorkerControl::Stop) => {
+                        pool.progress.finish_and_clear();
+                        warn!("Stopping, the remaining {} jobs will be released ({}).", pool.futures.len(), args.on_shutdown);
+                        proc.state = ProcessState::Stopping;
+                        proc.push(&mut con).await?;
+                        let job_ids = pool.abort_all();
+                        release_jobs(proc.id, &job_ids, args.on_shutdown, "The worker was stopped.", &mut con).await?;
+                        break;
+                    }
+                    Some(WorkerControl::Resize(n_workers)) => {
+                        info!("Resizing from {} to {} workers.", pool.max_workers, n_workers);
+                        pool.resize(n_workers);
+                        proc.workers = n_workers;
+                        proc.push(&mut con).await?;
+                    }
+                    None => {}
+                }
+            }
+
+            // Jobs that are due are moved into their queue, the notifications this sends wake the main loop
+            WorkerMessageReason::PromoteDue => {
+                if let Err(e) = pool.promote_due().await {
+                    warn!("Unable to move due jobs into their queue: {}", e);
+                }
+            }
+
+            // Sent by the wake and notify threads, or finished jobs
             WorkerMessageReason::CheckForJobs => {
 
                 // This message contains a job id, it was therefore sent by a job that has finished
@@ -93,23 +198,77 @@ pub async fn run_workers(queue: &str, workers: u16, max_secs: &Option<String>, m
                     pool.remove_job(finished_job_id);
                 }
 
-                // Jobs that were updated via another process (i.e. snakemake) need to be cancelled
-                pool.abort_cancelled().await?;
+                // Exit once the last running job has finished
+                if pool.draining && pool.futures.is_empty() {
+                    pool.progress.finish_and_clear();
+                    info!("Finished draining, exiting.");
+                    break;
+                }
 
                 // Start new jobs if possible
                 pool.maybe_start_new_jobs().await?;
+            }
 
-                // Update the last heartbeat from this process
-                proc.update_running(pool.futures.len(), &mut con).await?;
+            // Jobs that were updated via another process (i.e. snakemake) need to be cancelled
+            WorkerMessageReason::CheckCancelled => {
+                if !pool.futures.is_empty() {
+                    pool.abort_cancelled().await?;
+                }
+            }
+
+            WorkerMessageReason::Heartbeat => {
+                // Update the last heartbeat from this process, if it was reaped its jobs have already been released
+                match proc.update_running(pool.futures.len(), &mut con).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        pool.progress.finish_and_clear();
+                        let job_ids = pool.abort_all();
+                        error!("This process was reaped by another worker, killing {} running jobs (they were already released) and exiting.", job_ids.len());
+                        break;
+                    }
+                    Err(e) => warn!("Unable to send a heartbeat: {}", e),
+                }
+
+                // Release the jobs of any workers that have died (checked once per stale interval)
+                if last_reap.map_or(true, |x| x.elapsed().as_secs() >= stale_after_secs) {
+                    last_reap = Some(Instant::now());
+                    match reap_stale_processes(stale_after_secs, args.orphan_policy, &mut con).await {
+                        Ok(reaped) if !reaped.proc_ids.is_empty() => {
+                            warn!("Reaped {} dead process(es), re-queued {} and failed {} job(s).", reaped.proc_ids.len(), reaped.requeued.len(), reaped.failed.len());
+                        }
+                        Ok(_) => {}
+                        Err(e) => warn!("Unable to reap dead processes: {}", e),
+                    }
+                }
+
+                // Enqueue jobs for recurring schedules that are due (a lock ensures only one worker enqueues each)
+                match enqueue_due_schedules(&mut con).await {
+                    Ok(jobs) => {
+                        for job in jobs {
+                            info!("Enqueued scheduled job {} to queue: {}", job.id, job.queue);
+                        }
+                    }
+                    Err(e) => warn!("Unable to enqueue recurring schedules: {}", e),
+                }
             }
         }
     }
 
     // Terminate asynchronous threads
+    notify_thread.abort();
+    promote_thread.abort();
     wake_thread.abort();
+    cancel_thread.abort();
+    heartbeat_thread.abort();
+    control_thread.abort();
+
+    // Give the running jobs a chance to exit, the shutdown thread is kept alive to receive a second signal
+    if received_signal {
+        shutdown_running_jobs(&mut proc, &mut pool, &mut rx, shutdown_signal, grace_period_secs, args.on_shutdown, &mut con).await?;
+    }
     shutdown_thread.abort();
 
-    // Await any jobs that may still be running (not the case for SIGINT / time exceeded)
+    // Await any jobs that may still be running (i.e. after the maximum time or number of jobs is reached)
     for (_, future) in pool.futures {
         let _ = future.await;
     }
@@ -118,4 +277,58 @@ pub async fn run_workers(queue: &str, workers: u16, max_secs: &Option<String>, m
     proc.delete(&mut con).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Forward the signal to the running jobs and wait for them to exit, jobs that are still running after the grace period
+/// (or a second signal is received) are killed and released according to the policy.
+async fn shutdown_running_jobs(proc: &mut Process, pool: &mut WorkerPool, rx: &mut WorkerMsgRec, signal: Signal, grace_period_secs: u64, policy: OrphanPolicy, con: &mut ConnectionManager) -> RsrqResult<()> {
+    proc.state = ProcessState::Stopping;
+    proc.push(con).await?;
+    if pool.futures.is_empty() {
+        return Ok(());
+    }
+
+    // Jobs that exit without succeeding from now on are released by the policy, rather than failed or retried
+    let job_ids: Vec<usize> = pool.futures.keys().copied().collect();
+    pool.children.set_shutting_down();
+    let n_signalled = pool.children.signal_all(signal);
+    warn!("Sent {} to {} running jobs, waiting up to {} seconds for them to exit.", signal, n_signalled, grace_period_secs);
+
+    let deadline = Instant::now() + Duration::from_secs(grace_period_secs);
+    let interval = Duration::from_millis(proc.poll_ms.clamp(100, 1000));
+    while !pool.futures.is_empty() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+
+        let second_signal = std::iter::from_fn(|| rx.try_recv().ok())
+            .any(|x| matches!(x.reason, WorkerMessageReason::Sigint | WorkerMessageReason::Sigterm | WorkerMessageReason::Sighup));
+        if second_signal {
+            warn!("Received a second signal, skipping the grace period.");
+            break;
+        }
+
+        tokio::time::sleep(interval.min(deadline - now)).await;
+        pool.futures.retain(|_, future| !future.is_finished());
+
+        // Keep sending heartbeats so that the jobs are not released by another worker
+        match proc.update_running(pool.futures.len(), con).await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("This process was reaped by another worker, skipping the grace period.");
+                break;
+            }
+            Err(e) => warn!("Unable to send a heartbeat: {}", e),
+        }
+    }
+
+    // The commands of the remaining jobs are killed when their futures are aborted, the jobs that exited during
+    // the grace period are still running in the database (those that finished were removed from the running queue)
+    let n_killed = pool.abort_all().len();
+    let (requeued, failed) = release_jobs(proc.id, &job_ids, policy, "The worker was shut down before the job finished.", con).await?;
+    if !requeued.is_empty() || !failed.is_empty() {
+        warn!("Killed {} unfinished jobs, re-queued {} and failed {} interrupted jobs.", n_killed, requeued.len(), failed.len());
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
 pub mod main;
 pub mod util;
 pub mod run_on_job;
+pub mod ctl;
@@ -1,64 +1,112 @@
-use log::debug;
+use log::{debug, info};
 use redis::aio::ConnectionManager;
 
 use crate::command::worker::util::update_redis_start_job;
+use crate::config::JOB_ID_ENV;
 use crate::model::command::RsrqCommand;
 use crate::model::error::RsrqError;
+use crate::model::job::attempt::JobAttempt;
+use crate::model::job::dependency::resolve_dependents;
 use crate::model::job::key::JobKey;
 use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+use crate::model::output::OutputFiles;
 use crate::model::queue::queue_type::QueueType;
 use crate::model::queue::rsrq_queue::Queue;
 use crate::model::types::RsrqResult;
+use crate::model::worker::children::ChildProcesses;
+use crate::model::worker::job_defaults::JobDefaults;
 use crate::model::worker::result::WorkerResult;
 use crate::util::time::get_timestamp_s;
 
 /// This is the main method called by the worker to wrap all logic.
-pub async fn worker_async_on_job_id(proc_id: usize, job_id: usize, queue_name: String, con: &mut ConnectionManager) -> RsrqResult<()> {
+pub async fn worker_async_on_job_id(proc_id: usize, job_id: usize, queue_name: String, defaults: &JobDefaults, children: &ChildProcesses, con: &mut ConnectionManager) -> RsrqResult<()> {
     // Register the worker class
     debug!("Process {} is now listening on {}", proc_id, &queue_name);
 
     let job = Job::load(job_id, con).await?;
     debug!("Process {} has obtained job {}", proc_id, job.id);
-    process_new_job(proc_id, &queue_name, &job, con).await?;
+    process_new_job(proc_id, &queue_name, &job, defaults, children, con).await?;
 
     debug!("Process {} is now done on {}", proc_id, &queue_name);
     Ok(())
 }
 
 
-/// This is where the thread calls the command.
-pub async fn process_new_job(proc_id: usize, queue_name: &str, job: &Job, con: &mut ConnectionManager) -> RsrqResult<()> {
-    let start_ts = get_timestamp_s()?.to_string();
+/// This is where the thread calls the command, the job settings take precedence over the worker defaults.
+pub async fn process_new_job(proc_id: usize, queue_name: &str, job: &Job, defaults: &JobDefaults, children: &ChildProcesses, con: &mut ConnectionManager) -> RsrqResult<()> {
+    let start_ts = get_timestamp_s()?;
+    let attempt = job.attempt + 1;
+
+    // Output is written to files (if enabled), otherwise it is kept in memory
+    let output = defaults.log_dir.as_ref().map(|dir| {
+        OutputFiles::new(dir, &defaults.log_template, job.id, queue_name, attempt, defaults.log_tail)
+    });
 
     // Update the job and worker in the database to be in a running state
-    update_redis_start_job(proc_id, job, &start_ts, con).await?;
+    update_redis_start_job(proc_id, job, &start_ts.to_string(), output.as_ref(), con).await?;
 
     // Run the actual job
-    let command = RsrqCommand::new(&job.cmd);
-    let job_res = match command {
+    let command = RsrqCommand::new(&job.cmd, job.mode.unwrap_or(defaults.mode_for(queue_name)), &defaults.shell);
+    let (job_res, can_retry) = match command {
         // There was no issue parsing the command, run it
         Ok(engine) => {
-            let result = engine.run().await;
-            WorkerResult::from_command(&result)
+            let mut env = job.env.clone();
+            env.insert(JOB_ID_ENV.to_string(), job.id.to_string());
+            let result = engine.with_context(job.cwd.as_deref(), &env).run(job.timeout.or(defaults.timeout), output.as_ref(), Some(children)).await;
+            (WorkerResult::from_command(&result), true)
         }
-        // The command could not be parsed
-        Err(_) => WorkerResult::from_failed()
+        // The command could not be parsed, retrying it would fail in the same way
+        Err(err) => (WorkerResult::from_failed(&err.to_string()), false)
     };
 
+    // Jobs that were interrupted by the worker shutting down are left running, to be released by the shutdown policy
+    if can_retry && interrupted_by_shutdown(&job_res, children) {
+        info!("Job {} exited with code {} while the worker was shutting down, it will be released.", job.id, job_res.exit_code);
+        return Ok(());
+    }
+
     // The job has finished running (or didn't run if the parser failed)
-    let end_ts = get_timestamp_s()?.to_string();
+    let end_ts = get_timestamp_s()?;
+
+    // Keep a record of this attempt
+    let mut attempts = job.attempts.clone();
+    attempts.push(JobAttempt {
+        attempt,
+        status: job_res.job_status,
+        exit_code: job_res.exit_code,
+        stderr: job_res.stderr.clone(),
+        started: start_ts,
+        finished: end_ts,
+        duration_ms: job_res.duration_ms,
+    });
+
+    // Failed attempts are re-queued (possibly after a delay) until no retries remain
+    let is_failure = matches!(job_res.job_status, JobStatus::Failed | JobStatus::TimedOut);
+    let retry_delay = if is_failure && can_retry && attempt <= job.max_retries {
+        let delay = job.retry_backoff.get_delay(job.retry_delay, attempt);
+        info!("Job {} failed (attempt {}/{}), retrying in {} seconds.", job.id, attempt, job.max_retries + 1, delay);
+        Some(delay)
+    } else {
+        None
+    };
+    let (job_status, q_target) = match retry_delay {
+        Some(0) => (JobStatus::Queued, Queue::new(QueueType::Queued, queue_name)),
+        Some(_) => (JobStatus::Delayed, Queue::new(QueueType::Delayed, queue_name)),
+        None => (job_res.job_status, Queue::new(job_res.q_target, queue_name)),
+    };
 
-    // Set the target queues
+    // Set the source queue
     let q_source = Queue::new(QueueType::Running, queue_name);
-    let q_target = Queue::new(job_res.q_target, queue_name);
 
     let job_update_arr = [
-        (JobKey::Status, &job_res.job_status.to_string()),
-        (JobKey::Finished, &end_ts),
-        (JobKey::Stdout, &job_res.stdout),
-        (JobKey::Stderr, &job_res.stderr),
-        (JobKey::DurationMs, &job_res.duration_ms.to_string()),
-        (JobKey::ExitCode, &job_res.exit_code.to_string())
+        (JobKey::Status, job_status.to_string()),
+        (JobKey::Finished, end_ts.to_string()),
+        (JobKey::Stdout, job_res.stdout),
+        (JobKey::Stderr, job_res.stderr),
+        (JobKey::DurationMs, job_res.duration_ms.to_string()),
+        (JobKey::ExitCode, job_res.exit_code.to_string()),
+        (JobKey::Attempts, JobAttempt::to_json_list(&attempts)),
     ];
 
     // Update the database with the job status
@@ -67,10 +115,49 @@ pub async fn process_new_job(proc_id: usize, queue_name: &str, job: &Job, con: &
         pipe.atomic();
         pipe.hset_multiple(&job.key, &job_update_arr);
         q_source.pipe_remove_job_id(job.id, &mut pipe);
-        q_target.pipe_add_job_id(job.id, &mut pipe);
+        q_target.pipe_add_job_id_at(job.id, end_ts + retry_delay.unwrap_or(0), &mut pipe);
         pipe
     };
-    pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+    let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+
+    // Queue (or cancel) any jobs that were waiting on this job
+    if retry_delay.is_none() {
+        resolve_dependents(job.id, job_status, con).await?;
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// True if the command did not succeed after the worker started shutting down (e.g. it exited on the forwarded signal).
+fn interrupted_by_shutdown(result: &WorkerResult, children: &ChildProcesses) -> bool {
+    result.job_status != JobStatus::Finished && children.is_shutting_down()
+}
+
+#[tokio::test]
+async fn test_interrupted_by_shutdown() {
+    use nix::sys::signal::Signal;
+
+    // The command exits on the signal that is forwarded during the grace period
+    let children = ChildProcesses::default();
+    let command = RsrqCommand::shell("trap 'exit 143' TERM; sleep 30 & wait", "/bin/sh").unwrap();
+    let handle = {
+        let children = children.clone();
+        tokio::spawn(async move { command.run(None, None, Some(&children)).await })
+    };
+    // Wait for the command to start (SIGCONT has no effect on a running process), and to set its trap
+    while children.signal_all(Signal::SIGCONT) == 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    children.set_shutting_down();
+    assert_eq!(children.signal_all(Signal::SIGTERM), 1);
+
+    let result = WorkerResult::from_command(&handle.await.unwrap());
+    assert_eq!(result.exit_code, 143);
+    assert!(interrupted_by_shutdown(&result, &children));
+
+    // Jobs that succeed, or fail while the worker is not shutting down, are recorded as usual
+    let finished = RsrqCommand::exec("true").unwrap().run(None, None, Some(&children)).await;
+    assert!(!interrupted_by_shutdown(&WorkerResult::from_command(&finished), &children));
+    assert!(!interrupted_by_shutdown(&result, &ChildProcesses::default()));
+}
@@ -1,7 +1,8 @@
-use lazy_static::lazy_static;
-use log::warn;
+use std::path::PathBuf;
+
+use futures_util::StreamExt;
+use log::{error, warn};
 use redis::aio::ConnectionManager;
-use regex::Regex;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
@@ -9,13 +10,18 @@ use crate::model::error::RsrqError;
 use crate::model::job::key::JobKey;
 use crate::model::job::rsrq_job::Job;
 use crate::model::job::status::JobStatus;
+use crate::model::output::OutputFiles;
+use crate::model::queue::rsrq_queue::Queue;
 use crate::model::types::RsrqResult;
+use crate::model::worker::control::WorkerControl;
 use crate::model::worker::message::WorkerMessage;
+use crate::util::redis::redis_pubsub;
 
-lazy_static! {
-    static ref RE_MAX_DURATION: Regex =  Regex::new(r"(\d+)(\w)").unwrap();
-}
+/// Notifications of new jobs that arrive within this many milliseconds of each other wake the main loop once.
+const NOTIFY_DEBOUNCE_MS: u64 = 20;
 
+/// How long to wait before re-opening the connection used to wait for new jobs.
+const NOTIFY_RECONNECT_SECS: u64 = 5;
 
 /// Parses the worker arguments to ensure that the number of workers is less than the maximum number of iterations (if provided).
 pub fn parse_num_workers(num_workers: u16, max_iter: Option<u32>) -> u32 {
@@ -28,129 +34,84 @@ pub fn parse_num_workers(num_workers: u16, max_iter: Option<u32>) -> u32 {
     num_workers as u32
 }
 
-pub async fn update_redis_start_job(proc_id: usize, job: &Job, start_ts: &str, con: &mut ConnectionManager) -> RsrqResult<()> {
+pub async fn update_redis_start_job(proc_id: usize, job: &Job, start_ts: &str, output: Option<&OutputFiles>, con: &mut ConnectionManager) -> RsrqResult<()> {
+    let path_to_string = |path: &PathBuf| path.to_string_lossy().to_string();
     let job_arr = [
         (JobKey::Status, JobStatus::Running.to_string()),
         (JobKey::Started, start_ts.to_string()),
-        (JobKey::ProcessId, proc_id.to_string())
+        (JobKey::ProcessId, proc_id.to_string()),
+        (JobKey::StdoutPath, output.map(|x| path_to_string(&x.stdout)).unwrap_or_default()),
+        (JobKey::StderrPath, output.map(|x| path_to_string(&x.stderr)).unwrap_or_default()),
     ];
     let mut pipe = redis::pipe();
     pipe.atomic();
     pipe.hset_multiple(&job.key, &job_arr);
-    pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+    pipe.hincr(&job.key, JobKey::Attempt, 1);
+    let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
     Ok(())
 }
 
 
-pub fn create_wake_thread(tx: &mpsc::Sender<WorkerMessage>, poll_ms: u64) -> JoinHandle<()> {
+/// Send a message to the main loop every interval milliseconds (e.g. to check for jobs, or send a heartbeat).
+pub fn create_timer_thread(tx: &mpsc::Sender<WorkerMessage>, interval_ms: u64, message: fn() -> WorkerMessage) -> JoinHandle<()> {
     let tx = tx.clone();
     let thread = tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(poll_ms));
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(interval_ms));
         loop {
             interval.tick().await;
-            let _ = tx.send(WorkerMessage::check_for_jobs()).await;
+            let _ = tx.send(message()).await;
         }
     });
     thread
 }
 
-fn parse_time_unit(unit: &str, num: &str) -> RsrqResult<usize> {
-    let num_res = num.parse::<usize>();
-    if num_res.is_err() {
-        return Err(RsrqError::CmdParserError(format!("Invalid number format: {}", num)));
-    }
-    let num = num_res.unwrap();
-
-    match unit {
-        "h" => Ok(num * 60 * 60),
-        "m" => Ok(num * 60),
-        "s" => Ok(num),
-        _ => Err(RsrqError::CmdParserError(format!("Invalid time unit provided: {}", unit)))
-    }
+/// Wake the main loop as soon as a job is added to one of the queues, the channels are named after the queued keys.
+/// Messages that arrive together are combined into a single wake up, the connection is re-opened if it is lost.
+pub fn create_notify_thread(queues: &[Queue], tx: &mpsc::Sender<WorkerMessage>) -> JoinHandle<()> {
+    let tx = tx.clone();
+    let channels: Vec<String> = queues.iter().map(|x| x.key.clone()).collect();
+    let thread = tokio::spawn(async move {
+        loop {
+            match redis_pubsub(&channels).await {
+                Ok(mut pubsub) => {
+                    // Jobs may have been enqueued while the connection was being opened
+                    let _ = tx.send(WorkerMessage::check_for_jobs()).await;
+                    let mut stream = pubsub.on_message();
+                    while stream.next().await.is_some() {
+                        let _ = tx.send(WorkerMessage::check_for_jobs()).await;
+                        tokio::time::sleep(tokio::time::Duration::from_millis(NOTIFY_DEBOUNCE_MS)).await;
+                        while let Ok(Some(_)) = tokio::time::timeout(tokio::time::Duration::ZERO, stream.next()).await {}
+                    }
+                    warn!("Lost the connection used to wait for new jobs, reconnecting.");
+                }
+                Err(e) => {
+                    error!("Unable to wait for new jobs, falling back to polling: {}", e);
+                    let _ = tx.send(WorkerMessage::check_for_jobs()).await;
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(NOTIFY_RECONNECT_SECS)).await;
+        }
+    });
+    thread
 }
 
-pub fn get_max_runtime(max_duration: &str) -> RsrqResult<u64> {
-    let hits = RE_MAX_DURATION.captures_iter(max_duration).map(|hit| {
-        let (_, [num, unit]) = hit.extract();
-        parse_time_unit(unit, num)
+/// Poll Redis every poll milliseconds for control commands sent to this process (e.g. "rsrq worker ctl 1 drain").
+pub fn create_control_thread(proc_id: usize, tx: &mpsc::Sender<WorkerMessage>, poll_ms: u64, con: &ConnectionManager) -> JoinHandle<()> {
+    let tx = tx.clone();
+    let mut con = con.clone();
+    let thread = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(poll_ms));
+        loop {
+            interval.tick().await;
+            match WorkerControl::receive_all(proc_id, &mut con).await {
+                Ok(controls) => {
+                    for control in controls {
+                        let _ = tx.send(WorkerMessage::control(control)).await;
+                    }
+                }
+                Err(e) => error!("Unable to check for control commands: {}", e),
+            }
+        }
     });
-    let mut total_seconds = 0;
-    let mut hits_seen = 0;
-    for hit in hits {
-        total_seconds += hit?;
-        hits_seen += 1;
-    }
-    if hits_seen == 0 {
-        return Err(RsrqError::CmdParserError(format!("Invalid max duration provided: {}", max_duration)));
-    }
-    Ok(total_seconds as u64)
+    thread
 }
-
-
-#[test]
-fn test_get_max_runtime() {
-    fn h2s(hours: u64) -> u64 {
-        hours * 60 * 60
-    }
-
-    fn m2s(minutes: u64) -> u64 {
-        minutes * 60
-    }
-
-    assert_eq!(get_max_runtime("1h2m3s").unwrap(), h2s(1) + m2s(2) + 3);
-    assert_eq!(get_max_runtime("10h20m30s").unwrap(), h2s(10) + m2s(20) + 30);
-    assert_eq!(get_max_runtime("100h200m300s").unwrap(), h2s(100) + m2s(200) + 300);
-
-    // Test only seconds
-    assert_eq!(get_max_runtime("1s").unwrap(), h2s(0) + m2s(0) + 1);
-
-    // Test only minutes
-    assert_eq!(get_max_runtime("2m").unwrap(), h2s(0) + m2s(2) + 0);
-
-    // Test only hours
-    assert_eq!(get_max_runtime("1h").unwrap(), h2s(1) + m2s(0) + 0);
-
-    // Test hours and minutes
-    assert_eq!(get_max_runtime("1h2m").unwrap(), h2s(1) + m2s(2) + 0);
-
-    // Test minutes and seconds
-    assert_eq!(get_max_runtime("2m3s").unwrap(), h2s(0) + m2s(2) + 3);
-
-    // Test hours and seconds
-    assert_eq!(get_max_runtime("1h3s").unwrap(), h2s(1) + m2s(0) + 3);
-
-    // Test hours, minutes, and seconds
-    assert_eq!(get_max_runtime("1h2m3s").unwrap(), h2s(1) + m2s(2) + 3);
-
-    // Test with leading zeros
-    assert_eq!(get_max_runtime("01h02m03s").unwrap(), h2s(1) + m2s(2) + 3);
-
-    // Test with different orders
-    assert_eq!(get_max_runtime("2m1h").unwrap(), h2s(1) + m2s(2) + 0);
-    assert_eq!(get_max_runtime("3s1h").unwrap(), h2s(1) + m2s(0) + 3);
-    assert_eq!(get_max_runtime("3s2m").unwrap(), h2s(0) + m2s(2) + 3);
-
-    // Test with invalid inputs
-    assert!(get_max_runtime("1h2m3x").is_err());
-    assert!(get_max_runtime("1j2k3l").is_err());
-    assert!(get_max_runtime("").is_err());
-
-    assert_eq!(get_max_runtime("1h2m3s").unwrap(), h2s(1) + m2s(2) + 3);
-    assert_eq!(get_max_runtime("1h3s2m").unwrap(), h2s(1) + m2s(2) + 3);
-    assert_eq!(get_max_runtime("2m1h3s").unwrap(), h2s(1) + m2s(2) + 3);
-    assert_eq!(get_max_runtime("2m3s1h").unwrap(), h2s(1) + m2s(2) + 3);
-    assert_eq!(get_max_runtime("2m3s1h").unwrap(), h2s(1) + m2s(2) + 3);
-
-    assert_eq!(get_max_runtime("2m1h3s").unwrap(), h2s(1) + m2s(2) + 3);
-
-    assert_eq!(get_max_runtime("2m3s").unwrap(), h2s(0) + m2s(2) + 3);
-    assert_eq!(get_max_runtime("3s2m").unwrap(), h2s(0) + m2s(2) + 3);
-
-    assert_eq!(get_max_runtime("1h2m").unwrap(), h2s(1) + m2s(2) + 0);
-    assert_eq!(get_max_runtime("1h2s").unwrap(), h2s(1) + m2s(0) + 2);
-    assert_eq!(get_max_runtime("1m2s").unwrap(), h2s(0) + m2s(1) + 2);
-
-    assert_eq!(get_max_runtime("1s2m3h").unwrap(), h2s(3) + m2s(2) + 1);
-    assert_eq!(get_max_runtime("2m3h").unwrap(), h2s(3) + m2s(2) + 0);
-    assert_eq!(get_max_runtime("2m3h").unwrap(), h2s(3) + m2s(2) + 0);
-}
\ No newline at end of file
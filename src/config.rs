@@ -6,19 +6,59 @@ Configuration for Redis key values.
 // Environment variable for Redis connection string
 pub const REDIS_ENV_URL: &str = "REDIS_URL";
 
+// Environment variable set by Snakemake to the first line written by the sidecar (the path of its socket)
+pub const SNAKEMAKE_SIDECAR_ENV: &str = "SNAKEMAKE_CLUSTER_SIDECAR_VARS";
+
+// Environment variable that is set to the id of the job for each command run by a worker
+pub const JOB_ID_ENV: &str = "RSRQ_JOB_ID";
+
 // Lists are prefixed with the following
 pub const Q_RUNNING: &str = "rsrq:running";
-pub const Q_QUEUED: &str = "rsrq:queued";
 pub const Q_FINISHED: &str = "rsrq:finished";
 pub const Q_FAILED: &str = "rsrq:failed";
+pub const Q_DEFERRED: &str = "rsrq:deferred";
+
+// Sorted set (ordered by the job priority, then the job id) of jobs waiting to be run,
+// the id of each job added is also published on a channel with the same name as the key (as is an empty message
+// when the queue is resumed)
+pub const Q_QUEUED: &str = "rsrq:queued";
+
+// Sorted sets (scored by the timestamp the job becomes available) are prefixed with the following
+pub const Q_DELAYED: &str = "rsrq:delayed";
+pub const Q_SCHEDULED: &str = "rsrq:scheduled";
+
+// Set of the names of queues that workers should not claim jobs from
+pub const PAUSED_KEY: &str = "rsrq:paused";
 
 // Hash prefixed
 pub const JOB_KEY: &str = "rsrq:job";
 
 pub const PROC_KEY: &str = "rsrq:proc";
 
-// Auto-incrementing UID for worker and jobs
+pub const SCHEDULE_KEY: &str = "rsrq:schedule";
+
+// Set of the ids of all recurring schedules
+pub const SCHEDULES_KEY: &str = "rsrq:schedules";
+
+// Lists of control commands (e.g. drain, stop) waiting to be read by a worker process
+pub const CTL_KEY: &str = "rsrq:ctl";
+
+// Locks held while cleaning up after a process that has died
+pub const REAP_LOCK_KEY: &str = "rsrq:lock:reap";
+
+// Locks held by the worker that enqueues each occurrence of a recurring schedule
+pub const SCHEDULE_LOCK_KEY: &str = "rsrq:lock:schedule";
+
+// Sets of job ids that are waiting on a given job to finish
+pub const DEPENDENTS_KEY: &str = "rsrq:dependents";
+
+// Sets of the ids of the jobs in each array
+pub const ARRAY_KEY: &str = "rsrq:array";
+
+// Auto-incrementing UID for workers, jobs, schedules, and arrays
 pub const UID_KEY_JOB: &str = "rsrq:uid:job";
 pub const UID_KEY_PROC: &str = "rsrq:uid:proc";
+pub const UID_KEY_SCHEDULE: &str = "rsrq:uid:schedule";
+pub const UID_KEY_ARRAY: &str = "rsrq:uid:array";
 
 // TODO: SET TTL VALUES & redis timeout
@@ -0,0 +1,34 @@
+//! rsrq - a minimal Redis-backed job queue.
+//!
+//! The [`RsrqClient`] is the main entry point for interacting with rsrq from
+//! Rust, the `rsrq` binary is a thin layer over this API.
+//!
+//! ```no_run
+//! use rsrq::RsrqClient;
+//!
+//! # async fn example() -> rsrq::RsrqResult<()> {
+//! let mut client = RsrqClient::new().await?;
+//! let job = client.enqueue("default", "echo hello").await?;
+//! let job = client.load_job(job.id).await?;
+//! println!("Job {} is {}", job.id, job.status);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod util;
+pub mod model;
+pub mod config;
+pub mod command;
+pub mod client;
+
+pub use crate::client::RsrqClient;
+pub use crate::model::error::RsrqError;
+pub use crate::model::job::rsrq_job::Job;
+pub use crate::model::job::status::JobStatus;
+pub use crate::model::process::rsrq_process::Process;
+pub use crate::model::queue::queue_info::QueueInfo;
+pub use crate::model::queue::queue_type::QueueType;
+pub use crate::model::queue::rsrq_queue::Queue;
+pub use crate::model::schedule::rsrq_schedule::Schedule;
+pub use crate::model::types::RsrqResult;
+pub use crate::model::worker::pool::WorkerPool;
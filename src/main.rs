@@ -3,23 +3,32 @@ use std::env;
 use clap::Parser;
 use log::{error, info};
 
-use crate::command::enqueue::main::enqueue_file;
-use crate::command::purge::all::purge_all;
-use crate::command::purge::queue::purge_queue;
-use crate::command::snakemake::cancel::snakemake_cancel;
-use crate::command::snakemake::config::snakemake_config;
-use crate::command::snakemake::status::snakemake_status;
-use crate::command::snakemake::submit::snakemake_submit;
-use crate::command::status::check_status::check_status;
-use crate::command::worker::main::run_workers;
-use crate::model::cli::{Cli, Commands, PurgeCommands, SnakemakeCommands};
-use crate::model::queue::queue_type::QueueType;
-
-mod util;
-mod model;
-mod config;
-mod command;
-
+use rsrq::command::cancel::main::cancel_jobs;
+use rsrq::command::compat::main::run_compat;
+use rsrq::command::enqueue::array::enqueue_array;
+use rsrq::command::enqueue::main::enqueue_file;
+use rsrq::command::job::show::job_show;
+use rsrq::command::logs::main::print_logs;
+use rsrq::command::purge::all::purge_all;
+use rsrq::command::purge::queue::purge_queue;
+use rsrq::command::queue::pause::pause_queues;
+use rsrq::command::queue::resume::resume_queues;
+use rsrq::command::reap::main::reap;
+use rsrq::command::requeue::main::requeue_jobs;
+use rsrq::command::schedule::add::schedule_add;
+use rsrq::command::schedule::list::schedule_list;
+use rsrq::command::schedule::remove::schedule_remove;
+use rsrq::command::snakemake::cancel::snakemake_cancel;
+use rsrq::command::snakemake::config::snakemake_config;
+use rsrq::command::snakemake::sidecar::snakemake_sidecar;
+use rsrq::command::snakemake::status::snakemake_status;
+use rsrq::command::snakemake::submit::snakemake_submit;
+use rsrq::command::status::check_status::check_status;
+use rsrq::command::worker::ctl::worker_ctl;
+use rsrq::command::worker::main::run_workers;
+use rsrq::model::cli::{Cli, Commands, JobCommands, PurgeCommands, QueueCommands, ScheduleCommands, SnakemakeCommands, WorkerCommands};
+use rsrq::model::compat::tool::CompatTool;
+use rsrq::model::queue::queue_type::QueueType;
 
 #[tokio::main]
 async fn main() {
@@ -29,13 +38,23 @@ async fn main() {
     }
     env_logger::init();
 
+    // Act as a SLURM or SGE command if called by its name (e.g. a symlink named sbatch)
+    if let Some(tool) = env::args_os().next().and_then(|x| x.to_str().and_then(CompatTool::from_program)) {
+        let args: Vec<String> = env::args().skip(1).collect();
+        if let Err(err) = run_compat(tool, &args).await {
+            error!("{}: {}", tool, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Initialise the CLI and parse the arguments
     let cli = Cli::parse();
     match &cli.command {
 
         // Run the enqueue workflow
-        Commands::Enqueue { path, queue } => {
-            match enqueue_file(path, queue).await {
+        Commands::Enqueue { path, queue, format, options } => {
+            match enqueue_file(path, queue, *format, options).await {
                 Ok(_) => info!("Successfully enqueued jobs."),
                 Err(e) => {
                     error!("Error enqueuing jobs: {}", e);
@@ -44,19 +63,36 @@ async fn main() {
             }
         }
 
+        Commands::EnqueueArray { queue, template, params, range, glob, raw, options } => {
+            if let Err(err) = enqueue_array(queue, template, params, range, glob, *raw, options).await {
+                error!("Error enqueuing the array: {}", err);
+                std::process::exit(1);
+            }
+        }
+
         // Run the workers workflow
-        Commands::Worker { queue, workers, max_duration, max_jobs, burst, poll } => {
-            match run_workers(queue, *workers, max_duration, *max_jobs, *burst, *poll).await {
-                Ok(_) => info!("Workers stopped successfully."),
-                Err(e) => {
-                    error!("Error running workers: {}", e);
-                    std::process::exit(1);
+        Commands::Worker(worker) => {
+            match &worker.command {
+                Some(WorkerCommands::Ctl { target, action }) => {
+                    if let Err(err) = worker_ctl(target, action).await {
+                        error!("Error sending control command: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                None => {
+                    match run_workers(&worker.args).await {
+                        Ok(_) => info!("Workers stopped successfully."),
+                        Err(e) => {
+                            error!("Error running workers: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
                 }
             }
         }
 
-        Commands::Status { queue } => {
-            match check_status(queue).await {
+        Commands::Status { queue, format, group_by, array } => {
+            match check_status(queue, *format, *group_by, *array).await {
                 Ok(_) => {}
                 Err(e) => {
                     error!("Error running workers: {}", e);
@@ -65,6 +101,88 @@ async fn main() {
             }
         }
 
+        Commands::Cancel { job_ids, array } => {
+            if let Err(err) = cancel_jobs(job_ids, *array).await {
+                error!("Error cancelling jobs: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Requeue { job_ids, queue, status } => {
+            if let Err(err) = requeue_jobs(job_ids, queue, status).await {
+                error!("Error re-queuing jobs: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        // Run a job subcommand
+        Commands::Job(job) => {
+            match &job.command {
+                JobCommands::Show { job_id, format } => {
+                    if let Err(err) = job_show(*job_id, *format).await {
+                        error!("Error showing job: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+
+        Commands::Logs { job_id, stderr } => {
+            if let Err(err) = print_logs(*job_id, *stderr).await {
+                error!("Error printing logs: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Reap { stale_after, policy } => {
+            if let Err(err) = reap(stale_after, *policy).await {
+                error!("Error reaping processes: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        // Run a queue subcommand
+        Commands::Queue(queue) => {
+            match &queue.command {
+                QueueCommands::Pause { queues } => {
+                    if let Err(err) = pause_queues(queues).await {
+                        error!("Error pausing queues: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                QueueCommands::Resume { queues } => {
+                    if let Err(err) = resume_queues(queues).await {
+                        error!("Error resuming queues: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+
+        // Run a schedule subcommand
+        Commands::Schedule(schedule) => {
+            match &schedule.command {
+                ScheduleCommands::Add { queue, cron, cmd, options } => {
+                    if let Err(err) = schedule_add(queue, cron, cmd, options).await {
+                        error!("Error adding schedule: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                ScheduleCommands::List { format } => {
+                    if let Err(err) = schedule_list(*format).await {
+                        error!("Error listing schedules: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                ScheduleCommands::Remove { schedule_ids } => {
+                    if let Err(err) = schedule_remove(schedule_ids).await {
+                        error!("Error removing schedules: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+
         // Run a snakemake subcommand
         Commands::Snakemake(snakemake) => {
             match &snakemake.command {
@@ -75,19 +193,25 @@ async fn main() {
                     }
                 }
                 SnakemakeCommands::Status { job_id } => {
-                    if let Err(err) = snakemake_status(*job_id) {
+                    if let Err(err) = snakemake_status(*job_id).await {
                         error!("Error checking job status: {}", err);
                         std::process::exit(1);
                     }
                 }
                 SnakemakeCommands::Cancel { job_ids } => {
-                    if let Err(err) = snakemake_cancel(job_ids) {
+                    if let Err(err) = snakemake_cancel(job_ids).await {
                         error!("Error cancelling job: {}", err);
                         std::process::exit(1);
                     }
                 }
-                SnakemakeCommands::Config { directory } => {
-                    if let Err(err) = snakemake_config(directory) {
+                SnakemakeCommands::Sidecar => {
+                    if let Err(err) = snakemake_sidecar().await {
+                        error!("Error running the Snakemake sidecar: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                SnakemakeCommands::Config { directory, snakemake_version } => {
+                    if let Err(err) = snakemake_config(directory, *snakemake_version) {
                         error!("Error creating Snakemake cluster config: {}", err);
                         std::process::exit(1);
                     }
@@ -95,6 +219,14 @@ async fn main() {
             }
         }
 
+        // Act as a SLURM or SGE command
+        Commands::Compat { tool, args } => {
+            if let Err(err) = run_compat(*tool, args).await {
+                error!("{}: {}", tool, err);
+                std::process::exit(1);
+            }
+        }
+
         // Run a purge subcommand
         Commands::Purge(purge) => {
             match &purge.command {
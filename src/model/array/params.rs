@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_string;
+
+/// The placeholder of the position of the job in the array (starting at 1).
+pub const PARAM_INDEX: &str = "index";
+
+/// The placeholder of the numbers in a range.
+pub const PARAM_RANGE: &str = "n";
+
+/// The placeholders of the files matched by a glob: the path, the file name, and the file name without its extension.
+pub const PARAM_PATH: &str = "path";
+pub const PARAM_NAME: &str = "name";
+pub const PARAM_STEM: &str = "stem";
+
+/// The value of each placeholder for a single job.
+pub type ParamSet = BTreeMap<String, String>;
+
+/// Read the parameters from a TSV file, the header contains the names of the placeholders and each row is a job.
+pub fn parse_tsv(contents: &str) -> RsrqResult<Vec<ParamSet>> {
+    let mut lines = contents.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let header: Vec<&str> = match lines.next() {
+        Some((_, line)) => line.split('\t').map(|x| x.trim()).collect(),
+        None => return Err(RsrqError::ParserError("The parameters file is empty.".to_string())),
+    };
+    for (i, name) in header.iter().enumerate() {
+        if name.is_empty() || !name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') {
+            return Err(RsrqError::ParserError(format!("Invalid placeholder name in the header: \"{}\"", name)));
+        }
+        if *name == PARAM_INDEX {
+            return Err(RsrqError::ParserError(format!("The placeholder {{{}}} is reserved for the position of the job.", name)));
+        }
+        if header[..i].contains(name) {
+            return Err(RsrqError::ParserError(format!("The placeholder is defined more than once: {}", name)));
+        }
+    }
+
+    let mut out = Vec::new();
+    for (line_no, line) in lines {
+        let values: Vec<&str> = line.split('\t').collect();
+        if values.len() != header.len() {
+            return Err(RsrqError::ParserError(format!(
+                "Line {}: Expected {} values but found {}.", line_no, header.len(), values.len()
+            )));
+        }
+        out.push(header.iter().zip(values).map(|(k, v)| (k.to_string(), v.to_string())).collect());
+    }
+    Ok(out)
+}
+
+pub fn load_tsv(path: &str) -> RsrqResult<Vec<ParamSet>> {
+    let contents = std::fs::read_to_string(path).map_err(RsrqError::FileNotFound)?;
+    parse_tsv(&contents)
+}
+
+/// The numbers of an inclusive range, with an optional step (e.g. "1..100", "0..100:10").
+pub fn parse_range(value: &str) -> RsrqResult<Vec<ParamSet>> {
+    let invalid = || RsrqError::ParserError(format!("Invalid range: {} (expected e.g. 1..100 or 0..100:10)", value));
+    let (range, step) = match value.split_once(':') {
+        Some((range, step)) => (range, parse_string::<i64>(step.trim()).map_err(|_| invalid())?),
+        None => (value, 1),
+    };
+    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+    let start = parse_string::<i64>(start.trim()).map_err(|_| invalid())?;
+    let end = parse_string::<i64>(end.trim()).map_err(|_| invalid())?;
+    if step <= 0 || start > end {
+        return Err(invalid());
+    }
+    let mut out = Vec::new();
+    let mut n = start;
+    while n <= end {
+        out.push(ParamSet::from([(PARAM_RANGE.to_string(), n.to_string())]));
+        n += step;
+    }
+    Ok(out)
+}
+
+/// The files matching the pattern, sorted by path.
+pub fn expand_glob(pattern: &str) -> RsrqResult<Vec<ParamSet>> {
+    let paths = glob::glob(pattern).map_err(|e| RsrqError::ParserError(format!("Invalid glob: {} ({})", pattern, e)))?;
+    let mut out = Vec::new();
+    for path in paths {
+        let path = path.map_err(|e| RsrqError::IOError(e.into()))?;
+        let to_string = |x: Option<&OsStr>| x.map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+        out.push(ParamSet::from([
+            (PARAM_PATH.to_string(), path.to_string_lossy().to_string()),
+            (PARAM_NAME.to_string(), to_string(path.file_name())),
+            (PARAM_STEM.to_string(), to_string(path.file_stem())),
+        ]));
+    }
+    if out.is_empty() {
+        return Err(RsrqError::ParserError(format!("No files match the glob: {}", pattern)));
+    }
+    Ok(out)
+}
+
+/// Every combination of the parameters of each source (i.e. a parameter sweep), in order.
+pub fn combine(sources: Vec<Vec<ParamSet>>) -> RsrqResult<Vec<ParamSet>> {
+    let mut out: Vec<ParamSet> = vec![ParamSet::new()];
+    for source in sources {
+        let mut combined = Vec::with_capacity(out.len() * source.len());
+        for current in &out {
+            for params in &source {
+                let mut merged = current.clone();
+                for (name, value) in params {
+                    if merged.insert(name.clone(), value.clone()).is_some() {
+                        return Err(RsrqError::ParserError(format!("The placeholder is defined more than once: {}", name)));
+                    }
+                }
+                combined.push(merged);
+            }
+        }
+        out = combined;
+    }
+    Ok(out)
+}
+
+/// Load the parameters of each job from the sources (a TSV file, a range, or a glob), and number the jobs.
+pub fn load_array_params(tsv: Option<&str>, range: Option<&str>, glob: Option<&str>) -> RsrqResult<Vec<ParamSet>> {
+    let mut sources = Vec::new();
+    if let Some(path) = tsv {
+        sources.push(load_tsv(path)?);
+    }
+    if let Some(range) = range {
+        sources.push(parse_range(range)?);
+    }
+    if let Some(pattern) = glob {
+        sources.push(expand_glob(pattern)?);
+    }
+    if sources.is_empty() {
+        return Err(RsrqError::ParserError("No parameters were given (a TSV file, a range, or a glob).".to_string()));
+    }
+    let mut out = combine(sources)?;
+    if out.is_empty() {
+        return Err(RsrqError::ParserError("The parameters do not contain any jobs.".to_string()));
+    }
+    for (i, params) in out.iter_mut().enumerate() {
+        params.insert(PARAM_INDEX.to_string(), (i + 1).to_string());
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_parse_tsv() {
+    let params = parse_tsv("sample\tlane\r\nA\t1\n\nB\t2\n").unwrap();
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[1].get("sample").unwrap(), "B");
+    assert_eq!(params[1].get("lane").unwrap(), "2");
+
+    assert!(parse_tsv("").is_err());
+    assert!(parse_tsv("sample\tlane\nA\n").is_err());
+    assert!(parse_tsv("sample\tsample\nA\tB\n").is_err());
+    assert!(parse_tsv("index\nA\n").is_err());
+    assert!(parse_tsv("the sample\nA\n").is_err());
+}
+
+#[test]
+fn test_parse_range() {
+    let values = |x: Vec<ParamSet>| x.iter().map(|x| x.get(PARAM_RANGE).unwrap().clone()).collect::<Vec<String>>();
+    assert_eq!(values(parse_range("1..3").unwrap()), vec!["1", "2", "3"]);
+    assert_eq!(values(parse_range("0..25:10").unwrap()), vec!["0", "10", "20"]);
+    assert_eq!(values(parse_range("-1..-1").unwrap()), vec!["-1"]);
+    assert!(parse_range("3..1").is_err());
+    assert!(parse_range("1..3:0").is_err());
+    assert!(parse_range("1-3").is_err());
+}
+
+#[test]
+fn test_expand_glob() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["b.fa", "a.fa", "c.txt"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
+    let pattern = format!("{}/*.fa", dir.path().display());
+    let params = expand_glob(&pattern).unwrap();
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[0].get(PARAM_PATH).unwrap(), &format!("{}/a.fa", dir.path().display()));
+    assert_eq!(params[0].get(PARAM_NAME).unwrap(), "a.fa");
+    assert_eq!(params[1].get(PARAM_STEM).unwrap(), "b");
+    assert!(expand_glob(&format!("{}/*.fq", dir.path().display())).is_err());
+}
+
+#[test]
+fn test_combine() {
+    let samples = parse_tsv("sample\nA\nB\n").unwrap();
+    let params = combine(vec![samples.clone(), parse_range("1..2").unwrap()]).unwrap();
+    let pairs: Vec<(String, String)> = params.iter().map(|x| (x["sample"].clone(), x[PARAM_RANGE].clone())).collect();
+    assert_eq!(pairs, vec![
+        ("A".to_string(), "1".to_string()), ("A".to_string(), "2".to_string()),
+        ("B".to_string(), "1".to_string()), ("B".to_string(), "2".to_string()),
+    ]);
+    assert!(combine(vec![samples.clone(), samples]).is_err());
+}
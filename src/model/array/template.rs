@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Placeholder(String),
+}
+
+/// A command containing {placeholders} that are replaced with the parameters of each job in an array.
+/// Only names made of letters, digits, and underscores are placeholders, "{{" and "}}" are literal braces,
+/// and shell variables (e.g. "${HOME}") are left as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl CommandTemplate {
+    pub fn parse(template: &str) -> RsrqResult<CommandTemplate> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let chars: Vec<char> = template.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            match c {
+                '{' | '}' if chars.get(i) == Some(&c) => {
+                    text.push(c);
+                    i += 1;
+                }
+                // Shell variables are copied up to (and including) their closing brace
+                '$' if chars.get(i) == Some(&'{') => {
+                    let end = chars[i..].iter().position(|x| *x == '}').map_or(chars.len(), |x| i + x + 1);
+                    text.push(c);
+                    text.extend(&chars[i..end]);
+                    i = end;
+                }
+                '{' => {
+                    let len = chars[i..].iter().take_while(|x| is_name_char(**x)).count();
+                    if len > 0 && chars.get(i + len) == Some(&'}') {
+                        if !text.is_empty() {
+                            parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                        }
+                        parts.push(TemplatePart::Placeholder(chars[i..i + len].iter().collect()));
+                        i += len + 1;
+                    } else {
+                        text.push(c);
+                    }
+                }
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        if !parts.iter().any(|x| matches!(x, TemplatePart::Placeholder(_))) {
+            return Err(RsrqError::ParserError(format!("The command has no placeholders: {}", template)));
+        }
+        Ok(CommandTemplate { parts })
+    }
+
+    /// The names of the placeholders, in the order they first appear.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut out: Vec<&str> = Vec::new();
+        for part in &self.parts {
+            if let TemplatePart::Placeholder(name) = part {
+                if !out.contains(&name.as_str()) {
+                    out.push(name);
+                }
+            }
+        }
+        out
+    }
+
+    /// The command with each placeholder replaced by its value. Each value is quoted as a single shell word
+    /// (e.g. "a b.fa" is inserted as 'a b.fa'), unless raw is set in which case it is inserted as it is.
+    pub fn render(&self, values: &BTreeMap<String, String>, raw: bool) -> RsrqResult<String> {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => out.push_str(text),
+                TemplatePart::Placeholder(name) => match values.get(name) {
+                    Some(value) if raw => out.push_str(value),
+                    Some(value) => out.push_str(&shlex::try_quote(value)
+                        .map_err(|_| RsrqError::ParserError(format!("Unable to quote the value of {{{}}}: {}", name, value)))?),
+                    None => return Err(RsrqError::ParserError(format!("No value for the placeholder: {{{}}}", name))),
+                },
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[test]
+fn test_command_template() {
+    let template = CommandTemplate::parse("align {sample} --lane {n} > {sample}.{n}.log").unwrap();
+    assert_eq!(template.placeholders(), vec!["sample", "n"]);
+
+    let values = BTreeMap::from([("sample".to_string(), "A".to_string()), ("n".to_string(), "1".to_string())]);
+    assert_eq!(template.render(&values, false).unwrap(), "align A --lane 1 > A.1.log");
+    assert!(template.render(&BTreeMap::from([("n".to_string(), "1".to_string())]), false).is_err());
+
+    // Literal braces, shell variables, and brace expansion are left as they are
+    let template = CommandTemplate::parse("echo {{n}} ${HOME}/{a,b} { {n}; } ${n:-{n}}").unwrap();
+    assert_eq!(template.placeholders(), vec!["n"]);
+    assert_eq!(
+        template.render(&BTreeMap::from([("n".to_string(), "7".to_string())]), false).unwrap(),
+        "echo {n} ${HOME}/{a,b} { 7; } ${n:-{n}}"
+    );
+
+    assert!(CommandTemplate::parse("echo ${n}").is_err());
+}
+
+#[test]
+fn test_command_template_quoting() {
+    // Values are single shell words, unless they are inserted raw
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a b.fa"), "").unwrap();
+    let pattern = format!("{}/*.fa", dir.path().display());
+    let params = crate::model::array::params::load_array_params(None, None, Some(&pattern)).unwrap();
+    let template = CommandTemplate::parse("gzip -c {path} > out/{stem}.gz").unwrap();
+    let cmd = template.render(&params[0], false).unwrap();
+    let path = format!("{}/a b.fa", dir.path().display());
+    assert_eq!(shlex::split(&cmd).unwrap(), vec!["gzip", "-c", &path, ">", "out/a b.gz"]);
+    assert_eq!(template.render(&params[0], true).unwrap(), format!("gzip -c {} > out/a b.gz", path));
+
+    let template = CommandTemplate::parse("echo {x}").unwrap();
+    let values = BTreeMap::from([("x".to_string(), "$(touch x); 'q'".to_string())]);
+    let cmd = template.render(&values, false).unwrap();
+    assert_eq!(shlex::split(&cmd).unwrap(), vec!["echo", "$(touch x); 'q'"]);
+    assert_eq!(template.render(&BTreeMap::from([("x".to_string(), "".to_string())]), false).unwrap(), "echo ''");
+}
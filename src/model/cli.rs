@@ -1,4 +1,21 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::model::compat::tool::CompatTool;
+use crate::model::enqueue_format::EnqueueFormat;
+use crate::model::format::OutputFormat;
+use crate::model::job::backoff::RetryBackoff;
+use crate::model::job::exec_mode::ExecMode;
+use crate::model::job::options::JobOptions;
+use crate::model::job::status::JobStatus;
+use crate::model::output::DEFAULT_TAIL_BYTES;
+use crate::model::process::orphan_policy::OrphanPolicy;
+use crate::model::queue::status_group::StatusGroup;
+use crate::model::snakemake::version::SnakemakeVersion;
+use crate::model::types::RsrqResult;
+use crate::model::worker::control::WorkerControl;
+use crate::model::worker::queue_order::QueueOrder;
+use crate::util::parsing::parse_memory_mb;
+use crate::util::time::{get_timestamp_s, parse_datetime, parse_duration};
 
 #[derive(Parser)]
 #[command(author, version)]
@@ -16,51 +33,291 @@ pub enum Commands {
         /// The target queue to add jobs to.
         queue: String,
 
-        /// Path to the file containing one command per-line.
+        /// Path to the file containing one job per-line.
         path: String,
+
+        /// The format of the file, one command per line (lines), or one JSON object per line (jsonl)
+        /// with the fields: cmd, cwd, env, timeout, name, tags, priority, max_retries, after, mode, cpus, mem_mb.
+        #[clap(long, default_value = "lines")]
+        format: EnqueueFormat,
+
+        #[command(flatten)]
+        options: JobArgs,
     },
 
-    /// Spawns worker processes to consume jobs from a queue.
+    /// Enqueue an array of jobs, one for each set of parameters substituted into a command template.
     #[command(arg_required_else_help = true)]
-    Worker {
-        /// The target queue to process.
+    #[command(group(ArgGroup::new("array_params").required(true).multiple(true).args(["params", "range", "glob"])))]
+    EnqueueArray {
+        /// The target queue to add jobs to.
         queue: String,
 
-        /// The number of workers to spawn.
-        #[clap(long, default_value = "1")]
-        workers: u16,
+        /// The command to run, where each {placeholder} is replaced by a parameter (e.g. "align {sample} --lane {n}").
+        /// {index} is the position of the job in the array (starting at 1), use {{ and }} for literal braces.
+        template: String,
 
-        /// Stop processing after (h)ours (m)inutes (s)econds (eg: 1h30m, 30m, 1h5s).
+        /// Path to a TSV file with a header of placeholder names, and one job per row.
         #[clap(long)]
-        max_duration: Option<String>,
+        params: Option<String>,
 
-        /// Stop processing after this many jobs have finished.
+        /// A range of numbers for the {n} placeholder, with an optional step (e.g. 1..1000 or 0..100:10).
         #[clap(long)]
-        max_jobs: Option<u32>,
+        range: Option<String>,
 
-        /// Stop processing once the queue is empty.
-        #[clap(long, default_value = "false")]
-        burst: bool,
+        /// A glob matching files for the {path}, {name}, and {stem} placeholders (e.g. 'data/*.fa').
+        #[clap(long)]
+        glob: Option<String>,
+
+        /// Insert the values as they are, rather than quoted as single shell words.
+        #[clap(long)]
+        raw: bool,
 
-        /// Interval to check for new jobs in milliseconds.
-        #[clap(long, default_value = "1000")]
-        poll: u64,
+        #[command(flatten)]
+        options: JobArgs,
     },
 
+    /// Spawns worker processes to consume jobs from a queue.
+    #[command(arg_required_else_help = true)]
+    Worker(WorkerCommandArgs),
+
     /// Check the status of all objects in the Redis database
     Status {
         /// The target queue to check (default: all queues).
         #[clap(long)]
         queue: Option<String>,
+
+        /// The output format (human, json, tsv).
+        #[clap(long, default_value = "human")]
+        format: OutputFormat,
+
+        /// Count the jobs in each queue (queue), in each Snakemake rule (rule), or in each array (array).
+        #[clap(long, default_value = "queue")]
+        group_by: StatusGroup,
+
+        /// Only count the jobs of this array.
+        #[clap(long)]
+        array: Option<usize>,
+    },
+
+    /// Cancel queued or running jobs.
+    #[command(arg_required_else_help = true)]
+    Cancel {
+        /// The Job IDs to cancel.
+        job_ids: Vec<usize>,
+
+        /// Cancel all jobs of this array.
+        #[clap(long)]
+        array: Option<usize>,
+    },
+
+    /// Move failed or cancelled jobs back into the queue, keeping their Job IDs.
+    #[command(arg_required_else_help = true)]
+    Requeue {
+        /// The Job IDs to requeue (default: all jobs matching the filters).
+        job_ids: Vec<usize>,
+
+        /// Only requeue jobs in this queue.
+        #[clap(long)]
+        queue: Option<String>,
+
+        /// Only requeue jobs with these statuses (failed, cancelled, timed_out), comma separated (default: all).
+        #[clap(long, value_delimiter = ',')]
+        status: Vec<JobStatus>,
+    },
+
+    /// Commands for inspecting individual jobs.
+    Job(JobCommandArgs),
+
+    /// Print the captured output of a job.
+    #[command(arg_required_else_help = true)]
+    Logs {
+        /// The Job ID to print the output of.
+        job_id: usize,
+
+        /// Print stderr instead of stdout.
+        #[clap(long, default_value = "false")]
+        stderr: bool,
     },
 
+    /// Remove worker processes that have stopped sending heartbeats and release their jobs.
+    Reap {
+        /// Consider a process dead if it has not sent a heartbeat in (h)ours (m)inutes (s)econds.
+        #[clap(long, default_value = "1m")]
+        stale_after: String,
+
+        /// What to do with the running jobs of dead processes (requeue, fail).
+        #[clap(long, default_value = "requeue")]
+        policy: OrphanPolicy,
+    },
+
+    /// Commands for pausing and resuming queues.
+    Queue(QueueArgs),
+
+    /// Commands for managing recurring (cron) schedules that are enqueued by running workers.
+    Schedule(ScheduleArgs),
+
     /// Commands that can be issued by Snakemake for cluster execution.
     Snakemake(SnakemakeArgs),
 
+    /// Act as a SLURM or SGE command, this is also done if rsrq is called by its name (e.g. a symlink named sbatch).
+    #[command(arg_required_else_help = true)]
+    Compat {
+        /// The command to act as (sbatch, squeue, scancel, qsub, qstat, qdel).
+        tool: CompatTool,
+
+        /// The arguments of the command.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
     /// Commands for removing data from Redis.
     Purge(PurgeArgs),
 }
 
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct WorkerCommandArgs {
+    #[command(subcommand)]
+    pub command: Option<WorkerCommands>,
+
+    #[command(flatten)]
+    pub args: WorkerArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorkerCommands {
+    /// Send a control command to running workers.
+    #[command(arg_required_else_help = true)]
+    Ctl {
+        /// The Process ID of the worker, or a hostname to control all workers on that host.
+        target: String,
+
+        #[command(subcommand)]
+        action: WorkerCtlCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorkerCtlCommands {
+    /// Finish the running jobs without starting new ones, then exit.
+    Drain,
+    /// Kill the running jobs (releasing them according to the worker's shutdown policy), then exit.
+    Stop,
+    /// Change the number of jobs that are run at the same time.
+    Resize {
+        /// The new number of workers.
+        #[clap(value_parser = clap::value_parser!(u32).range(1..))]
+        workers: u32,
+    },
+}
+
+impl WorkerCtlCommands {
+    pub fn to_control(&self) -> WorkerControl {
+        match self {
+            WorkerCtlCommands::Drain => WorkerControl::Drain,
+            WorkerCtlCommands::Stop => WorkerControl::Stop,
+            WorkerCtlCommands::Resize { workers } => WorkerControl::Resize(*workers),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct WorkerArgs {
+    /// The target queue to process, or a comma separated list of queues in order of priority (eg: high,low).
+    /// Each queue can be given a weight for the weighted queue order (eg: high:3,low:1).
+    #[clap(required = true)]
+    pub queue: Option<String>,
+
+    /// How jobs are claimed from multiple queues: from the first non-empty queue (strict),
+    /// or shared between the queues in proportion to their weights (weighted).
+    #[clap(long, default_value = "strict")]
+    pub queue_order: QueueOrder,
+
+    /// The number of workers to spawn, i.e. the maximum number of jobs run at the same time
+    /// (default: 1, or the number of CPUs if they are limited).
+    #[clap(long)]
+    pub workers: Option<u16>,
+
+    /// Limit the total CPUs used by running jobs, jobs are only started once their CPUs are free.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub cpus: Option<u32>,
+
+    /// Limit the total memory used by running jobs (eg: 64G), jobs are only started once their memory is free.
+    #[clap(long)]
+    pub mem: Option<String>,
+
+    /// Stop processing after (h)ours (m)inutes (s)econds (eg: 1h30m, 30m, 1h5s).
+    #[clap(long)]
+    pub max_duration: Option<String>,
+
+    /// Stop processing after this many jobs have finished.
+    #[clap(long)]
+    pub max_jobs: Option<u32>,
+
+    /// Stop processing once the queue is empty.
+    #[clap(long, default_value = "false")]
+    pub burst: bool,
+
+    /// Interval to check for delayed, scheduled, and cancelled jobs in milliseconds.
+    /// New jobs are picked up as soon as they are enqueued.
+    #[clap(long, default_value = "1000")]
+    pub poll: u64,
+
+    /// Interval to send heartbeats, and check for control commands, dead workers and recurring schedules,
+    /// in (h)ours (m)inutes (s)econds.
+    #[clap(long, default_value = "5s")]
+    pub heartbeat: String,
+
+    /// Default timeout for jobs that do not specify one, in (h)ours (m)inutes (s)econds (eg: 1h30m).
+    #[clap(long)]
+    pub timeout: Option<String>,
+
+    /// How to run jobs that do not specify a mode: split into words (exec), or pass to the shell (shell).
+    #[clap(long, default_value = "exec")]
+    pub mode: ExecMode,
+
+    /// The mode of jobs in specific queues that do not specify one, taking precedence over "--mode",
+    /// comma separated (e.g. "build=shell,align=exec").
+    #[clap(long, value_delimiter = ',')]
+    pub queue_mode: Vec<String>,
+
+    /// The shell used to run jobs in shell mode.
+    #[clap(long, default_value = "/bin/sh")]
+    pub shell: String,
+
+    /// Write the stdout and stderr of each job to files in this directory, instead of storing them in Redis.
+    #[clap(long)]
+    pub log_dir: Option<String>,
+
+    /// The log file names within the log directory, "{id}", "{queue}", "{attempt}" and "{stream}" are replaced.
+    #[clap(long, default_value = "{queue}/{id}.{stream}")]
+    pub log_template: String,
+
+    /// The number of bytes from the end of each log file to also store in Redis.
+    #[clap(long, default_value_t = DEFAULT_TAIL_BYTES)]
+    pub log_tail: usize,
+
+    /// Consider other workers dead if they have not sent a heartbeat in (h)ours (m)inutes (s)econds.
+    #[clap(long, default_value = "1m")]
+    pub stale_after: String,
+
+    /// What to do with the running jobs of dead workers (requeue, fail).
+    #[clap(long, default_value = "requeue")]
+    pub orphan_policy: OrphanPolicy,
+
+    /// The signal sent to running jobs when the worker receives SIGINT, SIGTERM, or SIGHUP.
+    #[clap(long, default_value = "SIGTERM")]
+    pub shutdown_signal: String,
+
+    /// How long to wait for running jobs to exit after the shutdown signal, in (h)ours (m)inutes (s)econds.
+    #[clap(long, default_value = "30s")]
+    pub grace_period: String,
+
+    /// What to do with jobs that are still running after the grace period, or when stopped with "worker ctl" (requeue, fail).
+    #[clap(long, default_value = "requeue")]
+    pub on_shutdown: OrphanPolicy,
+}
+
 #[derive(Args)]
 #[command(args_conflicts_with_subcommands = true)]
 pub struct SnakemakeArgs {
@@ -85,10 +342,98 @@ pub enum SnakemakeCommands {
         /// The Jobs ID to cancel
         job_ids: Vec<usize>
     },
+    /// Answer status checks from a cache of job statuses, started by Snakemake as the cluster sidecar.
+    Sidecar,
     /// Write the Snakemake cluster profile to the target directory.
     Config {
         /// The target directory to write the profile to.
         directory: String,
+
+        /// The major version of Snakemake (7, 8), version 8 uses the cluster-generic executor plugin.
+        #[clap(long, default_value = "7")]
+        snakemake_version: SnakemakeVersion,
+    },
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct JobCommandArgs {
+    #[command(subcommand)]
+    pub command: JobCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum JobCommands {
+    /// Show the attributes of a job.
+    Show {
+        /// The Job ID to show.
+        job_id: usize,
+
+        /// The output format (human, json, tsv).
+        #[clap(long, default_value = "human")]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct QueueArgs {
+    #[command(subcommand)]
+    pub command: QueueCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueueCommands {
+    /// Stop workers from starting new jobs from the queues, running jobs are not affected.
+    #[command(arg_required_else_help = true)]
+    Pause {
+        /// The queues to pause.
+        queues: Vec<String>,
+    },
+    /// Allow workers to start jobs from the queues again.
+    #[command(arg_required_else_help = true)]
+    Resume {
+        /// The queues to resume.
+        queues: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct ScheduleArgs {
+    #[command(subcommand)]
+    pub command: ScheduleCommands,
+}
+
+#[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
+pub enum ScheduleCommands {
+    /// Enqueue a command each time the cron expression is due (eg: "0 2 * * 1-5" for 2am on weekdays).
+    #[command(arg_required_else_help = true)]
+    Add {
+        /// The target queue to add jobs to.
+        queue: String,
+
+        /// The cron expression (minute hour day-of-month month day-of-week), in local time.
+        cron: String,
+
+        /// The command to run.
+        cmd: String,
+
+        #[command(flatten)]
+        options: JobArgs,
+    },
+    /// List all schedules.
+    List {
+        /// The output format (human, json, tsv).
+        #[clap(long, default_value = "human")]
+        format: OutputFormat,
+    },
+    /// Remove schedules, jobs that were already enqueued are not affected.
+    #[command(arg_required_else_help = true)]
+    Remove {
+        /// The Schedule IDs to remove.
+        schedule_ids: Vec<usize>,
     },
 }
 
@@ -122,3 +467,82 @@ pub enum PurgeCommands {
         queue: Option<String>,
     },
 }
+
+/// Options that are applied to each job when enqueued.
+#[derive(Args, Debug)]
+pub struct JobArgs {
+    /// Kill each job if it runs longer than (h)ours (m)inutes (s)econds (eg: 1h30m, 30m, 1h5s).
+    #[clap(long)]
+    pub timeout: Option<String>,
+
+    /// The number of times to retry a job if it fails.
+    #[clap(long, default_value = "0")]
+    pub max_retries: u32,
+
+    /// Wait (h)ours (m)inutes (s)econds before retrying a failed job (eg: 30s, 5m).
+    #[clap(long)]
+    pub retry_delay: Option<String>,
+
+    /// How the retry delay grows with each failed attempt (fixed, exponential).
+    #[clap(long, default_value = "fixed")]
+    pub retry_backoff: RetryBackoff,
+
+    /// Only run the jobs once these Job IDs have finished successfully (comma separated).
+    #[clap(long, value_delimiter = ',')]
+    pub after: Vec<usize>,
+
+    /// Jobs with a higher priority are run first (default: 0).
+    #[clap(long, default_value = "0", allow_negative_numbers = true)]
+    pub priority: i16,
+
+    /// Don't run the jobs until this date/time (eg: "2024-01-31 02:00", "02:00", or RFC 3339).
+    #[clap(long, conflicts_with = "delay")]
+    pub at: Option<String>,
+
+    /// Don't run the jobs until after (h)ours (m)inutes (s)econds (eg: 1h30m, 30m, 1h5s).
+    #[clap(long)]
+    pub delay: Option<String>,
+
+    /// Run the commands by splitting them into words (exec), or by passing them to the worker's shell (shell).
+    #[clap(long)]
+    pub mode: Option<ExecMode>,
+
+    /// The number of CPUs each job uses, workers with limited CPUs only start jobs that fit (default: 1).
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub cpus: Option<u32>,
+
+    /// The memory each job uses (eg: 512M, 16G), workers with limited memory only start jobs that fit.
+    #[clap(long)]
+    pub mem: Option<String>,
+}
+
+impl JobArgs {
+    pub fn to_options(&self) -> RsrqResult<JobOptions> {
+        let timeout = match &self.timeout {
+            Some(timeout) => Some(parse_duration(timeout)?),
+            None => None,
+        };
+        let retry_delay = match &self.retry_delay {
+            Some(retry_delay) => parse_duration(retry_delay)?,
+            None => 0,
+        };
+        let run_at = match (&self.at, &self.delay) {
+            (Some(at), _) => Some(parse_datetime(at)?),
+            (None, Some(delay)) => Some(get_timestamp_s()? + parse_duration(delay)?),
+            (None, None) => None,
+        };
+        Ok(JobOptions {
+            timeout,
+            max_retries: self.max_retries,
+            retry_delay,
+            retry_backoff: self.retry_backoff,
+            depends_on: self.after.clone(),
+            mode: self.mode,
+            priority: self.priority,
+            run_at,
+            cpus: self.cpus,
+            mem_mb: self.mem.as_deref().map(parse_memory_mb).transpose()?,
+            ..JobOptions::default()
+        })
+    }
+}
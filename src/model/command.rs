@@ -1,21 +1,35 @@
-use lazy_static::lazy_static;
-use regex::Regex;
+use std::collections::BTreeMap;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::task::JoinHandle;
 
 use crate::model::error::RsrqError;
+use crate::model::job::exec_mode::ExecMode;
+use crate::model::output::OutputFiles;
 use crate::model::types::RsrqResult;
+use crate::model::worker::children::{ChildProcesses, ProcessGroupGuard};
 use crate::util::time::get_ms_since;
 
-lazy_static! {
-    static ref RE_CMD: Regex =  Regex::new(r#"("[^"]+"|'[^']+'|\S+)"#).unwrap();
-}
+/// How long to wait for the output of a command that was killed, in case a process outside of its process group
+/// (e.g. one that called setsid) is still holding its pipes open.
+const KILLED_OUTPUT_TIMEOUT: Duration = Duration::from_secs(1);
 
+/// Split the command into words following POSIX shell quoting rules.
+fn parse_cmd(input: &str) -> RsrqResult<Vec<String>> {
+    shlex::split(input).ok_or(RsrqError::CmdParserError(format!("Unbalanced quotes or trailing escape in command: {}", input)))
+}
 
-fn parse_cmd(input: &str) -> Vec<String> {
-    RE_CMD.captures_iter(input)
-        .map(|cap| {
-            cap[1].trim_matches('"').trim_matches('\'').to_string()
-        })
-        .collect()
+/// Check that a command can be run in the given mode, jobs without a mode are checked as they would be run directly.
+/// Commands run through a shell are parsed by the shell, so they are not checked.
+pub fn validate_cmd(cmd: &str, mode: Option<ExecMode>) -> RsrqResult<()> {
+    match mode {
+        Some(ExecMode::Shell) => Ok(()),
+        _ => parse_cmd(cmd).map(|_| ()),
+    }
 }
 
 pub struct RsrqCommandResult {
@@ -24,48 +38,338 @@ pub struct RsrqCommandResult {
     pub stderr: String,
     pub exit_code: i32,
     pub duration_ms: u128,
+    pub timed_out: bool,
 }
 
 pub struct RsrqCommand {
     pub cmd: String,
     pub args: Vec<String>,
+    /// The working directory, if not set the current working directory is used.
+    pub cwd: Option<String>,
+    /// Environment variables that are set in addition to those of the current process.
+    pub env: BTreeMap<String, String>,
+}
+
+/// Read the entire contents of a child process pipe (if present).
+async fn read_pipe<R: AsyncRead + Unpin>(pipe: Option<R>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buf).await;
+    }
+    buf
+}
+
+/// Write a child process pipe to a file, keeping only the last `tail_bytes` in memory.
+/// The pipe is always read to the end (even if the file can't be written) so that the child doesn't block.
+async fn stream_pipe<R: AsyncRead + Unpin>(pipe: Option<R>, path: PathBuf, tail_bytes: usize) -> (Vec<u8>, Option<String>) {
+    let mut tail: Vec<u8> = Vec::new();
+    let mut error: Option<String> = None;
+
+    let mut file = {
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        match tokio::fs::File::create(&path).await {
+            Ok(file) => Some(file),
+            Err(err) => {
+                error = Some(format!("Unable to write output to {}: {}", path.display(), err));
+                None
+            }
+        }
+    };
+
+    if let Some(mut pipe) = pipe {
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = match pipe.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            if let Some(cur_file) = &mut file {
+                if let Err(err) = cur_file.write_all(&buf[..n]).await {
+                    error = Some(format!("Unable to write output to {}: {}", path.display(), err));
+                    file = None;
+                }
+            }
+            tail.extend_from_slice(&buf[..n]);
+            if tail.len() > tail_bytes * 2 + buf.len() {
+                tail.drain(..tail.len() - tail_bytes);
+            }
+        }
+    }
+    if let Some(mut file) = file {
+        let _ = file.flush().await;
+    }
+    if tail.len() > tail_bytes {
+        tail.drain(..tail.len() - tail_bytes);
+    }
+    (tail, error)
+}
+
+/// Read a child process pipe in the background, either into memory or to a file.
+fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(pipe: Option<R>, path: Option<PathBuf>, tail_bytes: usize) -> JoinHandle<(Vec<u8>, Option<String>)> {
+    match path {
+        Some(path) => tokio::spawn(stream_pipe(pipe, path, tail_bytes)),
+        None => tokio::spawn(async move { (read_pipe(pipe).await, None) }),
+    }
+}
+
+/// Wait for a reader to finish, if a limit is given the reader is aborted once it is reached (losing its output).
+async fn join_reader(mut reader: JoinHandle<(Vec<u8>, Option<String>)>, limit: Option<Duration>) -> (Vec<u8>, Option<String>) {
+    match limit {
+        None => reader.await.unwrap_or_default(),
+        Some(limit) => match tokio::time::timeout(limit, &mut reader).await {
+            Ok(result) => result.unwrap_or_default(),
+            Err(_) => {
+                reader.abort();
+                (Vec::new(), Some("The output was not read to the end as the pipe was held open after the command was killed.".to_string()))
+            }
+        },
+    }
 }
 
 impl RsrqCommand {
-    pub fn new(cmd: &str) -> RsrqResult<RsrqCommand> {
-        let parsed_cmd = parse_cmd(cmd);
+    pub fn new(cmd: &str, mode: ExecMode, shell: &str) -> RsrqResult<RsrqCommand> {
+        match mode {
+            ExecMode::Exec => RsrqCommand::exec(cmd),
+            ExecMode::Shell => RsrqCommand::shell(cmd, shell),
+        }
+    }
+
+    /// Run the command directly, without a shell.
+    pub fn exec(cmd: &str) -> RsrqResult<RsrqCommand> {
+        let parsed_cmd = parse_cmd(cmd)?;
         if let Some((command, args)) = parsed_cmd.split_first() {
             return Ok(RsrqCommand {
                 cmd: command.to_string(),
                 args: args.to_vec(),
+                cwd: None,
+                env: BTreeMap::new(),
             });
         }
         Err(RsrqError::CmdParserError(format!("Could not parse command {}", cmd)))
     }
 
-    pub async fn run(&self) -> RsrqCommandResult {
+    /// Run the command via the shell (e.g. "/bin/sh" becomes `/bin/sh -c "<cmd>"`).
+    pub fn shell(cmd: &str, shell: &str) -> RsrqResult<RsrqCommand> {
+        if cmd.trim().is_empty() {
+            return Err(RsrqError::CmdParserError("Could not parse empty command".to_string()));
+        }
+        let mut shell_cmd = RsrqCommand::exec(shell)?;
+        shell_cmd.args.push("-c".to_string());
+        shell_cmd.args.push(cmd.to_string());
+        Ok(shell_cmd)
+    }
+
+    /// Run the command from the working directory (if given), with the additional environment variables.
+    pub fn with_context(mut self, cwd: Option<&str>, env: &BTreeMap<String, String>) -> RsrqCommand {
+        self.cwd = cwd.map(|x| x.to_string());
+        self.env = env.clone();
+        self
+    }
+
+    /// Run the command, the process is killed if it exceeds the timeout (in seconds).
+    /// The command is run in its own process group, so that any processes it starts (e.g. via a shell) are also killed.
+    /// If output files are given, stdout and stderr are written to them and only their tails are returned.
+    /// The PID is recorded in the child processes (if given) while the command is running, and the process group is
+    /// killed if the returned future is dropped before the command finishes (e.g. the job is aborted).
+    pub async fn run(&self, timeout: Option<u64>, output: Option<&OutputFiles>, children: Option<&ChildProcesses>) -> RsrqCommandResult {
         let start_time = std::time::Instant::now();
-        let cmd_result = tokio::process::Command::new(&self.cmd).args(&self.args).output().await;
-        let duration_ms = get_ms_since(&start_time);
-        return match cmd_result {
-            Ok(output) => {
-                RsrqCommandResult {
-                    cmd: self.cmd.to_string(),
-                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                    exit_code: output.status.code().unwrap_or(1),
-                    duration_ms,
-                }
-            }
+        let mut command = std::process::Command::new(&self.cmd);
+        command.args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        let child = tokio::process::Command::from(command).kill_on_drop(true).spawn();
+        let mut child = match child {
+            Ok(child) => child,
             Err(err) => {
-                RsrqCommandResult {
+                let stderr = match &self.cwd {
+                    Some(cwd) => format!("Unable to run {} in {}: {}", self.cmd, cwd, err),
+                    None => err.to_string(),
+                };
+                return RsrqCommandResult {
                     cmd: self.cmd.to_string(),
                     stdout: "".to_string(),
-                    stderr: err.to_string(),
+                    stderr,
                     exit_code: 1,
-                    duration_ms,
+                    duration_ms: get_ms_since(&start_time),
+                    timed_out: false,
+                };
+            }
+        };
+
+        let pid = child.id();
+        let guard = ProcessGroupGuard::new(pid, children);
+
+        // Collect the output in the background to prevent the pipes from filling up
+        let tail_bytes = output.map(|x| x.tail_bytes).unwrap_or_default();
+        let stdout = spawn_reader(child.stdout.take(), output.map(|x| x.stdout.clone()), tail_bytes);
+        let stderr = spawn_reader(child.stderr.take(), output.map(|x| x.stderr.clone()), tail_bytes);
+
+        // Wait for the process to exit, or kill it if the timeout is exceeded
+        let mut timed_out = false;
+        let status = match timeout {
+            Some(timeout) => {
+                match tokio::time::timeout(Duration::from_secs(timeout), child.wait()).await {
+                    Ok(status) => status,
+                    Err(_) => {
+                        timed_out = true;
+                        guard.kill();
+                        let _ = child.start_kill();
+                        child.wait().await
+                    }
                 }
             }
+            None => child.wait().await
         };
+        let duration_ms = get_ms_since(&start_time);
+
+        // Processes that left the process group could hold the pipes open after the command was killed
+        let limit = if timed_out { Some(KILLED_OUTPUT_TIMEOUT) } else { None };
+        let (stdout, stdout_err) = join_reader(stdout, limit).await;
+        let (stderr, stderr_err) = join_reader(stderr, limit).await;
+        guard.disarm();
+        let mut stderr = String::from_utf8_lossy(&stderr).to_string();
+        for err in [stdout_err, stderr_err].into_iter().flatten() {
+            stderr.push_str(&err);
+            stderr.push('\n');
+        }
+        let exit_code = match status {
+            Ok(status) => status.code().unwrap_or(1),
+            Err(err) => {
+                stderr.push_str(&err.to_string());
+                1
+            }
+        };
+        RsrqCommandResult {
+            cmd: self.cmd.to_string(),
+            stdout: String::from_utf8_lossy(&stdout).to_string(),
+            stderr,
+            exit_code,
+            duration_ms,
+            timed_out,
+        }
+    }
+}
+
+#[test]
+fn test_parse_cmd() {
+    assert_eq!(parse_cmd("echo hello").unwrap(), vec!["echo", "hello"]);
+    assert_eq!(parse_cmd("echo 'hello world'").unwrap(), vec!["echo", "hello world"]);
+    assert_eq!(parse_cmd(r#"echo "it's" 'a "b"'"#).unwrap(), vec!["echo", "it's", "a \"b\""]);
+    assert_eq!(parse_cmd(r#"echo a\ b "c\"d""#).unwrap(), vec!["echo", "a b", "c\"d"]);
+    assert_eq!(parse_cmd("echo --x='' y").unwrap(), vec!["echo", "--x=", "y"]);
+    assert!(parse_cmd("echo 'unbalanced").is_err());
+
+    // Commands run through a shell are left to the shell
+    assert!(validate_cmd("echo it's done", None).is_err());
+    assert!(validate_cmd("echo it's done", Some(ExecMode::Exec)).is_err());
+    assert!(validate_cmd("echo it's done", Some(ExecMode::Shell)).is_ok());
+    assert!(validate_cmd("", None).is_ok());
+    assert!(RsrqCommand::exec("   ").is_err());
+}
+
+#[tokio::test]
+async fn test_run_shell() {
+    let result = RsrqCommand::shell("echo hello | tr a-z A-Z && X=1 sh -c 'echo $X'", "/bin/sh").unwrap().run(None, None, None).await;
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, "HELLO\n1\n");
+
+    // Without a shell the pipe is passed as an argument
+    let result = RsrqCommand::exec("echo a | b").unwrap().run(None, None, None).await;
+    assert_eq!(result.stdout, "a | b\n");
+}
+
+#[tokio::test]
+async fn test_run_timeout() {
+    let result = RsrqCommand::exec("sleep 10").unwrap().run(Some(1), None, None).await;
+    assert!(result.timed_out);
+    assert!(result.duration_ms < 10000);
+
+    // Processes started by the shell are also killed, rather than holding the pipes open until they exit
+    let start = std::time::Instant::now();
+    let result = RsrqCommand::shell("sleep 30; echo x", "/bin/sh").unwrap().run(Some(1), None, None).await;
+    assert!(result.timed_out);
+    assert_eq!(result.stdout, "");
+    assert!(start.elapsed() < Duration::from_secs(3));
+
+    let result = RsrqCommand::exec("echo 'hello world'").unwrap().run(Some(10), None, None).await;
+    assert!(!result.timed_out);
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, "hello world\n");
+}
+
+#[tokio::test]
+async fn test_run_aborted() {
+    // The processes started by the shell are killed when the job is aborted
+    let dir = tempfile::tempdir().unwrap();
+    let pid_path = dir.path().join("pid");
+    let children = ChildProcesses::default();
+    let cmd = format!("sleep 30 & echo $! > {}; wait", pid_path.display());
+    let task = {
+        let children = children.clone();
+        tokio::spawn(async move { RsrqCommand::shell(&cmd, "/bin/sh").unwrap().run(None, None, Some(&children)).await })
+    };
+    let mut pid = String::new();
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        pid = std::fs::read_to_string(&pid_path).unwrap_or_default().trim().to_string();
+        if !pid.is_empty() {
+            break;
+        }
     }
-}
\ No newline at end of file
+    assert!(!pid.is_empty());
+    task.abort();
+    let _ = task.await;
+    assert_eq!(children.signal_all(nix::sys::signal::Signal::SIGTERM), 0);
+
+    // The sleep may briefly remain as a zombie until it is reaped
+    let is_running = || {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        stat.rsplit_once(") ").is_some_and(|(_, x)| !x.starts_with('Z'))
+    };
+    for _ in 0..50 {
+        if !is_running() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(!is_running());
+}
+
+#[tokio::test]
+async fn test_run_output_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = OutputFiles::new(dir.path().to_str().unwrap(), "{queue}/{id}.{stream}", 1, "q", 1, 4);
+    let result = RsrqCommand::shell("echo hello world; echo oops >&2", "/bin/sh").unwrap().run(None, Some(&output), None).await;
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, "rld\n");
+    assert_eq!(result.stderr, "ops\n");
+    assert_eq!(std::fs::read_to_string(&output.stdout).unwrap(), "hello world\n");
+    assert_eq!(std::fs::read_to_string(&output.stderr).unwrap(), "oops\n");
+}
+
+#[tokio::test]
+async fn test_run_with_context() {
+    let dir = tempfile::tempdir().unwrap();
+    let cwd = dir.path().canonicalize().unwrap();
+    let env = BTreeMap::from([("RSRQ_TEST_VAR".to_string(), "hello world".to_string())]);
+    let result = RsrqCommand::shell("pwd -P; echo $RSRQ_TEST_VAR", "/bin/sh").unwrap()
+        .with_context(cwd.to_str(), &env)
+        .run(None, None, None).await;
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, format!("{}\nhello world\n", cwd.display()));
+
+    // A working directory that doesn't exist fails the job
+    let result = RsrqCommand::exec("pwd").unwrap()
+        .with_context(Some("/rsrq/does/not/exist"), &BTreeMap::new())
+        .run(None, None, None).await;
+    assert_eq!(result.exit_code, 1);
+    assert!(result.stderr.contains("/rsrq/does/not/exist"));
+}
@@ -0,0 +1,73 @@
+use crate::model::compat::request::META_USER;
+use crate::model::compat::sbatch::script_name;
+use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+
+/// A job that is waiting to run or running, as listed by squeue and qstat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRow {
+    pub id: usize,
+    pub queue: String,
+    pub name: String,
+    pub user: String,
+    pub status: JobStatus,
+    pub cpus: u32,
+    pub priority: i16,
+    pub created: u64,
+    pub started: Option<u64>,
+    pub timeout: Option<u64>,
+    /// The hostname of the worker that is running the job.
+    pub host: Option<String>,
+}
+
+impl JobRow {
+    /// Jobs without a name are named after their command, and the user is only known for those submitted by sbatch or qsub.
+    pub fn from_job(job: &Job, host: Option<String>) -> JobRow {
+        let name = job.name.clone()
+            .unwrap_or_else(|| script_name(job.cmd.split_whitespace().next().unwrap_or_default()));
+        JobRow {
+            id: job.id,
+            queue: job.queue.clone(),
+            name,
+            user: job.metadata.get(META_USER).cloned().unwrap_or("-".to_string()),
+            status: job.status,
+            cpus: job.cpus,
+            priority: job.priority,
+            created: job.created,
+            started: job.started,
+            timeout: job.timeout,
+            host,
+        }
+    }
+
+    /// The number of seconds the job has been running for.
+    pub fn elapsed(&self, now: u64) -> u64 {
+        match (self.status, self.started) {
+            (JobStatus::Running, Some(started)) => now.saturating_sub(started),
+            _ => 0,
+        }
+    }
+}
+
+/// Shorten the value to the width (if given), it is padded on the left if right justified.
+pub fn fit_to_width(value: &str, width: Option<usize>, right: bool) -> String {
+    match width {
+        None => value.to_string(),
+        Some(width) => {
+            let value: String = value.chars().take(width).collect();
+            if right {
+                format!("{:>width$}", value, width = width)
+            } else {
+                format!("{:<width$}", value, width = width)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fit_to_width() {
+    assert_eq!(fit_to_width("abcdef", None, false), "abcdef");
+    assert_eq!(fit_to_width("abcdef", Some(4), false), "abcd");
+    assert_eq!(fit_to_width("ab", Some(4), false), "ab  ");
+    assert_eq!(fit_to_width("ab", Some(4), true), "  ab");
+}
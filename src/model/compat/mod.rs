@@ -0,0 +1,7 @@
+pub mod tool;
+pub mod request;
+pub mod sbatch;
+pub mod qsub;
+pub mod job_row;
+pub mod squeue;
+pub mod qstat;
@@ -0,0 +1,193 @@
+use chrono::TimeZone;
+
+use crate::model::compat::job_row::{fit_to_width, JobRow};
+use crate::model::error::RsrqError;
+use crate::model::job::status::JobStatus;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_id_list;
+
+/// Options that take a value, but don't change which jobs are listed.
+const IGNORED_WITH_VALUE: &[&str] = &["-U", "-l", "-pe"];
+
+/// Options without a value that don't change the output.
+const IGNORED_FLAGS: &[&str] = &["-r", "-t", "-ext", "-g"];
+
+/// The arguments of qstat.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct QstatArgs {
+    /// Show the details of these jobs instead of the table (qstat -j).
+    pub details: Vec<usize>,
+    pub queues: Vec<String>,
+    /// The users whose jobs are listed, all are listed if empty (only jobs submitted by sbatch or qsub have a user).
+    pub users: Vec<String>,
+    /// The states to list ("p" for pending, "r" for running), all are listed if empty.
+    pub states: Vec<char>,
+}
+
+impl QstatArgs {
+    /// Whether the job matches the filters.
+    pub fn includes(&self, row: &JobRow) -> bool {
+        let state = if row.status == JobStatus::Running { 'r' } else { 'p' };
+        (self.queues.is_empty() || self.queues.contains(&row.queue))
+            && (self.users.is_empty() || self.users.contains(&row.user))
+            && (self.states.is_empty() || self.states.contains(&state))
+    }
+}
+
+/// The SGE state of a job that is waiting to run (queued and waiting, or on hold if it depends on other jobs), or running.
+pub fn sge_state(status: JobStatus) -> &'static str {
+    match status {
+        JobStatus::Running => "r",
+        JobStatus::Deferred => "hqw",
+        _ => "qw",
+    }
+}
+
+fn format_sge_timestamp(timestamp: u64, format: &str) -> String {
+    match chrono::Local.timestamp_opt(timestamp as i64, 0).single() {
+        Some(time) => time.format(format).to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+/// The header of the table, followed by a separator.
+pub fn format_qstat_header() -> String {
+    let header = format!(
+        "{:<7} {:<7} {:<10} {:<12} {:<5} {:<19} {:<30} {:>5} {}",
+        "job-ID", "prior", "name", "user", "state", "submit/start at", "queue", "slots", "ja-task-ID"
+    );
+    format!("{}\n{}", header, "-".repeat(header.len()))
+}
+
+/// A line of the table, the priority is normalised to be between 0 and 1 as SGE does.
+/// Running jobs show when they started and the host they are running on, others show when they were submitted.
+pub fn format_qstat_row(row: &JobRow) -> String {
+    let priority = 0.5 + row.priority as f64 / (2.0 * (i16::MAX as f64 + 1.0));
+    let (time, queue) = match (row.status, row.started) {
+        (JobStatus::Running, Some(started)) => (started, format!("{}@{}", row.queue, row.host.as_deref().unwrap_or_default())),
+        _ => (row.created, String::new()),
+    };
+    format!(
+        "{:>7} {:.5} {} {} {:<5} {} {} {:>5}",
+        row.id,
+        priority,
+        fit_to_width(&row.name, Some(10), false),
+        fit_to_width(&row.user, Some(12), false),
+        sge_state(row.status),
+        format_sge_timestamp(time, "%m/%d/%Y %H:%M:%S"),
+        fit_to_width(&queue, Some(30), false),
+        row.cpus,
+    )
+}
+
+/// The details of a job (qstat -j).
+pub fn format_qstat_details(row: &JobRow) -> String {
+    let mut lines = vec![
+        ("job_number", row.id.to_string()),
+        ("job_name", row.name.clone()),
+        ("owner", row.user.clone()),
+        ("hard_queue_list", row.queue.clone()),
+        ("submission_time", format_sge_timestamp(row.created, "%a %b %e %H:%M:%S %Y")),
+        ("job_state", sge_state(row.status).to_string()),
+        ("slots", row.cpus.to_string()),
+        ("priority", row.priority.to_string()),
+    ];
+    if let Some(started) = row.started.filter(|_| row.status == JobStatus::Running) {
+        lines.push(("start_time", format_sge_timestamp(started, "%a %b %e %H:%M:%S %Y")));
+    }
+    if let Some(timeout) = row.timeout {
+        lines.push(("hard resource_list", format!("h_rt={}", timeout)));
+    }
+    let lines: Vec<String> = lines.iter().map(|(key, value)| format!("{:<28}{}", format!("{}:", key), value)).collect();
+    format!("{}\n{}", "=".repeat(62), lines.join("\n"))
+}
+
+/// Parse the arguments of qstat.
+pub fn parse_qstat_args(args: &[String]) -> RsrqResult<QstatArgs> {
+    let mut out = QstatArgs::default();
+    let mut i = 0;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        i += 1;
+        let mut value = || -> RsrqResult<String> {
+            let value = args.get(i).ok_or_else(|| RsrqError::ParserError(format!("Missing value for: {}", flag)))?;
+            i += 1;
+            Ok(value.clone())
+        };
+        match flag {
+            "-j" => out.details.extend(parse_id_list(&value()?)?),
+            "-q" => out.queues.extend(value()?.split(',').map(|x| x.to_string())),
+            "-u" => {
+                // "*" lists the jobs of all users
+                let users: Vec<String> = value()?.split(',').map(|x| x.to_string()).collect();
+                if users.iter().any(|x| x == "*") {
+                    out.users.clear();
+                } else {
+                    out.users.extend(users);
+                }
+            }
+            "-s" => {
+                for state in value()?.chars() {
+                    match state {
+                        'p' | 'r' => out.states.push(state),
+                        'a' => out.states.clear(),
+                        _ => return Err(RsrqError::ParserError(format!("Unsupported state: {}", state))),
+                    }
+                }
+            }
+            _ if IGNORED_FLAGS.contains(&flag) => {}
+            _ if IGNORED_WITH_VALUE.contains(&flag) => {
+                value()?;
+            }
+            _ => return Err(RsrqError::ParserError(format!("Unsupported option: {}", flag))),
+        }
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_format_qstat() {
+    let mut row = JobRow {
+        id: 12,
+        queue: "default".to_string(),
+        name: "a_long_job_name".to_string(),
+        user: "alice".to_string(),
+        status: JobStatus::Queued,
+        cpus: 4,
+        priority: 0,
+        created: 100,
+        started: None,
+        timeout: Some(3600),
+        host: None,
+    };
+    let header = format_qstat_header();
+    assert!(header.starts_with("job-ID  prior   name       user         state submit/start at     queue "));
+
+    // The columns of the rows line up with those of the header
+    let line = format_qstat_row(&row);
+    assert_eq!(line.len(), header.lines().next().unwrap().len() - " ja-task-ID".len());
+    assert!(line.starts_with("     12 0.50000 a_long_job alice        qw    "));
+    assert!(line.ends_with("                                   4"));
+
+    row.status = JobStatus::Running;
+    row.started = Some(1000);
+    row.host = Some("node1".to_string());
+    assert!(format_qstat_row(&row).contains(" r     "));
+    assert!(format_qstat_row(&row).contains(" default@node1 "));
+
+    let details = format_qstat_details(&row);
+    assert!(details.contains("\njob_number:                 12\n"));
+    assert!(details.contains("\nhard resource_list:         h_rt=3600"));
+}
+
+#[test]
+fn test_parse_qstat_args() {
+    let args: Vec<String> = ["-u", "*", "-s", "pr", "-q", "high"].iter().map(|x| x.to_string()).collect();
+    let args = parse_qstat_args(&args).unwrap();
+    assert_eq!(args.states, vec!['p', 'r']);
+    assert_eq!(args.queues, vec!["high".to_string()]);
+    assert!(args.users.is_empty());
+    assert_eq!(parse_qstat_args(&["-u".to_string(), "alice,bob".to_string()]).unwrap().users, vec!["alice", "bob"]);
+    assert_eq!(parse_qstat_args(&["-j".to_string(), "3".to_string()]).unwrap().details, vec![3]);
+    assert!(parse_qstat_args(&["-xml".to_string()]).is_err());
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::model::compat::request::{quote_word, EnvExport, PathPart, SubmitRequest};
+use crate::model::compat::sbatch::script_name;
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::{parse_id_list, parse_memory_with_unit, parse_string};
+
+/// The prefix of the lines in a job script that contain options.
+pub const QSUB_DIRECTIVE: &str = "#$";
+
+/// The environment variables that are set to the id of the job.
+pub const SGE_JOB_ID_VARS: &[&str] = &["JOB_ID"];
+
+/// Resources (-l) that request memory, they are per slot.
+const MEMORY_RESOURCES: &[&str] = &["h_vmem", "s_vmem", "mem_free", "mem", "virtual_free", "h_rss", "m_mem_free"];
+
+/// Resources (-l) that limit the runtime.
+const TIME_RESOURCES: &[&str] = &["h_rt", "s_rt"];
+
+/// Options that take a value, but have no effect on how the job is run by rsrq.
+const IGNORED_WITH_VALUE: &[&str] = &["-m", "-M", "-P", "-A", "-r", "-R", "-now", "-js", "-ckpt", "-c"];
+
+/// Options without a value that have no effect on how the job is run by rsrq.
+const IGNORED_FLAGS: &[&str] = &["-notify", "-cwd"];
+
+/// Parse a time limit ("hours:minutes:seconds", or seconds) into seconds.
+pub fn parse_sge_time(value: &str) -> RsrqResult<Option<u64>> {
+    let invalid = || RsrqError::ParserError(format!("Invalid time limit: {}", value));
+    let parts = value.split(':')
+        .map(|x| if x.is_empty() { Ok(0) } else { parse_string::<u64>(x) })
+        .collect::<RsrqResult<Vec<u64>>>()
+        .map_err(|_| invalid())?;
+    let seconds = match parts.as_slice() {
+        [s] => *s,
+        [h, m, s] => h * 3600 + m * 60 + s,
+        _ => return Err(invalid()),
+    };
+    Ok(Some(seconds).filter(|x| *x > 0))
+}
+
+fn parse_yes_no(flag: &str, value: &str) -> RsrqResult<bool> {
+    match value.to_lowercase().as_str() {
+        "y" | "yes" | "true" => Ok(true),
+        "n" | "no" | "false" => Ok(false),
+        _ => Err(RsrqError::ParserError(format!("Invalid value for {}: {}", flag, value))),
+    }
+}
+
+/// The number of slots of a parallel environment, the lower bound is used for a range (e.g. "4-8").
+fn parse_slots(value: &str) -> RsrqResult<u32> {
+    let lower = value.split('-').next().unwrap_or_default();
+    match parse_string::<u32>(lower) {
+        Ok(slots) if slots > 0 => Ok(slots),
+        _ => Err(RsrqError::ParserError(format!("Invalid number of slots: {}", value))),
+    }
+}
+
+/// SGE memory values without a unit are in bytes.
+const SGE_MEMORY_UNIT_MB: f64 = 1.0 / (1024.0 * 1024.0);
+
+/// Read the memory and runtime from a list of resources (e.g. "h_vmem=4G,h_rt=1:00:00"), the others are ignored.
+fn parse_resources(value: &str, request: &mut SubmitRequest) -> RsrqResult<()> {
+    for resource in value.split(',').filter(|x| !x.is_empty()) {
+        let (key, value) = resource.split_once('=')
+            .ok_or_else(|| RsrqError::ParserError(format!("Invalid resource: {}", resource)))?;
+        if MEMORY_RESOURCES.contains(&key) {
+            request.mem_per_cpu_mb = Some(parse_memory_with_unit(value, SGE_MEMORY_UNIT_MB)?).filter(|x| *x > 0);
+        } else if TIME_RESOURCES.contains(&key) {
+            request.timeout = parse_sge_time(value)?;
+        }
+    }
+    Ok(())
+}
+
+/// The $JOB_ID and $JOB_NAME variables are replaced in output paths, other variables (e.g. $HOME) are read from the
+/// environment of the job. If the path is a directory, the file is named after the job (e.g. "logs/name.o12").
+fn parse_output_path(value: &str, suffix: char) -> Vec<PathPart> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut var = String::new();
+        while c == '$' && chars.peek().is_some_and(|x| x.is_ascii_alphanumeric() || *x == '_') {
+            var.push(chars.next().unwrap_or_default());
+        }
+        if var.is_empty() {
+            text.push(c);
+            continue;
+        }
+        if !text.is_empty() {
+            out.push(PathPart::Text(std::mem::take(&mut text)));
+        }
+        out.push(match var.as_str() {
+            "JOB_ID" => PathPart::JobId,
+            "JOB_NAME" => PathPart::JobName,
+            _ => PathPart::Var(var),
+        });
+    }
+    if Path::new(value).is_dir() {
+        if !value.ends_with('/') {
+            text.push('/');
+        }
+        if !text.is_empty() {
+            out.push(PathPart::Text(std::mem::take(&mut text)));
+        }
+        out.push(PathPart::JobName);
+        out.push(PathPart::Text(format!(".{}", suffix)));
+        out.push(PathPart::JobId);
+    }
+    if !text.is_empty() {
+        out.push(PathPart::Text(text));
+    }
+    out
+}
+
+/// Parse the arguments of qsub (or the options of the "#$" directives, which can't include the script).
+pub fn parse_qsub_args(args: &[String], directives: bool) -> RsrqResult<SubmitRequest> {
+    let mut request = SubmitRequest::default();
+    let mut binary = false;
+    let mut i = 0;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        i += 1;
+
+        // The first argument that isn't an option is the script, anything after it are the arguments of the script
+        if !flag.starts_with('-') {
+            if directives {
+                return Err(RsrqError::ParserError(format!("Unexpected argument in a directive: {}", flag)));
+            }
+            request.script = Some(flag.to_string());
+            request.args = args[i..].to_vec();
+            break;
+        }
+
+        let mut value = || -> RsrqResult<String> {
+            let value = args.get(i).ok_or_else(|| RsrqError::ParserError(format!("Missing value for: {}", flag)))?;
+            i += 1;
+            Ok(value.clone())
+        };
+        match flag {
+            "-N" => request.name = Some(value()?),
+            "-q" => request.queue = Some(value()?),
+            "-pe" => {
+                value()?;
+                request.cpus = Some(parse_slots(&value()?)?);
+            }
+            "-l" => parse_resources(&value()?, &mut request)?,
+            "-o" => request.stdout = Some(parse_output_path(&value()?, 'o')),
+            "-e" => request.stderr = Some(parse_output_path(&value()?, 'e')),
+            "-j" => request.join_output = Some(parse_yes_no(flag, &value()?)?),
+            "-wd" => request.cwd = Some(value()?),
+            "-hold_jid" => request.depends_on = parse_id_list(&value()?)?,
+            "-b" => binary = parse_yes_no(flag, &value()?)?,
+            "-S" => request.interpreter = Some(quote_word(&value()?)?),
+            "-p" => request.priority = Some(parse_string(&value()?)?),
+            "-terse" => request.parsable = true,
+            "-V" => request.export.get_or_insert_with(EnvExport::default).all = true,
+            "-v" => {
+                let vars = EnvExport::parse_vars(&value()?);
+                request.export.get_or_insert_with(EnvExport::default).vars.extend(vars);
+            }
+            _ if IGNORED_FLAGS.contains(&flag) => {}
+            _ if IGNORED_WITH_VALUE.contains(&flag) => {
+                value()?;
+            }
+            _ => return Err(RsrqError::ParserError(format!("Unsupported option: {}", flag))),
+        }
+    }
+
+    // The script is a command that is run as it is
+    if binary {
+        if let Some(script) = request.script.take() {
+            let mut words = vec![quote_word(&script)?];
+            for arg in request.args.drain(..) {
+                words.push(quote_word(&arg)?);
+            }
+            request.name = request.name.or(Some(script_name(&script)));
+            request.wrap = Some(words.join(" "));
+        }
+    }
+    Ok(request)
+}
+
+/// Jobs are named after their script ("STDIN" for scripts read from stdin).
+pub fn apply_sge_defaults(request: &mut SubmitRequest) {
+    if request.name.is_none() {
+        let name = match &request.script {
+            Some(script) => script_name(script),
+            None => "STDIN".to_string(),
+        };
+        request.name = Some(name);
+    }
+}
+
+/// The SGE environment variables of the job (the job id is set by its command).
+pub fn sge_env(request: &SubmitRequest, submit_dir: &str) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    env.insert("SGE_O_WORKDIR".to_string(), submit_dir.to_string());
+    env.insert("NSLOTS".to_string(), request.cpus.unwrap_or(1).to_string());
+    if let Some(name) = &request.name {
+        env.insert("JOB_NAME".to_string(), name.clone());
+    }
+    if let Some(queue) = &request.queue {
+        env.insert("QUEUE".to_string(), queue.clone());
+    }
+    env
+}
+
+#[test]
+fn test_parse_sge_time() {
+    assert_eq!(parse_sge_time("3600").unwrap(), Some(3600));
+    assert_eq!(parse_sge_time("1:30:00").unwrap(), Some(5400));
+    assert_eq!(parse_sge_time("::30").unwrap(), Some(30));
+    assert_eq!(parse_sge_time("0").unwrap(), None);
+    assert!(parse_sge_time("1:30").is_err());
+}
+
+#[test]
+fn test_parse_sge_output_path() {
+    assert_eq!(parse_output_path("$HOME/$JOB_NAME.$JOB_ID.log", 'o'), vec![
+        PathPart::Var("HOME".to_string()), PathPart::Text("/".to_string()), PathPart::JobName,
+        PathPart::Text(".".to_string()), PathPart::JobId, PathPart::Text(".log".to_string()),
+    ]);
+    assert_eq!(parse_output_path("cost$", 'o'), vec![PathPart::Text("cost$".to_string())]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path().to_str().unwrap();
+    assert_eq!(parse_output_path(dir, 'e'), vec![
+        PathPart::Text(format!("{}/", dir)), PathPart::JobName, PathPart::Text(".e".to_string()), PathPart::JobId,
+    ]);
+}
+
+#[test]
+fn test_parse_qsub_args() {
+    let args: Vec<String> = ["-N", "align", "-pe", "smp", "4", "-l", "h_vmem=2G,h_rt=01:00:00,arch=x86", "-cwd",
+        "-j", "y", "-o", "out.log", "-hold_jid", "1,2", "-terse", "-m", "ea", "run.sh", "-N", "x"]
+        .iter().map(|x| x.to_string()).collect();
+    let request = parse_qsub_args(&args, false).unwrap();
+    assert_eq!(request.name.as_deref(), Some("align"));
+    assert_eq!(request.cpus, Some(4));
+    assert_eq!(request.total_mem_mb(), Some(8192));
+    assert_eq!(request.timeout, Some(3600));
+    assert_eq!(request.join_output, Some(true));
+    assert_eq!(request.depends_on, vec![1, 2]);
+    assert!(request.parsable);
+    assert_eq!(request.script.as_deref(), Some("run.sh"));
+    assert_eq!(request.args, vec!["-N", "x"]);
+
+    // Commands are named after their executable
+    let args: Vec<String> = ["-b", "y", "/bin/echo", "hello world"].iter().map(|x| x.to_string()).collect();
+    let mut request = parse_qsub_args(&args, false).unwrap();
+    apply_sge_defaults(&mut request);
+    assert_eq!(request.wrap.as_deref(), Some("/bin/echo 'hello world'"));
+    assert_eq!(request.name.as_deref(), Some("echo"));
+    assert_eq!(request.script, None);
+
+    // -V exports the whole environment, and -v the listed variables
+    let args: Vec<String> = ["-V", "-v", "A=1"].iter().map(|x| x.to_string()).collect();
+    let request = parse_qsub_args(&args, false).unwrap();
+    assert_eq!(request.export, Some(EnvExport { all: true, vars: vec![("A".to_string(), Some("1".to_string()))] }));
+
+    // Memory without a unit is in bytes
+    let request = parse_qsub_args(&["-l".to_string(), "h_vmem=1073741824".to_string()], false).unwrap();
+    assert_eq!(request.total_mem_mb(), Some(1024));
+
+    assert!(parse_qsub_args(&["run.sh".to_string()], true).is_err());
+    assert!(parse_qsub_args(&["-t".to_string(), "1-10".to_string()], false).is_err());
+    assert!(parse_qsub_args(&["-hold_jid".to_string(), "align".to_string()], false).is_err());
+    assert!(parse_qsub_args(&["-j".to_string(), "maybe".to_string()], false).is_err());
+}
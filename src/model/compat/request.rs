@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+
+use crate::config::JOB_ID_ENV;
+use crate::model::error::RsrqError;
+use crate::model::job::exec_mode::ExecMode;
+use crate::model::job::options::JobOptions;
+use crate::model::types::RsrqResult;
+
+/// The metadata key of the user that submitted the job through sbatch or qsub.
+pub const META_USER: &str = "user";
+
+/// The interpreter used for scripts that don't have a shebang (or an SGE -S shell).
+const DEFAULT_INTERPRETER: &str = "/bin/sh";
+
+/// The shell variable holding the path of the copy of the script that is run by the job.
+const SCRIPT_VAR: &str = "RSRQ_SCRIPT";
+
+/// Part of an output path (-o, -e), the job id is only known once the job is running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathPart {
+    Text(String),
+    JobId,
+    JobName,
+    /// An environment variable of the worker (e.g. $HOME).
+    Var(String),
+}
+
+/// The variables of the submitting environment that are set for the job (sbatch --export, qsub -V and -v).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvExport {
+    /// Export all of the variables of the submitting environment.
+    pub all: bool,
+    /// Variables that are exported with the given value, or with their value in the submitting environment.
+    pub vars: Vec<(String, Option<String>)>,
+}
+
+impl EnvExport {
+    /// Parse a list of variables, each with an optional value (e.g. "A,B=1").
+    pub fn parse_vars(value: &str) -> Vec<(String, Option<String>)> {
+        value.split(',')
+            .filter(|x| !x.is_empty())
+            .map(|x| match x.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (x.to_string(), None),
+            })
+            .collect()
+    }
+}
+
+/// A job submitted through sbatch or qsub, the options given on the command line take precedence over the script's directives.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SubmitRequest {
+    pub name: Option<String>,
+    pub queue: Option<String>,
+    pub cpus: Option<u32>,
+    pub mem_mb: Option<u64>,
+    /// The memory is multiplied by the number of CPUs if it was requested per CPU (or SGE slot).
+    pub mem_per_cpu_mb: Option<u64>,
+    pub timeout: Option<u64>,
+    pub priority: Option<i16>,
+    pub cwd: Option<String>,
+    pub stdout: Option<Vec<PathPart>>,
+    pub stderr: Option<Vec<PathPart>>,
+    /// Write stderr to the stdout file, unless a file is given for stderr.
+    pub join_output: Option<bool>,
+    pub depends_on: Vec<usize>,
+    /// The variables of the submitting environment that are set for the job, if not set the scheduler's default is used.
+    pub export: Option<EnvExport>,
+    /// The interpreter of the script, if not set the shebang of the script is used.
+    pub interpreter: Option<String>,
+    /// A command that is run instead of a script (sbatch --wrap, qsub -b y).
+    pub wrap: Option<String>,
+    pub script: Option<String>,
+    /// The contents of the script, they are copied when the job is submitted (as sbatch and qsub do)
+    /// so that the script can be changed or deleted once submitted.
+    pub contents: Option<String>,
+    pub args: Vec<String>,
+    /// Only print the id of the job once it has been submitted.
+    pub parsable: bool,
+}
+
+/// Quote a value for the shell, such that it is a single word.
+pub fn quote_word(value: &str) -> RsrqResult<String> {
+    shlex::try_quote(value)
+        .map(|x| x.to_string())
+        .map_err(|_| RsrqError::ParserError(format!("Unable to quote: {}", value)))
+}
+
+/// Escape the characters that have a special meaning within double quotes.
+fn escape_double_quoted(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '$' | '"' | '`') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The options (as words) of each directive line at the top of the script (e.g. "#SBATCH -c 4").
+/// Directives are read until the first line that is neither blank nor a comment.
+pub fn read_directives(contents: &str, prefix: &str) -> RsrqResult<Vec<String>> {
+    let mut out = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix(prefix) {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                let words = shlex::split(rest)
+                    .ok_or_else(|| RsrqError::ParserError(format!("Invalid directive: {}", line)))?;
+                out.extend(words);
+                continue;
+            }
+        }
+        if !line.is_empty() && !line.starts_with('#') {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+/// The interpreter given by the shebang of the script (e.g. "/usr/bin/env python3").
+pub fn read_shebang(contents: &str) -> Option<String> {
+    let line = contents.lines().next()?;
+    let interpreter = line.strip_prefix("#!")?.trim();
+    if interpreter.is_empty() {
+        None
+    } else {
+        Some(interpreter.to_string())
+    }
+}
+
+impl SubmitRequest {
+    /// Use the directives for anything that wasn't given on the command line.
+    pub fn merge(&mut self, directives: SubmitRequest) {
+        self.name = self.name.take().or(directives.name);
+        self.queue = self.queue.take().or(directives.queue);
+        self.cpus = self.cpus.or(directives.cpus);
+        self.mem_mb = self.mem_mb.or(directives.mem_mb);
+        self.mem_per_cpu_mb = self.mem_per_cpu_mb.or(directives.mem_per_cpu_mb);
+        self.timeout = self.timeout.or(directives.timeout);
+        self.priority = self.priority.or(directives.priority);
+        self.cwd = self.cwd.take().or(directives.cwd);
+        self.stdout = self.stdout.take().or(directives.stdout);
+        self.stderr = self.stderr.take().or(directives.stderr);
+        self.join_output = self.join_output.or(directives.join_output);
+        if self.depends_on.is_empty() {
+            self.depends_on = directives.depends_on;
+        }
+        self.export = self.export.take().or(directives.export);
+        self.interpreter = self.interpreter.take().or(directives.interpreter);
+        self.parsable |= directives.parsable;
+    }
+
+    /// The variables of the submitting environment that are exported to the job, all of them are exported by default
+    /// if export_all is set (as sbatch does).
+    pub fn exported_env(&self, submit_env: &BTreeMap<String, String>, export_all: bool) -> BTreeMap<String, String> {
+        let export = self.export.clone().unwrap_or(EnvExport { all: export_all, vars: Vec::new() });
+        let mut env = if export.all { submit_env.clone() } else { BTreeMap::new() };
+        for (name, value) in export.vars {
+            match value.or_else(|| submit_env.get(&name).cloned()) {
+                Some(value) => env.insert(name, value),
+                None => env.remove(&name),
+            };
+        }
+        env
+    }
+
+    /// The total memory of the job, in MB.
+    pub fn total_mem_mb(&self) -> Option<u64> {
+        self.mem_mb.or(self.mem_per_cpu_mb.map(|x| x * self.cpus.unwrap_or(1) as u64))
+    }
+
+    /// The output path as a double quoted shell word, the job id is read from the environment of the job.
+    fn quote_path(&self, parts: &[PathPart]) -> String {
+        let mut out = String::from("\"");
+        for part in parts {
+            match part {
+                PathPart::Text(text) => out.push_str(&escape_double_quoted(text)),
+                PathPart::JobId => out.push_str(&format!("${{{}}}", JOB_ID_ENV)),
+                PathPart::JobName => out.push_str(&escape_double_quoted(self.name.as_deref().unwrap_or_default())),
+                PathPart::Var(var) => out.push_str(&format!("${{{}}}", var)),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    /// The shell command of the job, the output is redirected to the requested files (if any)
+    /// and each of the variables is set to the id of the job (e.g. SLURM_JOB_ID).
+    pub fn to_command(&self, job_id_vars: &[&str]) -> RsrqResult<String> {
+        let mut lines = Vec::new();
+        if !job_id_vars.is_empty() {
+            let exports: Vec<String> = job_id_vars.iter().map(|x| format!("{}=\"${}\"", x, JOB_ID_ENV)).collect();
+            lines.push(format!("export {}", exports.join(" ")));
+        }
+
+        let stdout = self.stdout.as_ref().map(|x| self.quote_path(x));
+        let stderr = self.stderr.as_ref().map(|x| self.quote_path(x));
+        match (stdout, stderr) {
+            (Some(stdout), Some(stderr)) => lines.push(format!("exec >{} 2>{}", stdout, stderr)),
+            (Some(stdout), None) if self.join_output.unwrap_or(false) => lines.push(format!("exec >{} 2>&1", stdout)),
+            (Some(stdout), None) => lines.push(format!("exec >{}", stdout)),
+            (None, Some(stderr)) => lines.push(format!("exec 2>{}", stderr)),
+            (None, None) => {}
+        }
+
+        // The script is written to a temporary file that is run by its interpreter, and removed once it exits
+        match (&self.wrap, &self.contents) {
+            (Some(wrap), _) => lines.push(wrap.clone()),
+            (None, Some(contents)) => {
+                lines.push(format!("{}=\"$(mktemp)\" || exit 1", SCRIPT_VAR));
+                lines.push(format!("trap 'rm -f \"${}\"' EXIT", SCRIPT_VAR));
+                lines.push(format!("printf '%s' {} >\"${}\"", quote_word(contents)?, SCRIPT_VAR));
+                let mut words = vec![self.interpreter.clone().unwrap_or(DEFAULT_INTERPRETER.to_string())];
+                words.push(format!("\"${}\"", SCRIPT_VAR));
+                for arg in &self.args {
+                    words.push(quote_word(arg)?);
+                }
+                lines.push(words.join(" "));
+            }
+            (None, None) => return Err(RsrqError::ParserError("No script or command was given.".to_string())),
+        }
+        Ok(lines.join("; "))
+    }
+
+    /// The options of the job, the command is always run by a shell as it redirects the output.
+    pub fn to_options(&self, env: BTreeMap<String, String>, metadata: BTreeMap<String, String>) -> JobOptions {
+        JobOptions {
+            timeout: self.timeout,
+            depends_on: self.depends_on.clone(),
+            mode: Some(ExecMode::Shell),
+            priority: self.priority.unwrap_or(0),
+            cwd: self.cwd.clone(),
+            env,
+            name: self.name.clone(),
+            cpus: self.cpus,
+            mem_mb: self.total_mem_mb(),
+            metadata,
+            ..JobOptions::default()
+        }
+    }
+}
+
+#[test]
+fn test_read_directives() {
+    let script = "#!/bin/bash\n\n#SBATCH -J test --mem=4G\n# A comment\n#SBATCH -o 'out file.log'\n#SBATCHX -c 2\necho hi\n#SBATCH -c 8\n";
+    assert_eq!(read_directives(script, "#SBATCH").unwrap(), vec!["-J", "test", "--mem=4G", "-o", "out file.log"]);
+    assert_eq!(read_shebang(script).as_deref(), Some("/bin/bash"));
+    assert!(read_directives("#SBATCH -J 'unclosed\n", "#SBATCH").is_err());
+    assert_eq!(read_shebang("echo hi\n"), None);
+}
+
+#[test]
+fn test_submit_request_to_command() {
+    let mut request = SubmitRequest {
+        name: Some("my job".to_string()),
+        script: Some("/home/user/run job.sh".to_string()),
+        contents: Some("#!/bin/bash\necho $1\n".to_string()),
+        args: vec!["a".to_string(), "$b".to_string()],
+        interpreter: Some("/bin/bash".to_string()),
+        ..SubmitRequest::default()
+    };
+    let run_script = "RSRQ_SCRIPT=\"$(mktemp)\" || exit 1; trap 'rm -f \"$RSRQ_SCRIPT\"' EXIT; \
+        printf '%s' '#!/bin/bash\necho $1\n' >\"$RSRQ_SCRIPT\"; /bin/bash \"$RSRQ_SCRIPT\" a '$b'";
+    assert_eq!(request.to_command(&[]).unwrap(), run_script);
+
+    // The output of SLURM jobs is joined unless a file is given for stderr
+    request.stdout = Some(vec![PathPart::Text("logs/".to_string()), PathPart::JobName, PathPart::Text("-".to_string()), PathPart::JobId, PathPart::Text(".out".to_string())]);
+    request.join_output = Some(true);
+    assert_eq!(
+        request.to_command(&["SLURM_JOB_ID"]).unwrap(),
+        format!("export SLURM_JOB_ID=\"$RSRQ_JOB_ID\"; exec >\"logs/my job-${{RSRQ_JOB_ID}}.out\" 2>&1; {}", run_script)
+    );
+    request.stderr = Some(vec![PathPart::Var("HOME".to_string()), PathPart::Text("/$err".to_string())]);
+    assert!(request.to_command(&[]).unwrap().starts_with("exec >\"logs/my job-${RSRQ_JOB_ID}.out\" 2>\"${HOME}/\\$err\"; "));
+
+    // Commands are run as they are
+    request.wrap = Some("echo hi && sleep 1".to_string());
+    request.stdout = None;
+    request.stderr = None;
+    assert_eq!(request.to_command(&[]).unwrap(), "echo hi && sleep 1");
+
+    request.wrap = None;
+    request.contents = None;
+    assert!(request.to_command(&[]).is_err());
+}
+
+#[test]
+fn test_submit_request_run_script() {
+    // The copy of the script is run by its interpreter, with its arguments
+    let request = SubmitRequest {
+        contents: Some("if [[ -n \"$1\" ]]; then printf '%s|' \"$@\"; fi\n".to_string()),
+        interpreter: Some("bash".to_string()),
+        args: vec!["a b".to_string(), "$c".to_string()],
+        ..SubmitRequest::default()
+    };
+    let output = std::process::Command::new("sh").arg("-c").arg(request.to_command(&[]).unwrap()).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b|$c|");
+}
+
+#[test]
+fn test_submit_request_merge() {
+    let mut request = SubmitRequest { cpus: Some(2), mem_per_cpu_mb: Some(1024), ..SubmitRequest::default() };
+    request.merge(SubmitRequest {
+        name: Some("directive".to_string()),
+        cpus: Some(8),
+        depends_on: vec![1],
+        ..SubmitRequest::default()
+    });
+    assert_eq!(request.name.as_deref(), Some("directive"));
+    assert_eq!(request.cpus, Some(2));
+    assert_eq!(request.depends_on, vec![1]);
+    assert_eq!(request.total_mem_mb(), Some(2048));
+
+    let options = request.to_options(BTreeMap::new(), BTreeMap::new());
+    assert_eq!(options.mem_mb, Some(2048));
+    assert_eq!(options.mode, Some(ExecMode::Shell));
+}
+
+#[test]
+fn test_exported_env() {
+    let submit_env = BTreeMap::from([("A".to_string(), "1".to_string()), ("B".to_string(), "2".to_string())]);
+    let request = SubmitRequest::default();
+    assert_eq!(request.exported_env(&submit_env, true), submit_env);
+    assert!(request.exported_env(&submit_env, false).is_empty());
+
+    // Only the listed variables, with their values in the submitting environment unless a value is given
+    let request = SubmitRequest {
+        export: Some(EnvExport { all: false, vars: EnvExport::parse_vars("A,C=3,D") }),
+        ..SubmitRequest::default()
+    };
+    let env = request.exported_env(&submit_env, true);
+    assert_eq!(env, BTreeMap::from([("A".to_string(), "1".to_string()), ("C".to_string(), "3".to_string())]));
+
+    let request = SubmitRequest { export: Some(EnvExport { all: true, vars: EnvExport::parse_vars("B=x") }), ..SubmitRequest::default() };
+    assert_eq!(request.exported_env(&submit_env, false).get("B").unwrap(), "x");
+}
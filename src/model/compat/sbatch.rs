@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::model::compat::request::{EnvExport, PathPart, SubmitRequest};
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::{parse_memory_mb, parse_string};
+
+/// The prefix of the lines in a batch script that contain options.
+pub const SBATCH_DIRECTIVE: &str = "#SBATCH";
+
+/// The environment variables that are set to the id of the job.
+pub const SLURM_JOB_ID_VARS: &[&str] = &["SLURM_JOB_ID", "SLURM_JOBID"];
+
+/// Options that take a value, but have no effect on how the job is run by rsrq.
+const IGNORED_WITH_VALUE: &[&str] = &[
+    "-A", "--account", "-N", "--nodes", "-n", "--ntasks", "--ntasks-per-node", "-q", "--qos",
+    "--gres", "-G", "--gpus", "--mail-type", "--mail-user", "-C", "--constraint",
+    "-w", "--nodelist", "-x", "--exclude", "--comment", "--reservation", "--signal", "--open-mode",
+];
+
+/// Options without a value that have no effect on how the job is run by rsrq.
+const IGNORED_FLAGS: &[&str] = &["--exclusive", "-Q", "--quiet", "-v", "--verbose", "--requeue", "--no-requeue", "--get-user-env"];
+
+/// Split an option into its flag, and its value if it was given in the same argument (e.g. "--mem=4G", "-c4").
+pub fn split_option(arg: &str) -> (&str, Option<&str>) {
+    if arg.starts_with("--") {
+        match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value)),
+            None => (arg, None),
+        }
+    } else if arg.len() > 2 && arg.is_char_boundary(2) {
+        (&arg[..2], Some(&arg[2..]))
+    } else {
+        (arg, None)
+    }
+}
+
+/// Parse a time limit in one of the SLURM formats ("minutes", "minutes:seconds", "hours:minutes:seconds",
+/// "days-hours", "days-hours:minutes", "days-hours:minutes:seconds") into seconds, zero is unlimited.
+pub fn parse_slurm_time(value: &str) -> RsrqResult<Option<u64>> {
+    if matches!(value.to_lowercase().as_str(), "unlimited" | "infinite") {
+        return Ok(None);
+    }
+    let invalid = || RsrqError::ParserError(format!("Invalid time limit: {}", value));
+    let (days, rest) = match value.split_once('-') {
+        Some((days, rest)) => (Some(parse_string::<u64>(days).map_err(|_| invalid())?), rest),
+        None => (None, value),
+    };
+    let parts = rest.split(':').map(parse_string::<u64>).collect::<RsrqResult<Vec<u64>>>().map_err(|_| invalid())?;
+    let seconds = match (days, parts.as_slice()) {
+        (None, [m]) => m * 60,
+        (None, [m, s]) => m * 60 + s,
+        (None, [h, m, s]) => h * 3600 + m * 60 + s,
+        (Some(d), [h]) => d * 86400 + h * 3600,
+        (Some(d), [h, m]) => d * 86400 + h * 3600 + m * 60,
+        (Some(d), [h, m, s]) => d * 86400 + h * 3600 + m * 60 + s,
+        _ => return Err(invalid()),
+    };
+    Ok(Some(seconds).filter(|x| *x > 0))
+}
+
+/// Memory is in MB unless a unit is given, zero requests all of the memory (i.e. no limit).
+fn parse_slurm_memory(value: &str) -> RsrqResult<Option<u64>> {
+    Ok(Some(parse_memory_mb(value)?).filter(|x| *x > 0))
+}
+
+fn parse_cpus(value: &str) -> RsrqResult<u32> {
+    match parse_string::<u32>(value) {
+        Ok(cpus) if cpus > 0 => Ok(cpus),
+        _ => Err(RsrqError::ParserError(format!("Invalid number of CPUs: {}", value))),
+    }
+}
+
+/// Only "afterok" dependencies are supported, as rsrq runs a job once all of its dependencies have succeeded.
+fn parse_slurm_dependency(value: &str) -> RsrqResult<Vec<usize>> {
+    let mut out = Vec::new();
+    for dependency in value.split([',', '?']).filter(|x| !x.is_empty()) {
+        let mut parts = dependency.split(':');
+        match parts.next() {
+            Some("afterok") => {
+                for job_id in parts {
+                    out.push(parse_string(job_id)?);
+                }
+            }
+            _ => return Err(RsrqError::ParserError(format!("Unsupported dependency: {}", dependency))),
+        }
+    }
+    Ok(out)
+}
+
+/// The %j (job id), %A (array job id), and %x (job name) patterns are replaced in output paths.
+fn parse_output_path(value: &str) -> Vec<PathPart> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let part = match chars.next() {
+            Some('j') | Some('A') => PathPart::JobId,
+            Some('x') => PathPart::JobName,
+            Some('u') => PathPart::Var("USER".to_string()),
+            Some(other) => {
+                if other != '%' {
+                    text.push('%');
+                }
+                text.push(other);
+                continue;
+            }
+            None => {
+                text.push('%');
+                continue;
+            }
+        };
+        if !text.is_empty() {
+            out.push(PathPart::Text(std::mem::take(&mut text)));
+        }
+        out.push(part);
+    }
+    if !text.is_empty() {
+        out.push(PathPart::Text(text));
+    }
+    out
+}
+
+/// Parse the variables to export (e.g. "ALL", "NONE", "ALL,A=1", or "A,B=2"), only those listed are exported
+/// unless ALL is given.
+fn parse_slurm_export(value: &str) -> EnvExport {
+    let mut export = EnvExport::default();
+    for (name, value) in EnvExport::parse_vars(value) {
+        match (name.as_str(), &value) {
+            ("ALL", None) => export.all = true,
+            ("NONE" | "NIL", None) => export.all = false,
+            _ => export.vars.push((name, value)),
+        }
+    }
+    export
+}
+
+/// Parse the arguments of sbatch (or the options of the "#SBATCH" directives, which can't include the script).
+pub fn parse_sbatch_args(args: &[String], directives: bool) -> RsrqResult<SubmitRequest> {
+    let mut request = SubmitRequest::default();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        i += 1;
+
+        // The first argument that isn't an option is the script, anything after it are the arguments of the script
+        if !arg.starts_with('-') || arg == "-" {
+            if directives {
+                return Err(RsrqError::ParserError(format!("Unexpected argument in a directive: {}", arg)));
+            }
+            request.script = Some(arg.clone());
+            request.args = args[i..].to_vec();
+            break;
+        }
+
+        let (flag, inline) = split_option(arg);
+        let mut value = || -> RsrqResult<String> {
+            if let Some(value) = inline {
+                return Ok(value.to_string());
+            }
+            let value = args.get(i).ok_or_else(|| RsrqError::ParserError(format!("Missing value for: {}", flag)))?;
+            i += 1;
+            Ok(value.clone())
+        };
+        match flag {
+            "-J" | "--job-name" => request.name = Some(value()?),
+            "-p" | "--partition" => request.queue = Some(value()?),
+            "-c" | "--cpus-per-task" => request.cpus = Some(parse_cpus(&value()?)?),
+            "--mem" => request.mem_mb = parse_slurm_memory(&value()?)?,
+            "--mem-per-cpu" => request.mem_per_cpu_mb = parse_slurm_memory(&value()?)?,
+            "-t" | "--time" => request.timeout = parse_slurm_time(&value()?)?,
+            "-o" | "--output" => request.stdout = Some(parse_output_path(&value()?)),
+            "-e" | "--error" => request.stderr = Some(parse_output_path(&value()?)),
+            "-D" | "--chdir" => request.cwd = Some(value()?),
+            "-d" | "--dependency" => request.depends_on = parse_slurm_dependency(&value()?)?,
+            "--wrap" => request.wrap = Some(value()?),
+            "--export" => request.export = Some(parse_slurm_export(&value()?)),
+            "--parsable" => request.parsable = true,
+            _ if IGNORED_FLAGS.contains(&flag) => {}
+            _ if IGNORED_WITH_VALUE.contains(&flag) => {
+                value()?;
+            }
+            _ => return Err(RsrqError::ParserError(format!("Unsupported option: {}", flag))),
+        }
+    }
+    Ok(request)
+}
+
+/// Jobs are named after their script ("wrap" for commands, "sbatch" for scripts read from stdin),
+/// and stderr is written to the stdout file unless a file is given for it.
+pub fn apply_slurm_defaults(request: &mut SubmitRequest) {
+    if request.name.is_none() {
+        let name = match (&request.wrap, &request.script) {
+            (Some(_), _) => "wrap".to_string(),
+            (None, Some(script)) => script_name(script),
+            (None, None) => "sbatch".to_string(),
+        };
+        request.name = Some(name);
+    }
+    request.join_output = Some(true);
+}
+
+/// The file name of the script.
+pub fn script_name(script: &str) -> String {
+    Path::new(script).file_name().map_or(script.to_string(), |x| x.to_string_lossy().to_string())
+}
+
+/// The SLURM environment variables of the job (the job id is set by its command).
+pub fn slurm_env(request: &SubmitRequest, submit_dir: &str) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    env.insert("SLURM_SUBMIT_DIR".to_string(), submit_dir.to_string());
+    env.insert("SLURM_CPUS_PER_TASK".to_string(), request.cpus.unwrap_or(1).to_string());
+    if let Some(name) = &request.name {
+        env.insert("SLURM_JOB_NAME".to_string(), name.clone());
+    }
+    if let Some(queue) = &request.queue {
+        env.insert("SLURM_JOB_PARTITION".to_string(), queue.clone());
+    }
+    env
+}
+
+#[test]
+fn test_parse_slurm_time() {
+    assert_eq!(parse_slurm_time("30").unwrap(), Some(1800));
+    assert_eq!(parse_slurm_time("30:15").unwrap(), Some(1815));
+    assert_eq!(parse_slurm_time("1:30:00").unwrap(), Some(5400));
+    assert_eq!(parse_slurm_time("2-12").unwrap(), Some(216000));
+    assert_eq!(parse_slurm_time("1-0:30").unwrap(), Some(88200));
+    assert_eq!(parse_slurm_time("1-00:00:10").unwrap(), Some(86410));
+    assert_eq!(parse_slurm_time("0").unwrap(), None);
+    assert_eq!(parse_slurm_time("UNLIMITED").unwrap(), None);
+    assert!(parse_slurm_time("1:2:3:4").is_err());
+    assert!(parse_slurm_time("1h").is_err());
+}
+
+#[test]
+fn test_parse_slurm_output_path() {
+    assert_eq!(parse_output_path("slurm-%j.out"), vec![
+        PathPart::Text("slurm-".to_string()), PathPart::JobId, PathPart::Text(".out".to_string()),
+    ]);
+    assert_eq!(parse_output_path("%x_100%%_%n%"), vec![
+        PathPart::JobName, PathPart::Text("_100%_%n%".to_string()),
+    ]);
+}
+
+#[test]
+fn test_parse_sbatch_args() {
+    let args: Vec<String> = ["-J", "align", "-c4", "--mem=16G", "--time=1:00:00", "-o", "logs/%j.out", "--parsable",
+        "-A", "lab", "--exclusive", "-d", "afterok:1:2", "run.sh", "-c", "sample1"]
+        .iter().map(|x| x.to_string()).collect();
+    let request = parse_sbatch_args(&args, false).unwrap();
+    assert_eq!(request.name.as_deref(), Some("align"));
+    assert_eq!(request.cpus, Some(4));
+    assert_eq!(request.mem_mb, Some(16384));
+    assert_eq!(request.timeout, Some(3600));
+    assert_eq!(request.stdout, Some(vec![PathPart::Text("logs/".to_string()), PathPart::JobId, PathPart::Text(".out".to_string())]));
+    assert_eq!(request.depends_on, vec![1, 2]);
+    assert!(request.parsable);
+    assert_eq!(request.script.as_deref(), Some("run.sh"));
+    assert_eq!(request.args, vec!["-c", "sample1"]);
+
+    // Directives can't include the script, and unknown options are rejected
+    // Only the listed variables are exported, unless ALL is given
+    let request = parse_sbatch_args(&["--export=A,B=1".to_string()], false).unwrap();
+    assert_eq!(request.export, Some(EnvExport { all: false, vars: vec![("A".to_string(), None), ("B".to_string(), Some("1".to_string()))] }));
+    assert!(parse_sbatch_args(&["--export=ALL".to_string()], false).unwrap().export.unwrap().all);
+    assert!(!parse_sbatch_args(&["--export=NONE".to_string()], false).unwrap().export.unwrap().all);
+
+    let args: Vec<String> = ["--wrap", "echo hi", "-p", "high"].iter().map(|x| x.to_string()).collect();
+    let mut request = parse_sbatch_args(&args, true).unwrap();
+    assert_eq!(request.wrap.as_deref(), Some("echo hi"));
+    assert_eq!(request.queue.as_deref(), Some("high"));
+    apply_slurm_defaults(&mut request);
+    assert_eq!(request.name.as_deref(), Some("wrap"));
+    assert!(parse_sbatch_args(&["run.sh".to_string()], true).is_err());
+    assert!(parse_sbatch_args(&["--array=1-10".to_string()], false).is_err());
+    assert!(parse_sbatch_args(&["-d".to_string(), "afterany:1".to_string()], false).is_err());
+    assert!(parse_sbatch_args(&["-c".to_string()], false).is_err());
+}
@@ -0,0 +1,272 @@
+use chrono::TimeZone;
+
+use crate::model::compat::job_row::{fit_to_width, JobRow};
+use crate::model::compat::sbatch::split_option;
+use crate::model::error::RsrqError;
+use crate::model::job::status::JobStatus;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_id_list;
+
+/// The format used by squeue when none is given.
+pub const DEFAULT_FORMAT: &str = "%.18i %.9P %.8j %.8u %.2t %.10M %.6D %R";
+
+/// Options that take a value, but don't change which jobs are listed (rsrq doesn't store the account).
+const IGNORED_WITH_VALUE: &[&str] = &["-A", "--account", "-M", "--clusters", "-q", "--qos"];
+
+/// Options without a value that don't change the output.
+const IGNORED_FLAGS: &[&str] = &["-a", "--all", "-l", "--long", "-r", "--array"];
+
+/// The arguments of squeue.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SqueueArgs {
+    pub job_ids: Vec<usize>,
+    pub queues: Vec<String>,
+    /// The users whose jobs are listed, all are listed if empty (only jobs submitted by sbatch or qsub have a user).
+    pub users: Vec<String>,
+    /// The compact states to list (e.g. "PD", "R"), all are listed if empty.
+    pub states: Vec<String>,
+    pub header: bool,
+    pub format: String,
+}
+
+impl Default for SqueueArgs {
+    fn default() -> Self {
+        SqueueArgs {
+            job_ids: Vec::new(),
+            queues: Vec::new(),
+            users: Vec::new(),
+            states: Vec::new(),
+            header: true,
+            format: DEFAULT_FORMAT.to_string(),
+        }
+    }
+}
+
+impl SqueueArgs {
+    /// Whether the job matches the filters.
+    pub fn includes(&self, row: &JobRow) -> bool {
+        (self.job_ids.is_empty() || self.job_ids.contains(&row.id))
+            && (self.queues.is_empty() || self.queues.contains(&row.queue))
+            && (self.users.is_empty() || self.users.contains(&row.user))
+            && (self.states.is_empty() || self.states.iter().any(|x| x == slurm_state(row.status).0))
+    }
+}
+
+/// The compact and long SLURM states of a job that is waiting to run or running.
+pub fn slurm_state(status: JobStatus) -> (&'static str, &'static str) {
+    match status {
+        JobStatus::Running => ("R", "RUNNING"),
+        JobStatus::Queued | JobStatus::Delayed | JobStatus::Deferred | JobStatus::Scheduled => ("PD", "PENDING"),
+        JobStatus::Finished => ("CD", "COMPLETED"),
+        JobStatus::Failed => ("F", "FAILED"),
+        JobStatus::Cancelled => ("CA", "CANCELLED"),
+        JobStatus::TimedOut => ("TO", "TIMEOUT"),
+    }
+}
+
+/// Why a job is waiting to run.
+fn slurm_reason(status: JobStatus) -> &'static str {
+    match status {
+        JobStatus::Deferred => "Dependency",
+        JobStatus::Delayed | JobStatus::Scheduled => "BeginTime",
+        _ => "None",
+    }
+}
+
+/// Format a number of seconds as SLURM does ("M:SS", "H:MM:SS", or "D-HH:MM:SS").
+pub fn format_slurm_time(seconds: u64) -> String {
+    let (days, hours, mins, secs) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60);
+    if days > 0 {
+        format!("{}-{:02}:{:02}:{:02}", days, hours, mins, secs)
+    } else if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, mins, secs)
+    } else {
+        format!("{}:{:02}", mins, secs)
+    }
+}
+
+fn format_slurm_timestamp(timestamp: Option<u64>) -> String {
+    match timestamp.and_then(|x| chrono::Local.timestamp_opt(x as i64, 0).single()) {
+        Some(time) => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        None => "N/A".to_string(),
+    }
+}
+
+/// The header of a field of the format (e.g. "JOBID" for "i").
+fn field_header(field: char) -> &'static str {
+    match field {
+        'i' => "JOBID",
+        'P' => "PARTITION",
+        'j' => "NAME",
+        'u' => "USER",
+        't' => "ST",
+        'T' => "STATE",
+        'M' => "TIME",
+        'l' => "TIME_LIMIT",
+        'D' => "NODES",
+        'C' => "CPUS",
+        'R' => "NODELIST(REASON)",
+        'N' => "NODELIST",
+        'r' => "REASON",
+        'Q' => "PRIORITY",
+        'V' => "SUBMIT_TIME",
+        'S' => "START_TIME",
+        _ => "N/A",
+    }
+}
+
+/// The value of a field of the format for the job (e.g. the job id for "i").
+fn field_value(field: char, row: &JobRow, now: u64) -> String {
+    let (state, long_state) = slurm_state(row.status);
+    match field {
+        'i' => row.id.to_string(),
+        'P' => row.queue.clone(),
+        'j' => row.name.clone(),
+        'u' => row.user.clone(),
+        't' => state.to_string(),
+        'T' => long_state.to_string(),
+        'M' => format_slurm_time(row.elapsed(now)),
+        'l' => row.timeout.map_or("UNLIMITED".to_string(), format_slurm_time),
+        'D' => "1".to_string(),
+        'C' => row.cpus.to_string(),
+        'R' => match row.status {
+            JobStatus::Running => row.host.clone().unwrap_or_default(),
+            _ => format!("({})", slurm_reason(row.status)),
+        },
+        'N' => row.host.clone().unwrap_or_default(),
+        'r' => slurm_reason(row.status).to_string(),
+        'Q' => row.priority.to_string(),
+        'V' => format_slurm_timestamp(Some(row.created)),
+        'S' => format_slurm_timestamp(row.started),
+        _ => "N/A".to_string(),
+    }
+}
+
+/// Format the header (if no job is given), or a line for the job, using a squeue format (e.g. "%.18i %.9P %j").
+/// A field is shortened to its width, and is right justified if the width is preceded by a ".".
+pub fn format_squeue(format: &str, row: Option<&JobRow>, now: u64) -> String {
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let right = chars.next_if_eq(&'.').is_some();
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(|x| x.is_ascii_digit()) {
+            width.push(digit);
+        }
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some(field) => {
+                let value = match row {
+                    Some(row) => field_value(field, row, now),
+                    None => field_header(field).to_string(),
+                };
+                out.push_str(&fit_to_width(&value, width.parse().ok(), right));
+            }
+            None => out.push('%'),
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// Parse the arguments of squeue.
+pub fn parse_squeue_args(args: &[String]) -> RsrqResult<SqueueArgs> {
+    let mut out = SqueueArgs::default();
+    let mut i = 0;
+    while i < args.len() {
+        let (flag, inline) = split_option(&args[i]);
+        i += 1;
+        let mut value = || -> RsrqResult<String> {
+            if let Some(value) = inline {
+                return Ok(value.to_string());
+            }
+            let value = args.get(i).ok_or_else(|| RsrqError::ParserError(format!("Missing value for: {}", flag)))?;
+            i += 1;
+            Ok(value.clone())
+        };
+        match flag {
+            "-j" | "--jobs" => out.job_ids.extend(parse_id_list(&value()?)?),
+            "-p" | "--partition" => out.queues.extend(value()?.split(',').map(|x| x.to_string())),
+            "-u" | "--user" => out.users.extend(value()?.split(',').map(|x| x.to_string())),
+            "--me" => out.users.push(std::env::var("USER").unwrap_or_default()),
+            "-t" | "--states" => {
+                for state in value()?.split(',') {
+                    match state.to_uppercase().as_str() {
+                        "ALL" => out.states.clear(),
+                        "PD" | "PENDING" => out.states.push("PD".to_string()),
+                        "R" | "RUNNING" => out.states.push("R".to_string()),
+                        other => out.states.push(other.to_string()),
+                    }
+                }
+            }
+            "-h" | "--noheader" => out.header = false,
+            "-o" | "--format" => out.format = value()?,
+            _ if IGNORED_FLAGS.contains(&flag) => {}
+            _ if IGNORED_WITH_VALUE.contains(&flag) => {
+                value()?;
+            }
+            _ => return Err(RsrqError::ParserError(format!("Unsupported option: {}", flag))),
+        }
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_format_slurm_time() {
+    assert_eq!(format_slurm_time(0), "0:00");
+    assert_eq!(format_slurm_time(65), "1:05");
+    assert_eq!(format_slurm_time(3725), "1:02:05");
+    assert_eq!(format_slurm_time(90061), "1-01:01:01");
+}
+
+#[test]
+fn test_format_squeue() {
+    let mut row = JobRow {
+        id: 12,
+        queue: "default".to_string(),
+        name: "alignment".to_string(),
+        user: "alice".to_string(),
+        status: JobStatus::Running,
+        cpus: 4,
+        priority: 0,
+        created: 100,
+        started: Some(1000),
+        timeout: Some(3600),
+        host: Some("node1".to_string()),
+    };
+    assert_eq!(
+        format_squeue(DEFAULT_FORMAT, None, 0),
+        "             JOBID PARTITION     NAME     USER ST       TIME  NODES NODELIST(REASON)"
+    );
+    assert_eq!(
+        format_squeue(DEFAULT_FORMAT, Some(&row), 1065),
+        "                12   default alignmen    alice  R       1:05      1 node1"
+    );
+    assert_eq!(format_squeue("%i|%T|%l|%C|%%", Some(&row), 1065), "12|RUNNING|1:00:00|4|%");
+
+    row.status = JobStatus::Deferred;
+    assert_eq!(format_squeue("%t %M %R", Some(&row), 1065), "PD 0:00 (Dependency)");
+
+    // Jobs are listed for the given users
+    assert!(SqueueArgs { users: vec!["alice".to_string()], ..SqueueArgs::default() }.includes(&row));
+    assert!(!SqueueArgs { users: vec!["bob".to_string()], ..SqueueArgs::default() }.includes(&row));
+}
+
+#[test]
+fn test_parse_squeue_args() {
+    let args: Vec<String> = ["-j", "1,2", "--partition=high", "-t", "pending", "-h", "-u", "alice", "-o", "%i %t"]
+        .iter().map(|x| x.to_string()).collect();
+    let args = parse_squeue_args(&args).unwrap();
+    assert_eq!(args, SqueueArgs {
+        job_ids: vec![1, 2],
+        queues: vec!["high".to_string()],
+        users: vec!["alice".to_string()],
+        states: vec!["PD".to_string()],
+        header: false,
+        format: "%i %t".to_string(),
+    });
+    assert!(parse_squeue_args(&["--iterate=5".to_string()]).is_err());
+}
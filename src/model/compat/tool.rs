@@ -0,0 +1,71 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// A SLURM or SGE command that rsrq can act as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatTool {
+    /// Submit a batch script (SLURM).
+    Sbatch,
+    /// List the pending and running jobs (SLURM).
+    Squeue,
+    /// Cancel jobs (SLURM).
+    Scancel,
+    /// Submit a batch script (SGE).
+    Qsub,
+    /// List the pending and running jobs (SGE).
+    Qstat,
+    /// Cancel jobs (SGE).
+    Qdel,
+}
+
+impl CompatTool {
+    pub fn from_string(value: &str) -> RsrqResult<CompatTool> {
+        match value {
+            "sbatch" => Ok(CompatTool::Sbatch),
+            "squeue" => Ok(CompatTool::Squeue),
+            "scancel" => Ok(CompatTool::Scancel),
+            "qsub" => Ok(CompatTool::Qsub),
+            "qstat" => Ok(CompatTool::Qstat),
+            "qdel" => Ok(CompatTool::Qdel),
+            _ => Err(RsrqError::ParserError(format!("Unsupported command: {}", value))),
+        }
+    }
+
+    /// The tool that rsrq acts as when the executable is called by that name (e.g. a symlink named "sbatch").
+    pub fn from_program(program: &str) -> Option<CompatTool> {
+        let name = Path::new(program).file_name()?.to_str()?;
+        CompatTool::from_string(name).ok()
+    }
+}
+
+impl fmt::Display for CompatTool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompatTool::Sbatch => write!(f, "sbatch"),
+            CompatTool::Squeue => write!(f, "squeue"),
+            CompatTool::Scancel => write!(f, "scancel"),
+            CompatTool::Qsub => write!(f, "qsub"),
+            CompatTool::Qstat => write!(f, "qstat"),
+            CompatTool::Qdel => write!(f, "qdel"),
+        }
+    }
+}
+
+impl std::str::FromStr for CompatTool {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CompatTool::from_string(s)
+    }
+}
+
+#[test]
+fn test_compat_tool_from_program() {
+    assert_eq!(CompatTool::from_program("/usr/local/bin/sbatch"), Some(CompatTool::Sbatch));
+    assert_eq!(CompatTool::from_program("qdel"), Some(CompatTool::Qdel));
+    assert_eq!(CompatTool::from_program("/usr/local/bin/rsrq"), None);
+    assert_eq!(CompatTool::from_program(""), None);
+}
@@ -1,34 +1,212 @@
 use std::io::BufRead;
-use log::error;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::model::command::validate_cmd;
+use crate::model::enqueue_format::EnqueueFormat;
+use crate::model::error::RsrqError;
+use crate::model::job::exec_mode::ExecMode;
+use crate::model::job::options::JobOptions;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::{parse_id_list, parse_memory_mb};
+use crate::util::time::parse_duration;
+
+/// Lines prefixed with this directive depend on the specified Job IDs (e.g. "@after=1,2 echo hello").
+const AFTER_DIRECTIVE: &str = "@after=";
+
+/// A single job that was read from the file.
+pub struct EnqueueEntry {
+    pub cmd: String,
+    pub options: JobOptions,
+}
 
 pub struct EnqueueFile {
-    pub jobs: Vec<String>,
+    pub jobs: Vec<EnqueueEntry>,
 }
 
-impl EnqueueFile {
-    pub fn load(filename: &str) -> EnqueueFile {
-        let mut jobs: Vec<String> = Vec::new();
-        let file = match std::fs::File::open(filename) {
-            Ok(file) => file,
-            Err(error) => {
-                error!("Unable to open file: {}", error);
-                std::process::exit(1);
+/// Split a line into the command and any Job IDs that it depends on.
+fn parse_line(line: &str) -> RsrqResult<(String, Vec<usize>)> {
+    if let Some(rest) = line.strip_prefix(AFTER_DIRECTIVE) {
+        let (ids, cmd) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let depends_on = parse_id_list(ids)?;
+        return Ok((cmd.trim_start().to_string(), depends_on));
+    }
+    Ok((line.to_string(), Vec::new()))
+}
+
+/// Read a JSON object describing a job (e.g. {"cmd": "echo hello", "cwd": "/tmp", "env": {"A": "1"}}),
+/// the fields that are set take precedence over the options. A relative working directory is resolved against
+/// the directory the jobs are enqueued from, as workers may run in another directory (or on another host).
+fn parse_json_line(line: &str, options: &JobOptions, submit_dir: &Path) -> RsrqResult<EnqueueEntry> {
+    let value: Value = serde_json::from_str(line).map_err(|e| RsrqError::ParserError(format!("Invalid JSON: {}", e)))?;
+    let fields = value.as_object().ok_or(RsrqError::ParserError("Expected a JSON object.".to_string()))?;
+    let invalid = |key: &str, expected: &str| RsrqError::ParserError(format!("The field \"{}\" must be {}.", key, expected));
+
+    let mut cmd: Option<String> = None;
+    let mut job_options = options.clone();
+    for (key, value) in fields {
+        match key.as_str() {
+            "cmd" => cmd = Some(value.as_str().ok_or(invalid(key, "a string"))?.to_string()),
+            "cwd" => {
+                let cwd = value.as_str().ok_or(invalid(key, "a string"))?;
+                job_options.cwd = Some(submit_dir.join(cwd).to_string_lossy().to_string());
+            }
+            "name" => job_options.name = Some(value.as_str().ok_or(invalid(key, "a string"))?.to_string()),
+            "env" => {
+                let env: &Map<String, Value> = value.as_object().ok_or(invalid(key, "an object"))?;
+                for (name, value) in env {
+                    let value = value.as_str().ok_or(invalid(key, "an object of strings"))?;
+                    job_options.env.insert(name.to_string(), value.to_string());
+                }
+            }
+            "tags" => {
+                for tag in value.as_array().ok_or(invalid(key, "an array"))? {
+                    let tag = tag.as_str().filter(|x| !x.is_empty() && !x.contains(',')).ok_or(invalid(key, "an array of strings (without commas)"))?;
+                    job_options.tags.push(tag.to_string());
+                }
+            }
+            "timeout" => {
+                job_options.timeout = match value {
+                    Value::Number(secs) => Some(secs.as_u64().ok_or(invalid(key, "a number of seconds or a duration"))?),
+                    Value::String(duration) => Some(parse_duration(duration)?),
+                    _ => return Err(invalid(key, "a number of seconds or a duration")),
+                }
+            }
+            "priority" => {
+                let priority = value.as_i64().and_then(|x| i16::try_from(x).ok());
+                job_options.priority = priority.ok_or(invalid(key, "an integer"))?;
+            }
+            "max_retries" => {
+                let max_retries = value.as_u64().and_then(|x| u32::try_from(x).ok());
+                job_options.max_retries = max_retries.ok_or(invalid(key, "a positive integer"))?;
+            }
+            "after" => {
+                for id in value.as_array().ok_or(invalid(key, "an array of Job IDs"))? {
+                    let id = id.as_u64().ok_or(invalid(key, "an array of Job IDs"))?;
+                    job_options.depends_on.push(id as usize);
+                }
+            }
+            "cpus" => {
+                let cpus = value.as_u64().and_then(|x| u32::try_from(x).ok()).filter(|x| *x > 0);
+                job_options.cpus = Some(cpus.ok_or(invalid(key, "a positive integer"))?);
+            }
+            "mem_mb" => {
+                job_options.mem_mb = match value {
+                    Value::Number(mem_mb) => Some(mem_mb.as_u64().ok_or(invalid(key, "a number of MB or an amount (e.g. 16G)"))?),
+                    Value::String(mem) => Some(parse_memory_mb(mem)?),
+                    _ => return Err(invalid(key, "a number of MB or an amount (e.g. 16G)")),
+                }
             }
-        };
+            "mode" => job_options.mode = Some(ExecMode::from_string(value.as_str().ok_or(invalid(key, "a string"))?)?),
+            _ => return Err(RsrqError::ParserError(format!("Unknown field: {}", key))),
+        }
+    }
+    let cmd = cmd.ok_or(RsrqError::ParserError("The field \"cmd\" is required.".to_string()))?;
+    Ok(EnqueueEntry {
+        cmd,
+        options: job_options,
+    })
+}
+
+impl EnqueueFile {
+    /// Read the jobs from the file, the options are applied to each job.
+    /// Either one command per line, or one JSON object per line (blank lines are ignored).
+    pub fn load(filename: &str, format: EnqueueFormat, options: &JobOptions) -> RsrqResult<EnqueueFile> {
+        let mut jobs: Vec<EnqueueEntry> = Vec::new();
+        let file = std::fs::File::open(filename).map_err(RsrqError::FileNotFound)?;
+        let submit_dir = std::env::current_dir().map_err(RsrqError::IOError)?;
 
         let reader = std::io::BufReader::new(file);
-        for line in reader.lines() {
-            let job_str = match line {
-                Ok(line) => line,
-                Err(error) => {
-                    error!("Unable to read line: {}", error);
-                    std::process::exit(1);
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.map_err(RsrqError::FileReadError)?;
+            let entry = match format {
+                EnqueueFormat::Lines => {
+                    let (cmd, depends_on) = parse_line(&line)?;
+                    let mut job_options = options.clone();
+                    job_options.depends_on.extend(depends_on);
+                    EnqueueEntry {
+                        cmd,
+                        options: job_options,
+                    }
+                }
+                EnqueueFormat::Jsonl => {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    parse_json_line(&line, options, &submit_dir)
+                        .map_err(|e| RsrqError::ParserError(format!("Line {}: {}", idx + 1, e)))?
                 }
             };
-            jobs.push(job_str);
+
+            // The whole file is rejected before any job is created if a command can't be parsed
+            validate_cmd(&entry.cmd, entry.options.mode)
+                .map_err(|e| RsrqError::ParserError(format!("Line {}: {}", idx + 1, e)))?;
+            jobs.push(entry);
         }
-        EnqueueFile {
+        Ok(EnqueueFile {
             jobs
-        }
+        })
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_line() {
+    assert_eq!(parse_line("echo 'hello'").unwrap(), ("echo 'hello'".to_string(), vec![]));
+    assert_eq!(parse_line("@after=1,2 echo 'hello'").unwrap(), ("echo 'hello'".to_string(), vec![1, 2]));
+    assert_eq!(parse_line("@after=3   echo").unwrap(), ("echo".to_string(), vec![3]));
+    assert_eq!(parse_line("@after=3").unwrap(), ("".to_string(), vec![3]));
+    assert!(parse_line("@after=x echo").is_err());
+}
+
+#[test]
+fn test_parse_json_line() {
+    let options = JobOptions { timeout: Some(60), priority: 1, ..JobOptions::default() };
+    let submit_dir = Path::new("/home/user");
+
+    let entry = parse_json_line(r#"{"cmd": "echo hi", "cwd": "/tmp", "env": {"A": "1"}, "timeout": "1h", "name": "a", "tags": ["x", "y"]}"#, &options, submit_dir).unwrap();
+    assert_eq!(entry.cmd, "echo hi");
+    assert_eq!(entry.options.cwd.as_deref(), Some("/tmp"));
+    assert_eq!(entry.options.env.get("A").unwrap(), "1");
+    assert_eq!(entry.options.timeout, Some(3600));
+    assert_eq!(entry.options.name.as_deref(), Some("a"));
+    assert_eq!(entry.options.tags, vec!["x", "y"]);
+    assert_eq!(entry.options.priority, 1);
+
+    // Only the fields that are set replace the options
+    let entry = parse_json_line(r#"{"cmd": "echo", "timeout": 5, "priority": -2, "after": [1, 2], "mode": "shell"}"#, &options, submit_dir).unwrap();
+    assert_eq!(entry.options.timeout, Some(5));
+    assert_eq!(entry.options.priority, -2);
+    assert_eq!(entry.options.depends_on, vec![1, 2]);
+    assert_eq!(entry.options.mode, Some(ExecMode::Shell));
+
+    let entry = parse_json_line(r#"{"cmd": "echo", "cpus": 16, "mem_mb": "4G"}"#, &options, submit_dir).unwrap();
+    assert_eq!(entry.options.cpus, Some(16));
+    assert_eq!(entry.options.mem_mb, Some(4096));
+    assert!(parse_json_line(r#"{"cmd": "echo", "cpus": 0}"#, &options, submit_dir).is_err());
+
+    // Relative working directories are resolved against the directory the jobs are enqueued from
+    let entry = parse_json_line(r#"{"cmd": "echo", "cwd": "data/a"}"#, &options, submit_dir).unwrap();
+    assert_eq!(entry.options.cwd.as_deref(), Some("/home/user/data/a"));
+
+    assert!(parse_json_line(r#"{"cwd": "/tmp"}"#, &options, submit_dir).is_err());
+    assert!(parse_json_line(r#"{"cmd": "echo", "env": {"A": 1}}"#, &options, submit_dir).is_err());
+    assert!(parse_json_line(r#"{"cmd": "echo", "tags": ["a,b"]}"#, &options, submit_dir).is_err());
+    assert!(parse_json_line(r#"{"cmd": "echo", "timout": 5}"#, &options, submit_dir).is_err());
+    assert!(parse_json_line(r#"["echo"]"#, &options, submit_dir).is_err());
+    assert!(parse_json_line("echo", &options, submit_dir).is_err());
+}
+
+#[test]
+fn test_load_rejects_unparsable_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cmds.txt");
+    std::fs::write(&path, "echo a\necho it's done\n").unwrap();
+    let path = path.to_string_lossy();
+    let err = EnqueueFile::load(&path, EnqueueFormat::Lines, &JobOptions::default()).err().unwrap();
+    assert!(err.to_string().contains("Line 2: Command parser error"));
+
+    // Commands run through a shell are parsed by the shell
+    let options = JobOptions { mode: Some(ExecMode::Shell), ..JobOptions::default() };
+    assert_eq!(EnqueueFile::load(&path, EnqueueFormat::Lines, &options).unwrap().jobs.len(), 2);
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// How the jobs are written in the file that is enqueued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnqueueFormat {
+    /// One command per line.
+    #[default]
+    Lines,
+    /// One JSON object per line, with the command and its options (e.g. {"cmd": "echo hello", "cwd": "/tmp"}).
+    Jsonl,
+}
+
+impl EnqueueFormat {
+    pub fn from_string(value: &str) -> RsrqResult<EnqueueFormat> {
+        match value {
+            "lines" => Ok(EnqueueFormat::Lines),
+            "jsonl" => Ok(EnqueueFormat::Jsonl),
+            _ => Err(RsrqError::ParserError(format!("Invalid enqueue format: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for EnqueueFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnqueueFormat::Lines => write!(f, "lines"),
+            EnqueueFormat::Jsonl => write!(f, "jsonl"),
+        }
+    }
+}
+
+impl std::str::FromStr for EnqueueFormat {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EnqueueFormat::from_string(s)
+    }
+}
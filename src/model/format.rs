@@ -0,0 +1,74 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// How command output is written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Human readable text.
+    #[default]
+    Human,
+    /// JSON, for use by other programs.
+    Json,
+    /// Tab separated values with a header.
+    Tsv,
+}
+
+impl OutputFormat {
+    pub fn from_string(value: &str) -> RsrqResult<OutputFormat> {
+        match value {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(RsrqError::ParserError(format!("Invalid output format: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Human => write!(f, "human"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Tsv => write!(f, "tsv"),
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::from_string(s)
+    }
+}
+
+/// Print the rows as tab separated values, the header is printed even if there are no rows.
+pub fn print_tsv(columns: &[&str], rows: &[Value]) {
+    println!("{}", columns.join("\t"));
+    for row in rows {
+        let values: Vec<String> = columns.iter().map(|col| tsv_value(&row[*col])).collect();
+        println!("{}", values.join("\t"));
+    }
+}
+
+pub fn tsv_value(value: &Value) -> String {
+    match value {
+        Value::Null => "".to_string(),
+        Value::String(s) => s.replace(['\t', '\n'], " "),
+        _ => value.to_string(),
+    }
+}
+
+#[test]
+fn test_tsv_value() {
+    use serde_json::json;
+
+    assert_eq!(tsv_value(&Value::Null), "");
+    assert_eq!(tsv_value(&json!("a\tb\nc")), "a b c");
+    assert_eq!(tsv_value(&json!(12)), "12");
+    assert_eq!(tsv_value(&json!(true)), "true");
+}
@@ -0,0 +1,27 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+use crate::config::{ARRAY_KEY, UID_KEY_ARRAY};
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::util::redis::get_next_uid;
+
+/// The key of the set containing the ids of the jobs in the array.
+pub fn get_array_key(id: usize) -> String {
+    format!("{}:{}", ARRAY_KEY, id)
+}
+
+/// Reserve the id of a new array.
+pub async fn get_next_array_id(con: &mut ConnectionManager) -> RsrqResult<usize> {
+    get_next_uid(UID_KEY_ARRAY, con).await
+}
+
+/// The ids of the jobs in the array (sorted), an error is returned if the array does not exist.
+pub async fn get_array_job_ids(id: usize, con: &mut ConnectionManager) -> RsrqResult<Vec<usize>> {
+    let mut job_ids: Vec<usize> = con.smembers(get_array_key(id)).await.map_err(RsrqError::RedisOpError)?;
+    if job_ids.is_empty() {
+        return Err(RsrqError::GeneralError(format!("Array not found: {}", id)));
+    }
+    job_ids.sort();
+    Ok(job_ids)
+}
@@ -0,0 +1,90 @@
+use serde_json::{json, Value};
+
+use crate::model::error::RsrqError;
+use crate::model::job::status::JobStatus;
+use crate::model::types::RsrqResult;
+
+/// The outcome of a single attempt at running a job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobAttempt {
+    pub attempt: u32,
+    pub status: JobStatus,
+    pub exit_code: i32,
+    pub stderr: String,
+    pub started: u64,
+    pub finished: u64,
+    pub duration_ms: u128,
+}
+
+impl JobAttempt {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "attempt": self.attempt,
+            "status": self.status.to_string(),
+            "exit_code": self.exit_code,
+            "stderr": self.stderr,
+            "started": self.started,
+            "finished": self.finished,
+            "duration_ms": self.duration_ms as u64,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> RsrqResult<JobAttempt> {
+        let get_u64 = |key: &str| value[key].as_u64().ok_or(RsrqError::InvalidJson(format!("Missing attempt field: {}", key)));
+        let status_str = value["status"].as_str().ok_or(RsrqError::InvalidJson("Missing attempt field: status".to_string()))?;
+        Ok(JobAttempt {
+            attempt: get_u64("attempt")? as u32,
+            status: JobStatus::from_string(status_str)?,
+            exit_code: value["exit_code"].as_i64().unwrap_or(1) as i32,
+            stderr: value["stderr"].as_str().unwrap_or("").to_string(),
+            started: get_u64("started")?,
+            finished: get_u64("finished")?,
+            duration_ms: get_u64("duration_ms")? as u128,
+        })
+    }
+
+    /// Serialise a collection of attempts to be stored in the job hash.
+    pub fn to_json_list(attempts: &[JobAttempt]) -> String {
+        Value::Array(attempts.iter().map(|x| x.to_json()).collect()).to_string()
+    }
+
+    /// Parse the collection of attempts stored in the job hash (empty if never run).
+    pub fn from_json_list(value: &str) -> RsrqResult<Vec<JobAttempt>> {
+        if value.is_empty() {
+            return Ok(Vec::new());
+        }
+        let json: Value = serde_json::from_str(value).map_err(|e| RsrqError::InvalidJson(e.to_string()))?;
+        match json.as_array() {
+            Some(values) => values.iter().map(JobAttempt::from_json).collect(),
+            None => Err(RsrqError::InvalidJson(format!("Expected a list of attempts: {}", value)))
+        }
+    }
+}
+
+#[test]
+fn test_job_attempt_json() {
+    let attempts = vec![
+        JobAttempt {
+            attempt: 1,
+            status: JobStatus::Failed,
+            exit_code: 2,
+            stderr: "No such \"file\"\n".to_string(),
+            started: 100,
+            finished: 105,
+            duration_ms: 5000,
+        },
+        JobAttempt {
+            attempt: 2,
+            status: JobStatus::TimedOut,
+            exit_code: 1,
+            stderr: "".to_string(),
+            started: 110,
+            finished: 170,
+            duration_ms: 60000,
+        },
+    ];
+    let json = JobAttempt::to_json_list(&attempts);
+    assert_eq!(JobAttempt::from_json_list(&json).unwrap(), attempts);
+    assert!(JobAttempt::from_json_list("").unwrap().is_empty());
+    assert!(JobAttempt::from_json_list("{}").is_err());
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use redis::{ErrorKind, FromRedisValue, RedisResult, RedisWrite, ToRedisArgs, Value};
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// The maximum delay between retries (1 day).
+const MAX_RETRY_DELAY_SECS: u64 = 60 * 60 * 24;
+
+/// How the delay between retries of a failed job grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetryBackoff {
+    /// Wait the same delay between each attempt.
+    #[default]
+    Fixed,
+    /// Double the delay after each attempt.
+    Exponential,
+}
+
+impl RetryBackoff {
+    pub fn from_string(value: &str) -> RsrqResult<RetryBackoff> {
+        match value {
+            "fixed" => Ok(RetryBackoff::Fixed),
+            "exponential" => Ok(RetryBackoff::Exponential),
+            _ => Err(RsrqError::ParserError(format!("Invalid retry backoff: {}", value))),
+        }
+    }
+
+    /// The number of seconds to wait before running the next attempt, given the attempt that just failed (starting at 1).
+    pub fn get_delay(&self, delay_secs: u64, attempt: u32) -> u64 {
+        let delay = match self {
+            RetryBackoff::Fixed => delay_secs,
+            RetryBackoff::Exponential => {
+                let exponent = attempt.saturating_sub(1).min(32);
+                delay_secs.saturating_mul(2_u64.saturating_pow(exponent))
+            }
+        };
+        delay.min(MAX_RETRY_DELAY_SECS)
+    }
+}
+
+impl fmt::Display for RetryBackoff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RetryBackoff::Fixed => write!(f, "fixed"),
+            RetryBackoff::Exponential => write!(f, "exponential"),
+        }
+    }
+}
+
+impl std::str::FromStr for RetryBackoff {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RetryBackoff::from_string(s)
+    }
+}
+
+impl ToRedisArgs for RetryBackoff {
+    fn write_redis_args<W>(&self, out: &mut W) where W: ?Sized + RedisWrite {
+        ToRedisArgs::write_redis_args(&self.to_string(), out);
+    }
+}
+
+impl FromRedisValue for RetryBackoff {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        let string_value: String = FromRedisValue::from_redis_value(v)?;
+        let res = RetryBackoff::from_string(&string_value);
+        match res {
+            Ok(backoff) => Ok(backoff),
+            Err(_) => Err((ErrorKind::TypeError, "Unable to convert value.").into())
+        }
+    }
+}
+
+#[test]
+fn test_get_delay() {
+    assert_eq!(RetryBackoff::Fixed.get_delay(30, 1), 30);
+    assert_eq!(RetryBackoff::Fixed.get_delay(30, 5), 30);
+    assert_eq!(RetryBackoff::Fixed.get_delay(0, 3), 0);
+
+    assert_eq!(RetryBackoff::Exponential.get_delay(30, 1), 30);
+    assert_eq!(RetryBackoff::Exponential.get_delay(30, 2), 60);
+    assert_eq!(RetryBackoff::Exponential.get_delay(30, 3), 120);
+    assert_eq!(RetryBackoff::Exponential.get_delay(0, 3), 0);
+
+    // Delays are capped
+    assert_eq!(RetryBackoff::Exponential.get_delay(30, 1000), MAX_RETRY_DELAY_SECS);
+    assert_eq!(RetryBackoff::Fixed.get_delay(u64::MAX, 1), MAX_RETRY_DELAY_SECS);
+}
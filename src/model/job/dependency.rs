@@ -0,0 +1,85 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+use crate::config::DEPENDENTS_KEY;
+use crate::model::error::RsrqError;
+use crate::model::job::key::JobKey;
+use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+use crate::model::queue::queue_type::QueueType;
+use crate::model::queue::rsrq_queue::Queue;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_id_list;
+use crate::util::time::get_timestamp_s;
+
+/// The key of the set containing the ids of jobs that depend on this job.
+pub fn get_dependents_key(id: usize) -> String {
+    format!("{}:{}", DEPENDENTS_KEY, id)
+}
+
+/// Queue a deferred job once all of its dependencies have finished, or cancel it if any of them never will.
+pub async fn check_deferred(job_id: usize, con: &mut ConnectionManager) -> RsrqResult<()> {
+    let key = Job::get_redis_key(job_id);
+    let (status, queue, depends_on, run_at): (Option<JobStatus>, Option<String>, Option<String>, Option<String>) = redis::cmd("HMGET")
+        .arg(&key).arg(JobKey::Status).arg(JobKey::Queue).arg(JobKey::DependsOn).arg(JobKey::RunAt)
+        .query_async(con).await.map_err(RsrqError::RedisOpError)?;
+
+    // Only jobs that are still waiting need to be checked
+    let (queue, depends_on) = match (status, queue, depends_on) {
+        (Some(JobStatus::Deferred), Some(queue), Some(depends_on)) => (queue, parse_id_list(&depends_on)?),
+        _ => return Ok(()),
+    };
+
+    // Collect the status of each dependency
+    let statuses: Vec<Option<JobStatus>> = {
+        let mut pipe = redis::pipe();
+        for parent_id in &depends_on {
+            pipe.hget(Job::get_redis_key(*parent_id), JobKey::Status);
+        }
+        pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?
+    };
+
+    // A dependency that failed, was cancelled, or no longer exists will never finish
+    let will_never_run = statuses.iter().any(|status| {
+        matches!(status, None | Some(JobStatus::Failed) | Some(JobStatus::Cancelled) | Some(JobStatus::TimedOut))
+    });
+    if will_never_run {
+        Job::cancel_many(&[job_id], con).await?;
+    } else if statuses.iter().all(|status| matches!(status, Some(JobStatus::Finished))) {
+        let q_deferred = Queue::new(QueueType::Deferred, &queue);
+        match run_at.and_then(|x| x.parse::<u64>().ok()) {
+            // The job is not yet due, it will be queued by the workers once it is
+            Some(run_at) if run_at > get_timestamp_s()? => {
+                let mut pipe = redis::pipe();
+                pipe.atomic();
+                q_deferred.pipe_remove_job_id(job_id, &mut pipe);
+                Queue::new(QueueType::Scheduled, &queue).pipe_add_job_id_at(job_id, run_at, &mut pipe);
+                pipe.hset(&key, JobKey::Status, JobStatus::Scheduled);
+                let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+            }
+            _ => {
+                q_deferred.promote_job(job_id, con).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Called once a job has reached a terminal state to queue (or cancel) the jobs that are waiting on it.
+pub async fn resolve_dependents(job_id: usize, status: JobStatus, con: &mut ConnectionManager) -> RsrqResult<()> {
+    let dependents: Vec<usize> = con.smembers(get_dependents_key(job_id)).await.map_err(RsrqError::RedisOpError)?;
+    if dependents.is_empty() {
+        return Ok(());
+    }
+    match status {
+        JobStatus::Finished => {
+            for dependent_id in dependents {
+                check_deferred(dependent_id, con).await?;
+            }
+        }
+        _ => {
+            Job::cancel_many(&dependents, con).await?;
+        }
+    }
+    Ok(())
+}
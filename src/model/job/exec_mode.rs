@@ -0,0 +1,60 @@
+use std::fmt;
+
+use redis::{ErrorKind, FromRedisValue, RedisResult, RedisWrite, ToRedisArgs, Value};
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// How the worker runs the command of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecMode {
+    /// Split the command into words (POSIX shell rules) and run it directly.
+    #[default]
+    Exec,
+    /// Pass the command to a shell, allowing pipes, redirects, globbing, etc.
+    Shell,
+}
+
+impl ExecMode {
+    pub fn from_string(value: &str) -> RsrqResult<ExecMode> {
+        match value {
+            "exec" => Ok(ExecMode::Exec),
+            "shell" => Ok(ExecMode::Shell),
+            _ => Err(RsrqError::ParserError(format!("Invalid execution mode: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for ExecMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecMode::Exec => write!(f, "exec"),
+            ExecMode::Shell => write!(f, "shell"),
+        }
+    }
+}
+
+impl std::str::FromStr for ExecMode {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExecMode::from_string(s)
+    }
+}
+
+impl ToRedisArgs for ExecMode {
+    fn write_redis_args<W>(&self, out: &mut W) where W: ?Sized + RedisWrite {
+        ToRedisArgs::write_redis_args(&self.to_string(), out);
+    }
+}
+
+impl FromRedisValue for ExecMode {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        let string_value: String = FromRedisValue::from_redis_value(v)?;
+        let res = ExecMode::from_string(&string_value);
+        match res {
+            Ok(mode) => Ok(mode),
+            Err(_) => Err((ErrorKind::TypeError, "Unable to convert value.").into())
+        }
+    }
+}
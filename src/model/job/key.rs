@@ -19,6 +19,26 @@ pub enum JobKey {
     ExitCode,
     DurationMs,
     ProcessId,
+    Timeout,
+    MaxRetries,
+    RetryDelay,
+    RetryBackoff,
+    Attempt,
+    Attempts,
+    DependsOn,
+    Mode,
+    StdoutPath,
+    StderrPath,
+    Priority,
+    RunAt,
+    Cwd,
+    Env,
+    Name,
+    Tags,
+    Cpus,
+    MemMb,
+    Metadata,
+    ArrayId,
 }
 
 impl JobKey {
@@ -36,6 +56,26 @@ impl JobKey {
             "exit_code" => Ok(JobKey::ExitCode),
             "duration_ms" => Ok(JobKey::DurationMs),
             "process_id" => Ok(JobKey::ProcessId),
+            "timeout" => Ok(JobKey::Timeout),
+            "max_retries" => Ok(JobKey::MaxRetries),
+            "retry_delay" => Ok(JobKey::RetryDelay),
+            "retry_backoff" => Ok(JobKey::RetryBackoff),
+            "attempt" => Ok(JobKey::Attempt),
+            "attempts" => Ok(JobKey::Attempts),
+            "depends_on" => Ok(JobKey::DependsOn),
+            "mode" => Ok(JobKey::Mode),
+            "stdout_path" => Ok(JobKey::StdoutPath),
+            "stderr_path" => Ok(JobKey::StderrPath),
+            "priority" => Ok(JobKey::Priority),
+            "run_at" => Ok(JobKey::RunAt),
+            "cwd" => Ok(JobKey::Cwd),
+            "env" => Ok(JobKey::Env),
+            "name" => Ok(JobKey::Name),
+            "tags" => Ok(JobKey::Tags),
+            "cpus" => Ok(JobKey::Cpus),
+            "mem_mb" => Ok(JobKey::MemMb),
+            "metadata" => Ok(JobKey::Metadata),
+            "array_id" => Ok(JobKey::ArrayId),
             _ => Err(RsrqError::ParserError(value.to_string())),
         }
     }
@@ -56,6 +96,26 @@ impl fmt::Display for JobKey {
             JobKey::ExitCode => write!(f, "exit_code"),
             JobKey::DurationMs => write!(f, "duration_ms"),
             JobKey::ProcessId => write!(f, "process_id"),
+            JobKey::Timeout => write!(f, "timeout"),
+            JobKey::MaxRetries => write!(f, "max_retries"),
+            JobKey::RetryDelay => write!(f, "retry_delay"),
+            JobKey::RetryBackoff => write!(f, "retry_backoff"),
+            JobKey::Attempt => write!(f, "attempt"),
+            JobKey::Attempts => write!(f, "attempts"),
+            JobKey::DependsOn => write!(f, "depends_on"),
+            JobKey::Mode => write!(f, "mode"),
+            JobKey::StdoutPath => write!(f, "stdout_path"),
+            JobKey::StderrPath => write!(f, "stderr_path"),
+            JobKey::Priority => write!(f, "priority"),
+            JobKey::RunAt => write!(f, "run_at"),
+            JobKey::Cwd => write!(f, "cwd"),
+            JobKey::Env => write!(f, "env"),
+            JobKey::Name => write!(f, "name"),
+            JobKey::Tags => write!(f, "tags"),
+            JobKey::Cpus => write!(f, "cpus"),
+            JobKey::MemMb => write!(f, "mem_mb"),
+            JobKey::Metadata => write!(f, "metadata"),
+            JobKey::ArrayId => write!(f, "array_id"),
         }
     }
 }
@@ -1,3 +1,10 @@
 pub mod rsrq_job;
 pub mod status;
-pub mod key;
\ No newline at end of file
+pub mod key;
+pub mod options;
+pub mod backoff;
+pub mod attempt;
+pub mod dependency;
+pub mod exec_mode;
+pub mod resources;
+pub mod array;
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use crate::model::job::backoff::RetryBackoff;
+use crate::model::job::exec_mode::ExecMode;
+
+/// Optional attributes that can be set on a job when it is enqueued.
+#[derive(Debug, Clone, Default)]
+pub struct JobOptions {
+    /// Kill the job if it runs for longer than this many seconds.
+    pub timeout: Option<u64>,
+    /// The number of times to re-run the job if it fails.
+    pub max_retries: u32,
+    /// The number of seconds to wait before retrying a failed job.
+    pub retry_delay: u64,
+    /// How the retry delay grows with each failed attempt.
+    pub retry_backoff: RetryBackoff,
+    /// Only run the job once all of these jobs have finished successfully.
+    pub depends_on: Vec<usize>,
+    /// How the command is run, if not set the worker's mode is used.
+    pub mode: Option<ExecMode>,
+    /// Jobs with a higher priority are run first, those with the same priority are run in the order they were enqueued.
+    pub priority: i16,
+    /// Don't run the job before this UNIX timestamp (in seconds).
+    pub run_at: Option<u64>,
+    /// The working directory of the command, if not set the worker's working directory is used.
+    pub cwd: Option<String>,
+    /// Environment variables that are set for the command (in addition to those of the worker).
+    pub env: BTreeMap<String, String>,
+    /// A name to identify the job by.
+    pub name: Option<String>,
+    /// Labels to group the job by.
+    pub tags: Vec<String>,
+    /// The number of CPUs the job uses (default: 1).
+    pub cpus: Option<u32>,
+    /// The memory the job uses in MB (default: 0).
+    pub mem_mb: Option<u64>,
+    /// Additional information about the job (e.g. the Snakemake rule).
+    pub metadata: BTreeMap<String, String>,
+    /// The array that the job was enqueued as part of.
+    pub array_id: Option<usize>,
+}
@@ -0,0 +1,16 @@
+/// The CPUs and memory that a job needs to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobResources {
+    pub cpus: u32,
+    pub mem_mb: u64,
+}
+
+impl Default for JobResources {
+    /// Jobs that don't declare their resources take a single CPU.
+    fn default() -> Self {
+        JobResources {
+            cpus: 1,
+            mem_mb: 0,
+        }
+    }
+}
@@ -2,15 +2,25 @@ use std::collections::BTreeMap;
 
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
+use serde_json::{json, Value};
 
 use crate::config::{JOB_KEY, UID_KEY_JOB};
+use crate::model::command::validate_cmd;
 use crate::model::error::RsrqError;
+use crate::model::job::array::get_array_key;
+use crate::model::job::attempt::JobAttempt;
+use crate::model::job::backoff::RetryBackoff;
+use crate::model::job::dependency::{check_deferred, get_dependents_key};
+use crate::model::job::exec_mode::ExecMode;
 use crate::model::job::key::JobKey;
+use crate::model::job::options::JobOptions;
+use crate::model::job::resources::JobResources;
 use crate::model::job::status::JobStatus;
 use crate::model::queue::queue_type::QueueType;
-use crate::model::queue::rsrq_queue::Queue;
+use crate::model::queue::rsrq_queue::{Queue, MAX_QUEUED_JOB_ID};
 use crate::model::types::RsrqResult;
-use crate::util::parsing::{btree_get, btree_get_opt};
+use crate::util::parsing::{btree_get, btree_get_opt, format_string_map, format_id_list, format_tag_list, parse_string_map, parse_id_list, parse_tag_list};
+use crate::util::collection::deduplicate;
 use crate::util::redis::get_next_uid;
 use crate::util::time::get_timestamp_s;
 
@@ -30,6 +40,27 @@ pub struct Job {
     pub stderr: Option<String>,
     pub exit_code: Option<i32>,
     pub duration_ms: Option<u128>,
+    pub process_id: Option<usize>,
+    pub timeout: Option<u64>,
+    pub max_retries: u32,
+    pub retry_delay: u64,
+    pub retry_backoff: RetryBackoff,
+    pub attempt: u32,
+    pub attempts: Vec<JobAttempt>,
+    pub depends_on: Vec<usize>,
+    pub mode: Option<ExecMode>,
+    pub stdout_path: Option<String>,
+    pub stderr_path: Option<String>,
+    pub priority: i16,
+    pub run_at: Option<u64>,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    pub cpus: u32,
+    pub mem_mb: u64,
+    pub metadata: BTreeMap<String, String>,
+    pub array_id: Option<usize>,
 }
 
 impl Job {
@@ -38,43 +69,106 @@ impl Job {
         format!("{}:{}", JOB_KEY, id)
     }
 
-    pub async fn new<C: redis::aio::ConnectionLike>(queue: &str, cmd: &str, con: &mut C) -> RsrqResult<Job> {
+    pub async fn new(queue: &str, cmd: &str, options: &JobOptions, con: &mut ConnectionManager) -> RsrqResult<Job> {
+        // Commands that can't be parsed are rejected now, rather than failing once they run
+        validate_cmd(cmd, options.mode)?;
+
+        // Jobs can only depend on jobs that exist
+        let depends_on = deduplicate(&options.depends_on);
+        if !depends_on.is_empty() {
+            let mut pipe = redis::pipe();
+            for parent_id in &depends_on {
+                pipe.exists(Job::get_redis_key(*parent_id));
+            }
+            let exists: Vec<bool> = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+            if let Some((parent_id, _)) = depends_on.iter().zip(exists).find(|(_, exists)| !exists) {
+                return Err(RsrqError::JobNotFound(*parent_id));
+            }
+        }
+
+        // Jobs with dependencies wait in the deferred queue until they have finished,
+        // jobs that should run later wait in the scheduled queue until they are due
+        let created = get_timestamp_s()?;
+        let run_at = options.run_at.filter(|x| *x > created);
+        let status = if !depends_on.is_empty() {
+            JobStatus::Deferred
+        } else if run_at.is_some() {
+            JobStatus::Scheduled
+        } else {
+            JobStatus::Queued
+        };
+
         let job = {
             let id = get_next_uid(UID_KEY_JOB, con).await?;
+            if id > MAX_QUEUED_JOB_ID {
+                return Err(RsrqError::GeneralError(format!("The maximum job id ({}) has been reached, purge the database to reset it.", MAX_QUEUED_JOB_ID)));
+            }
             Job {
                 id,
                 key: Job::get_redis_key(id),
                 cmd: cmd.to_string(),
-                status: JobStatus::Queued,
+                status,
                 queue: queue.to_string(),
-                created: get_timestamp_s()?,
+                created,
                 started: None,
                 finished: None,
                 stdout: None,
                 stderr: None,
                 exit_code: None,
                 duration_ms: None,
+                process_id: None,
+                timeout: options.timeout,
+                max_retries: options.max_retries,
+                retry_delay: options.retry_delay,
+                retry_backoff: options.retry_backoff,
+                attempt: 0,
+                attempts: Vec::new(),
+                depends_on,
+                mode: options.mode,
+                stdout_path: None,
+                stderr_path: None,
+                priority: options.priority,
+                run_at,
+                cwd: options.cwd.clone(),
+                env: options.env.clone(),
+                name: options.name.clone(),
+                tags: options.tags.clone(),
+                cpus: options.cpus.unwrap_or(JobResources::default().cpus),
+                mem_mb: options.mem_mb.unwrap_or(JobResources::default().mem_mb),
+                metadata: options.metadata.clone(),
+                array_id: options.array_id,
             }
         };
 
         // Create the target queue object
-        let queue = Queue::new(QueueType::Queued, queue);
+        let queue = Queue::new(status.to_queue_type(), queue);
 
         // Create the Job and Enqueue it
         let pipe = {
             let mut pipe = redis::pipe();
             pipe.atomic();
             pipe.hset_multiple(&job.key, &job.to_array());
-            queue.pipe_add_job_id(job.id, &mut pipe);
+            queue.pipe_add_job_id_at(job.id, run_at.unwrap_or(0), &mut pipe);
+            for parent_id in &job.depends_on {
+                pipe.sadd(get_dependents_key(*parent_id), job.id);
+            }
+            if let Some(array_id) = job.array_id {
+                pipe.sadd(get_array_key(array_id), job.id);
+            }
             pipe
         };
-        pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+        let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+
+        // The dependencies may have already finished (or failed)
+        if !job.depends_on.is_empty() {
+            check_deferred(job.id, con).await?;
+        }
 
         // Return the Job
         Ok(job)
     }
 
-    pub fn to_array(&self) -> [(JobKey, String); 11] {
+    pub fn to_array(&self) -> [(JobKey, String); 32] {
         [
             (JobKey::Id, self.id.to_string()),
             (JobKey::Cmd, self.cmd.clone()),
@@ -87,9 +181,73 @@ impl Job {
             (JobKey::Stderr, self.stderr.clone().unwrap_or("".to_string())),
             (JobKey::ExitCode, self.exit_code.map(|x| x.to_string()).unwrap_or("".to_string())),
             (JobKey::DurationMs, self.duration_ms.map(|x| x.to_string()).unwrap_or("".to_string())),
+            (JobKey::ProcessId, self.process_id.map(|x| x.to_string()).unwrap_or("".to_string())),
+            (JobKey::Timeout, self.timeout.map(|x| x.to_string()).unwrap_or("".to_string())),
+            (JobKey::MaxRetries, self.max_retries.to_string()),
+            (JobKey::RetryDelay, self.retry_delay.to_string()),
+            (JobKey::RetryBackoff, self.retry_backoff.to_string()),
+            (JobKey::Attempt, self.attempt.to_string()),
+            (JobKey::Attempts, JobAttempt::to_json_list(&self.attempts)),
+            (JobKey::DependsOn, format_id_list(&self.depends_on)),
+            (JobKey::Mode, self.mode.map(|x| x.to_string()).unwrap_or("".to_string())),
+            (JobKey::StdoutPath, self.stdout_path.clone().unwrap_or("".to_string())),
+            (JobKey::StderrPath, self.stderr_path.clone().unwrap_or("".to_string())),
+            (JobKey::Priority, self.priority.to_string()),
+            (JobKey::RunAt, self.run_at.map(|x| x.to_string()).unwrap_or("".to_string())),
+            (JobKey::Cwd, self.cwd.clone().unwrap_or("".to_string())),
+            (JobKey::Env, format_string_map(&self.env)),
+            (JobKey::Name, self.name.clone().unwrap_or("".to_string())),
+            (JobKey::Tags, format_tag_list(&self.tags)),
+            (JobKey::Cpus, self.cpus.to_string()),
+            (JobKey::MemMb, self.mem_mb.to_string()),
+            (JobKey::Metadata, format_string_map(&self.metadata)),
+            (JobKey::ArrayId, self.array_id.map(|x| x.to_string()).unwrap_or("".to_string())),
         ]
     }
 
+    /// The CPUs and memory the job needs to run.
+    pub fn resources(&self) -> JobResources {
+        JobResources {
+            cpus: self.cpus,
+            mem_mb: self.mem_mb,
+        }
+    }
+
+    /// The attributes of the job as JSON (the captured output is not included).
+    /// Only the names of the environment variables are included, their values are often secrets (e.g. tokens).
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "cmd": self.cmd,
+            "status": self.status.to_string(),
+            "queue": self.queue,
+            "priority": self.priority,
+            "created": self.created,
+            "run_at": self.run_at,
+            "started": self.started,
+            "finished": self.finished,
+            "duration_ms": self.duration_ms.map(|x| x as u64),
+            "exit_code": self.exit_code,
+            "process_id": self.process_id,
+            "timeout": self.timeout,
+            "mode": self.mode.map(|x| x.to_string()),
+            "max_retries": self.max_retries,
+            "attempt": self.attempt,
+            "attempts": self.attempts.iter().map(|x| x.to_json()).collect::<Vec<Value>>(),
+            "depends_on": self.depends_on,
+            "stdout_path": self.stdout_path,
+            "stderr_path": self.stderr_path,
+            "name": self.name,
+            "tags": self.tags,
+            "cpus": self.cpus,
+            "mem_mb": self.mem_mb,
+            "metadata": self.metadata,
+            "array_id": self.array_id,
+            "cwd": self.cwd,
+            "env": self.env.keys().collect::<Vec<&String>>(),
+        })
+    }
+
     pub async fn get_status_many(ids: Vec<usize>, con: &mut ConnectionManager) -> RsrqResult<Vec<JobStatus>> {
         let mut pipe = redis::pipe();
         for id in ids {
@@ -100,12 +258,21 @@ impl Job {
         Ok(statuses)
     }
 
+    pub async fn get_status(id: usize, con: &mut ConnectionManager) -> RsrqResult<JobStatus> {
+        let key = Job::get_redis_key(id);
+        let status: Option<JobStatus> = con.hget(&key, JobKey::Status).await.map_err(RsrqError::RedisOpError)?;
+        status.ok_or(RsrqError::JobNotFound(id))
+    }
+
     pub async fn load(id: usize, con: &mut ConnectionManager) -> RsrqResult<Job> {
         // Set the parameters
         let key = Job::get_redis_key(id);
 
         // Load the job
         let map: BTreeMap<String, String> = con.hgetall(&key).await.map_err(RsrqError::RedisOpError)?;
+        if map.is_empty() {
+            return Err(RsrqError::JobNotFound(id));
+        }
 
         // Convert them to the expected data types
         let job_id = btree_get(&map, JobKey::Id)?;
@@ -120,6 +287,33 @@ impl Job {
         let job_stderr = btree_get_opt(&map, JobKey::Stderr)?;
         let job_exit_code = btree_get_opt(&map, JobKey::ExitCode)?;
         let job_duration_ms = btree_get_opt(&map, JobKey::DurationMs)?;
+        let job_process_id = btree_get_opt(&map, JobKey::ProcessId)?;
+        let job_timeout = btree_get_opt(&map, JobKey::Timeout)?;
+        let job_max_retries = btree_get_opt(&map, JobKey::MaxRetries)?.unwrap_or(0);
+        let job_retry_delay = btree_get_opt(&map, JobKey::RetryDelay)?.unwrap_or(0);
+        let job_retry_backoff = btree_get_opt(&map, JobKey::RetryBackoff)?.unwrap_or_default();
+        let job_attempt = btree_get_opt(&map, JobKey::Attempt)?.unwrap_or(0);
+        let job_attempts_str: Option<String> = btree_get_opt(&map, JobKey::Attempts)?;
+        let job_attempts = JobAttempt::from_json_list(&job_attempts_str.unwrap_or_default())?;
+        let job_depends_on_str: Option<String> = btree_get_opt(&map, JobKey::DependsOn)?;
+        let job_depends_on = parse_id_list(&job_depends_on_str.unwrap_or_default())?;
+        let job_mode_str: Option<String> = btree_get_opt(&map, JobKey::Mode)?;
+        let job_mode = job_mode_str.map(|x| ExecMode::from_string(&x)).transpose()?;
+        let job_stdout_path = btree_get_opt(&map, JobKey::StdoutPath)?;
+        let job_stderr_path = btree_get_opt(&map, JobKey::StderrPath)?;
+        let job_priority = btree_get_opt(&map, JobKey::Priority)?.unwrap_or(0);
+        let job_run_at = btree_get_opt(&map, JobKey::RunAt)?;
+        let job_cwd = btree_get_opt(&map, JobKey::Cwd)?;
+        let job_env_str: Option<String> = btree_get_opt(&map, JobKey::Env)?;
+        let job_env = parse_string_map(&job_env_str.unwrap_or_default())?;
+        let job_name = btree_get_opt(&map, JobKey::Name)?;
+        let job_tags_str: Option<String> = btree_get_opt(&map, JobKey::Tags)?;
+        let job_tags = parse_tag_list(&job_tags_str.unwrap_or_default());
+        let job_cpus = btree_get_opt(&map, JobKey::Cpus)?.unwrap_or(JobResources::default().cpus);
+        let job_mem_mb = btree_get_opt(&map, JobKey::MemMb)?.unwrap_or(JobResources::default().mem_mb);
+        let job_metadata_str: Option<String> = btree_get_opt(&map, JobKey::Metadata)?;
+        let job_metadata = parse_string_map(&job_metadata_str.unwrap_or_default())?;
+        let job_array_id = btree_get_opt(&map, JobKey::ArrayId)?;
 
         // Create the job
         let job = Job {
@@ -135,8 +329,130 @@ impl Job {
             stderr: job_stderr,
             exit_code: job_exit_code,
             duration_ms: job_duration_ms,
+            process_id: job_process_id,
+            timeout: job_timeout,
+            max_retries: job_max_retries,
+            retry_delay: job_retry_delay,
+            retry_backoff: job_retry_backoff,
+            attempt: job_attempt,
+            attempts: job_attempts,
+            depends_on: job_depends_on,
+            mode: job_mode,
+            stdout_path: job_stdout_path,
+            stderr_path: job_stderr_path,
+            priority: job_priority,
+            run_at: job_run_at,
+            cwd: job_cwd,
+            env: job_env,
+            name: job_name,
+            tags: job_tags,
+            cpus: job_cpus,
+            mem_mb: job_mem_mb,
+            metadata: job_metadata,
+            array_id: job_array_id,
         };
         Ok(job)
     }
-}
 
+    /// Sets the Job status to cancelled, removes it from the queue and adds it to the failed queue.
+    /// Jobs that depend on a cancelled job are also cancelled.
+    /// Returns the ids of the jobs that were cancelled (i.e. not already in a terminal state).
+    pub async fn cancel_many(ids: &[usize], con: &mut ConnectionManager) -> RsrqResult<Vec<usize>> {
+        let mut cancelled: Vec<usize> = Vec::new();
+        let mut to_cancel: Vec<usize> = deduplicate(ids);
+        while !to_cancel.is_empty() {
+            let cur_cancelled = Job::cancel_batch(&to_cancel, con).await?;
+
+            // Collect the jobs waiting on those that were just cancelled
+            to_cancel = if cur_cancelled.is_empty() {
+                Vec::new()
+            } else {
+                let mut pipe = redis::pipe();
+                for id in &cur_cancelled {
+                    pipe.smembers(get_dependents_key(*id));
+                }
+                let dependents: Vec<Vec<usize>> = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+                deduplicate(&dependents.into_iter().flatten().collect::<Vec<usize>>())
+            };
+            cancelled.extend(cur_cancelled);
+        }
+        Ok(cancelled)
+    }
+
+    /// Moves failed, cancelled, or timed out jobs back into their queue, keeping their ids. Only jobs with one
+    /// of the statuses (and in the queue, if specified) are re-queued. Jobs with dependencies wait in the
+    /// deferred queue until their dependencies have finished. Returns the ids of the jobs that were re-queued.
+    pub async fn requeue_many(ids: &[usize], queue_name: Option<&str>, statuses: &[JobStatus], con: &mut ConnectionManager) -> RsrqResult<Vec<usize>> {
+        let ids = deduplicate(ids);
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Obtain the status, queue, and dependencies of each job
+        let results: Vec<(Option<JobStatus>, Option<String>, Option<String>)> = {
+            let mut pipe = redis::pipe();
+            for id in &ids {
+                pipe.cmd("HMGET").arg(Job::get_redis_key(*id)).arg(JobKey::Status).arg(JobKey::Queue).arg(JobKey::DependsOn);
+            }
+            pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?
+        };
+
+        let mut requeued: Vec<usize> = Vec::new();
+        let mut deferred: Vec<usize> = Vec::new();
+        for (id, (status, queue, depends_on)) in ids.iter().zip(results) {
+            if let (Some(status), Some(queue)) = (status, queue) {
+                if !status.is_requeueable() || !statuses.contains(&status) || queue_name.is_some_and(|x| x != queue) {
+                    continue;
+                }
+                let has_deps = !depends_on.unwrap_or_default().is_empty();
+                if Queue::new(QueueType::Failed, &queue).requeue_job(*id, has_deps, con).await? {
+                    requeued.push(*id);
+                    if has_deps {
+                        deferred.push(*id);
+                    }
+                }
+            }
+        }
+
+        // Jobs whose dependencies have already finished (or will never finish) are resolved now
+        for id in deferred {
+            check_deferred(id, con).await?;
+        }
+        Ok(requeued)
+    }
+
+    async fn cancel_batch(ids: &[usize], con: &mut ConnectionManager) -> RsrqResult<Vec<usize>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Obtain the status and queue of each job
+        let results: Vec<(Option<JobStatus>, Option<String>)> = {
+            let mut pipe = redis::pipe();
+            for id in ids {
+                let key = Job::get_redis_key(*id);
+                pipe.cmd("HMGET").arg(&key).arg(JobKey::Status).arg(JobKey::Queue);
+            }
+            pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?
+        };
+
+        // Move each cancellable job into the failed queue
+        let mut cancelled: Vec<usize> = Vec::new();
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (id, (status, queue)) in ids.iter().zip(results) {
+            if let (Some(status), Some(queue)) = (status, queue) {
+                if status.is_cancellable() {
+                    pipe.hset(Job::get_redis_key(*id), JobKey::Status, JobStatus::Cancelled);
+                    Queue::new(status.to_queue_type(), &queue).pipe_remove_job_id(*id, &mut pipe);
+                    Queue::new(QueueType::Failed, &queue).pipe_add_job_id(*id, &mut pipe);
+                    cancelled.push(*id);
+                }
+            }
+        }
+        if !cancelled.is_empty() {
+            let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+        }
+        Ok(cancelled)
+    }
+}
@@ -6,13 +6,17 @@ use crate::model::error::RsrqError;
 use crate::model::queue::queue_type::QueueType;
 use crate::model::types::RsrqResult;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JobStatus {
     Queued,
     Running,
     Finished,
     Failed,
     Cancelled,
+    TimedOut,
+    Delayed,
+    Deferred,
+    Scheduled,
 }
 
 impl JobStatus {
@@ -23,10 +27,19 @@ impl JobStatus {
             "finished" => Ok(JobStatus::Finished),
             "failed" => Ok(JobStatus::Failed),
             "cancelled" => Ok(JobStatus::Cancelled),
+            "timed_out" => Ok(JobStatus::TimedOut),
+            "delayed" => Ok(JobStatus::Delayed),
+            "deferred" => Ok(JobStatus::Deferred),
+            "scheduled" => Ok(JobStatus::Scheduled),
             _ => Err(RsrqError::CmdParserError(format!("Invalid job status: {}", s))),
         }
     }
 
+    /// True if the job is in the failed queue and can be re-queued.
+    pub fn is_requeueable(&self) -> bool {
+        matches!(self, JobStatus::Failed | JobStatus::Cancelled | JobStatus::TimedOut)
+    }
+
     pub fn to_queue_type(&self) -> QueueType {
         match self {
             JobStatus::Queued => QueueType::Queued,
@@ -34,6 +47,25 @@ impl JobStatus {
             JobStatus::Finished => QueueType::Finished,
             JobStatus::Failed => QueueType::Failed,
             JobStatus::Cancelled => QueueType::Failed,
+            JobStatus::TimedOut => QueueType::Failed,
+            JobStatus::Delayed => QueueType::Delayed,
+            JobStatus::Deferred => QueueType::Deferred,
+            JobStatus::Scheduled => QueueType::Scheduled,
+        }
+    }
+
+    /// True if the job has not yet reached a terminal state.
+    pub fn is_cancellable(&self) -> bool {
+        match self {
+            JobStatus::Queued => true,
+            JobStatus::Running => true,
+            JobStatus::Finished => false,
+            JobStatus::Failed => false,
+            JobStatus::Cancelled => false,
+            JobStatus::TimedOut => false,
+            JobStatus::Delayed => true,
+            JobStatus::Deferred => true,
+            JobStatus::Scheduled => true,
         }
     }
 }
@@ -46,10 +78,22 @@ impl fmt::Display for JobStatus {
             JobStatus::Finished => write!(f, "finished"),
             JobStatus::Failed => write!(f, "failed"),
             JobStatus::Cancelled => write!(f, "cancelled"),
+            JobStatus::TimedOut => write!(f, "timed_out"),
+            JobStatus::Delayed => write!(f, "delayed"),
+            JobStatus::Deferred => write!(f, "deferred"),
+            JobStatus::Scheduled => write!(f, "scheduled"),
         }
     }
 }
 
+impl std::str::FromStr for JobStatus {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JobStatus::from_string(s)
+    }
+}
+
 impl ToRedisArgs for JobStatus {
     fn write_redis_args<W>(&self, out: &mut W) where W: ?Sized + RedisWrite {
         ToRedisArgs::write_redis_args(&self.to_string(), out);
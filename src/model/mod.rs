@@ -6,6 +6,13 @@ pub mod progress_bar;
 pub mod shutdown_handler;
 pub mod types;
 pub mod command;
+pub mod output;
+pub mod format;
 pub mod enqueue_file;
+pub mod enqueue_format;
 pub mod cli;
 pub mod process;
+pub mod schedule;
+pub mod snakemake;
+pub mod compat;
+pub mod array;
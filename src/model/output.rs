@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+
+/// The default number of bytes kept from the end of each stream when writing to files.
+pub const DEFAULT_TAIL_BYTES: usize = 64 * 1024;
+
+/// Files that the stdout and stderr of a job are written to.
+#[derive(Debug, Clone)]
+pub struct OutputFiles {
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+    /// The number of bytes kept in memory from the end of each stream.
+    pub tail_bytes: usize,
+}
+
+impl OutputFiles {
+    /// Create the paths from a template relative to the directory, where "{id}", "{queue}",
+    /// "{attempt}" and "{stream}" (stdout/stderr) are substituted.
+    pub fn new(dir: &str, template: &str, job_id: usize, queue: &str, attempt: u32, tail_bytes: usize) -> OutputFiles {
+        OutputFiles {
+            stdout: render_path(dir, template, job_id, queue, attempt, "stdout"),
+            stderr: render_path(dir, template, job_id, queue, attempt, "stderr"),
+            tail_bytes,
+        }
+    }
+}
+
+fn render_path(dir: &str, template: &str, job_id: usize, queue: &str, attempt: u32, stream: &str) -> PathBuf {
+    let name = template
+        .replace("{id}", &job_id.to_string())
+        .replace("{queue}", queue)
+        .replace("{attempt}", &attempt.to_string())
+        .replace("{stream}", stream);
+    Path::new(dir).join(name)
+}
+
+#[test]
+fn test_output_files() {
+    let files = OutputFiles::new("/tmp/logs", "{queue}/{id}.{stream}", 12, "default", 1, 10);
+    assert_eq!(files.stdout, PathBuf::from("/tmp/logs/default/12.stdout"));
+    assert_eq!(files.stderr, PathBuf::from("/tmp/logs/default/12.stderr"));
+
+    let files = OutputFiles::new("logs", "{id}_{attempt}.log.{stream}", 3, "q", 2, 10);
+    assert_eq!(files.stdout, PathBuf::from("logs/3_2.log.stdout"));
+}
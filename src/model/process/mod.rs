@@ -1,4 +1,6 @@
 pub mod rsrq_process;
 pub mod rsrq_process_key;
 pub mod process_jobs;
-pub mod rsrq_process_state;
\ No newline at end of file
+pub mod rsrq_process_state;
+pub mod orphan_policy;
+pub mod reaper;
@@ -0,0 +1,40 @@
+use std::fmt;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// What happens to the running jobs of a worker process that has died (or is shutting down).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Place the jobs back into the queue to be run again.
+    Requeue,
+    /// Mark the jobs as failed.
+    Fail,
+}
+
+impl OrphanPolicy {
+    pub fn from_string(value: &str) -> RsrqResult<OrphanPolicy> {
+        match value {
+            "requeue" => Ok(OrphanPolicy::Requeue),
+            "fail" => Ok(OrphanPolicy::Fail),
+            _ => Err(RsrqError::ParserError(format!("Invalid orphan policy: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for OrphanPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrphanPolicy::Requeue => write!(f, "requeue"),
+            OrphanPolicy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+impl std::str::FromStr for OrphanPolicy {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OrphanPolicy::from_string(s)
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, HashSet};
+
+use log::warn;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+use crate::config::REAP_LOCK_KEY;
+use crate::model::error::RsrqError;
+use crate::model::job::attempt::JobAttempt;
+use crate::model::job::dependency::resolve_dependents;
+use crate::model::job::key::JobKey;
+use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+use crate::model::process::orphan_policy::OrphanPolicy;
+use crate::model::process::rsrq_process::Process;
+use crate::model::queue::queue_type::QueueType;
+use crate::model::queue::rsrq_queue::Queue;
+use crate::model::types::RsrqResult;
+use crate::util::time::get_timestamp_s;
+
+/// The number of seconds a reaper may hold the lock for a dead process.
+const REAP_LOCK_SECS: u64 = 300;
+
+/// A summary of the processes and jobs that were cleaned up by the reaper.
+#[derive(Debug, Default)]
+pub struct ReapResult {
+    pub proc_ids: Vec<usize>,
+    pub requeued: Vec<usize>,
+    pub failed: Vec<usize>,
+}
+
+/// Find all processes that have not sent a heartbeat within the specified number of seconds
+/// and remove them from Redis. Any running jobs that belong to a process that no longer
+/// exists are then re-queued or failed depending on the policy.
+pub async fn reap_stale_processes(stale_after_secs: u64, policy: OrphanPolicy, con: &mut ConnectionManager) -> RsrqResult<ReapResult> {
+    let now = get_timestamp_s()?;
+    let mut out = ReapResult::default();
+
+    // Remove processes that have stopped sending heartbeats
+    for proc in Process::load_all(con).await? {
+        if proc.is_stale(now, stale_after_secs) {
+            warn!("Process {} ({}:{}) has not sent a heartbeat in {} seconds, removing it.", proc.id, proc.hostname, proc.pid, now.saturating_sub(proc.last_heartbeat));
+            proc.delete(con).await?;
+            out.proc_ids.push(proc.id);
+        }
+    }
+
+    // Group the running jobs by the process that is running them
+    let mut by_proc: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for queue in Queue::find_all(con).await? {
+        if !matches!(queue.q_type, QueueType::Running) {
+            continue;
+        }
+        let job_ids: Vec<usize> = con.lrange(&queue.key, 0, -1).await.map_err(RsrqError::RedisOpError)?;
+        for job_id in job_ids {
+            let proc_id: Option<String> = con.hget(Job::get_redis_key(job_id), JobKey::ProcessId).await.map_err(RsrqError::RedisOpError)?;
+            if let Some(Ok(proc_id)) = proc_id.map(|x| x.parse::<usize>()) {
+                by_proc.entry(proc_id).or_default().push(job_id);
+            }
+        }
+    }
+
+    // Release the jobs of any process that no longer exists
+    let mut seen: HashSet<usize> = out.proc_ids.iter().copied().collect();
+    for (proc_id, job_ids) in by_proc {
+        let exists: bool = con.exists(Process::get_key(proc_id)).await.map_err(RsrqError::RedisOpError)?;
+        if exists {
+            continue;
+        }
+
+        // Only one reaper may clean up after a given process
+        let lock_key = format!("{}:{}", REAP_LOCK_KEY, proc_id);
+        let lock: Option<String> = redis::cmd("SET").arg(&lock_key).arg(1).arg("NX").arg("EX").arg(REAP_LOCK_SECS)
+            .query_async(con).await.map_err(RsrqError::RedisOpError)?;
+        if lock.is_none() {
+            continue;
+        }
+
+        let reason = format!("The worker process ({}) running this job was lost.", proc_id);
+        let (requeued, failed) = release_jobs(proc_id, &job_ids, policy, &reason, con).await?;
+        out.requeued.extend(requeued);
+        out.failed.extend(failed);
+        if seen.insert(proc_id) {
+            out.proc_ids.push(proc_id);
+        }
+
+        let _: () = con.del(&lock_key).await.map_err(RsrqError::RedisOpError)?;
+    }
+
+    Ok(out)
+}
+
+/// Release jobs that are running under a process, returning the ids of the jobs that were
+/// re-queued and failed. Jobs that have since finished, or have been picked up by another
+/// process, are left untouched.
+pub async fn release_jobs(proc_id: usize, job_ids: &[usize], policy: OrphanPolicy, reason: &str, con: &mut ConnectionManager) -> RsrqResult<(Vec<usize>, Vec<usize>)> {
+    let mut requeued: Vec<usize> = Vec::new();
+    let mut failed: Vec<usize> = Vec::new();
+
+    for &job_id in job_ids {
+        let job = match Job::load(job_id, con).await {
+            Ok(job) => job,
+            Err(RsrqError::JobNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        if job.status != JobStatus::Running || job.process_id != Some(proc_id) {
+            continue;
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        Queue::new(QueueType::Running, &job.queue).pipe_remove_job_id(job_id, &mut pipe);
+
+        match policy {
+            OrphanPolicy::Requeue => {
+                pipe.hset(&job.key, JobKey::Status, JobStatus::Queued);
+                Queue::new(QueueType::Queued, &job.queue).pipe_add_job_id(job_id, &mut pipe);
+                requeued.push(job_id);
+            }
+            OrphanPolicy::Fail => {
+                let now = get_timestamp_s()?;
+                let started = job.started.unwrap_or(now);
+                let mut attempts = job.attempts.clone();
+                attempts.push(JobAttempt {
+                    attempt: job.attempt,
+                    status: JobStatus::Failed,
+                    exit_code: -1,
+                    stderr: reason.to_string(),
+                    started,
+                    finished: now,
+                    duration_ms: now.saturating_sub(started) as u128 * 1000,
+                });
+                let job_update_arr = [
+                    (JobKey::Status, JobStatus::Failed.to_string()),
+                    (JobKey::Finished, now.to_string()),
+                    (JobKey::Stderr, reason.to_string()),
+                    (JobKey::ExitCode, "-1".to_string()),
+                    (JobKey::Attempts, JobAttempt::to_json_list(&attempts)),
+                ];
+                pipe.hset_multiple(&job.key, &job_update_arr);
+                Queue::new(QueueType::Failed, &job.queue).pipe_add_job_id(job_id, &mut pipe);
+                failed.push(job_id);
+            }
+        }
+        let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+    }
+
+    // Jobs waiting on a failed job can no longer run
+    for job_id in &failed {
+        resolve_dependents(*job_id, JobStatus::Failed, con).await?;
+    }
+
+    Ok((requeued, failed))
+}
@@ -1,15 +1,33 @@
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
+use serde_json::{json, Value};
 
 use crate::config::{PROC_KEY, UID_KEY_PROC};
 use crate::model::error::RsrqError;
 use crate::model::process::rsrq_process_key::ProcessKey;
 use crate::model::process::rsrq_process_state::ProcessState;
 use crate::model::types::RsrqResult;
+use crate::model::worker::control::WorkerControl;
+use crate::util::parsing::{btree_get, btree_get_opt};
 use crate::util::redis::get_next_uid;
 use crate::util::system::{get_hostname, get_pid};
 use crate::util::time::get_timestamp_s;
 
+lazy_static! {
+    /// Updates the heartbeat of a process, unless it has been removed by a reaper.
+    static ref SCRIPT_HEARTBEAT: redis::Script = redis::Script::new(r"
+        if redis.call('EXISTS', KEYS[1]) == 0 then
+            return 0
+        end
+        redis.call('HSET', KEYS[1], ARGV[1], ARGV[2], ARGV[3], ARGV[4], ARGV[5], ARGV[6])
+        return 1
+    ");
+}
+
+#[derive(Debug)]
 pub struct Process {
     pub key: String,
 
@@ -21,12 +39,14 @@ pub struct Process {
     pub state: ProcessState,
     pub n_running: usize,
 
-    pub queue: String,
+    /// The queues the process claims jobs from, in order of priority.
+    pub queues: Vec<String>,
     pub workers: u32,
     pub max_duration_sec: Option<u64>,
     pub max_jobs: Option<u32>,
     pub burst: bool,
     pub poll_ms: u64,
+    pub heartbeat_ms: u64,
 }
 
 
@@ -35,7 +55,8 @@ impl Process {
         format!("{}:{}", PROC_KEY, id)
     }
 
-    pub async fn new(queue: &str, workers: u32, max_duration_sec: Option<u64>, max_jobs: Option<u32>, burst: bool, poll_ms: u64, con: &mut ConnectionManager) -> RsrqResult<Process> {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(queues: &[String], workers: u32, max_duration_sec: Option<u64>, max_jobs: Option<u32>, burst: bool, poll_ms: u64, heartbeat_ms: u64, con: &mut ConnectionManager) -> RsrqResult<Process> {
         // Assign the next worker id to this worker
         let worker_id = get_next_uid(UID_KEY_PROC, con).await?;
 
@@ -53,12 +74,13 @@ impl Process {
             last_heartbeat: birth,
             state: ProcessState::Idle,
             n_running: 0,
-            queue: queue.to_string(),
+            queues: queues.to_vec(),
             workers,
             max_duration_sec,
             max_jobs,
             burst,
             poll_ms,
+            heartbeat_ms,
         };
 
         // Update Redis
@@ -67,7 +89,7 @@ impl Process {
         // Return the worker
         Ok(proc)
     }
-    pub fn to_array(&self) -> [(ProcessKey, String); 13] {
+    pub fn to_array(&self) -> [(ProcessKey, String); 14] {
         // Parse the optional attributes
         let max_jobs = {
             if let Some(max_jobs) = self.max_jobs {
@@ -91,41 +113,168 @@ impl Process {
             (ProcessKey::LastHeartbeat, self.last_heartbeat.to_string()),
             (ProcessKey::State, self.state.to_string()),
             (ProcessKey::NumRunning, self.n_running.to_string()),
-            (ProcessKey::Queue, self.queue.to_string()),
+            (ProcessKey::Queue, self.queues.join(",")),
             (ProcessKey::Workers, self.workers.to_string()),
             (ProcessKey::MaxDurationSec, max_duration_sec),
             (ProcessKey::MaxJobs, max_jobs),
             (ProcessKey::Burst, self.burst.to_string()),
             (ProcessKey::PollMs, self.poll_ms.to_string()),
+            (ProcessKey::HeartbeatMs, self.heartbeat_ms.to_string()),
         ]
     }
     pub async fn push(&self, con: &mut ConnectionManager) -> RsrqResult<()> {
         let arr = self.to_array();
-        con.hset_multiple(&self.key, &arr).await.map_err(RsrqError::RedisOpError)?;
+        let _: () = con.hset_multiple(&self.key, &arr).await.map_err(RsrqError::RedisOpError)?;
         Ok(())
     }
 
+    /// Load a process from Redis, returns None if it does not exist.
+    pub async fn load(id: usize, con: &mut ConnectionManager) -> RsrqResult<Option<Process>> {
+        let key = Process::get_key(id);
+        let map: BTreeMap<String, String> = con.hgetall(&key).await.map_err(RsrqError::RedisOpError)?;
+        if map.is_empty() {
+            return Ok(None);
+        }
+        let state_str: String = btree_get(&map, ProcessKey::State)?;
+        let queue_str: String = btree_get(&map, ProcessKey::Queue)?;
+        let proc = Process {
+            id: btree_get(&map, ProcessKey::Id)?,
+            key,
+            hostname: btree_get(&map, ProcessKey::Hostname)?,
+            pid: btree_get(&map, ProcessKey::Pid)?,
+            birth: btree_get(&map, ProcessKey::Birth)?,
+            last_heartbeat: btree_get(&map, ProcessKey::LastHeartbeat)?,
+            state: ProcessState::from_string(&state_str)?,
+            n_running: btree_get(&map, ProcessKey::NumRunning)?,
+            queues: queue_str.split(',').map(|x| x.to_string()).collect(),
+            workers: btree_get(&map, ProcessKey::Workers)?,
+            max_duration_sec: btree_get_opt(&map, ProcessKey::MaxDurationSec)?,
+            max_jobs: btree_get_opt(&map, ProcessKey::MaxJobs)?,
+            burst: btree_get(&map, ProcessKey::Burst)?,
+            poll_ms: btree_get(&map, ProcessKey::PollMs)?,
+            heartbeat_ms: btree_get_opt(&map, ProcessKey::HeartbeatMs)?.unwrap_or(0),
+        };
+        Ok(Some(proc))
+    }
+
+    /// Load all processes that are registered in Redis, sorted by id.
+    pub async fn load_all(con: &mut ConnectionManager) -> RsrqResult<Vec<Process>> {
+        let mut ids: Vec<usize> = Vec::new();
+        {
+            let pattern = format!("{}:*", PROC_KEY);
+            let mut keys: redis::AsyncIter<String> = con.scan_match(&pattern).await.map_err(RsrqError::RedisOpError)?;
+            while let Some(key) = keys.next_item().await {
+                if let Some(Ok(id)) = key.rsplit(':').next().map(|x| x.parse::<usize>()) {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+
+        let mut out = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(proc) = Process::load(id, con).await? {
+                out.push(proc);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Delete the process, along with any control commands that it did not read.
     pub async fn delete(&self, con: &mut ConnectionManager) -> RsrqResult<()> {
-        con.del(&self.key).await.map_err(RsrqError::RedisOpError)?;
+        let _: () = con.del(&[&self.key, &WorkerControl::get_key(self.id)]).await.map_err(RsrqError::RedisOpError)?;
         Ok(())
     }
 
-    pub async fn update_running(&mut self, n_running: usize, con: &mut ConnectionManager) -> RsrqResult<()> {
+    /// The columns of the TSV output, in the order of the JSON fields.
+    pub const TSV_COLUMNS: &'static [&'static str] = &["id", "hostname", "pid", "state", "queues", "n_running",
+        "heartbeat_age_s", "workers", "max_jobs", "max_duration_sec", "burst"];
+
+    /// The attributes of the process as JSON, including the seconds since the last heartbeat.
+    pub fn to_json(&self, now: u64) -> Value {
+        json!({
+            "id": self.id,
+            "hostname": self.hostname,
+            "pid": self.pid,
+            "state": self.state.to_string(),
+            "queues": self.queues,
+            "n_running": self.n_running,
+            "heartbeat_age_s": now.saturating_sub(self.last_heartbeat),
+            "workers": self.workers,
+            "max_jobs": self.max_jobs,
+            "max_duration_sec": self.max_duration_sec,
+            "burst": self.burst,
+        })
+    }
+
+    /// True if the process has not sent a heartbeat within the specified number of seconds.
+    /// Processes that poll or send heartbeats infrequently are given at least three of those intervals.
+    pub fn is_stale(&self, now: u64, stale_after_secs: u64) -> bool {
+        let threshold = stale_after_secs.max(3 * self.poll_ms.max(self.heartbeat_ms) / 1000);
+        now.saturating_sub(self.last_heartbeat) > threshold
+    }
+
+    /// Send a heartbeat with the number of running jobs, returns false if the process no longer exists.
+    /// A process that was reaped is not re-created, as its running jobs have already been released.
+    pub async fn update_running(&mut self, n_running: usize, con: &mut ConnectionManager) -> RsrqResult<bool> {
         self.last_heartbeat = get_timestamp_s()?;
-        self.state = if n_running > 0 {
-            ProcessState::Running
-        } else {
-            ProcessState::Idle
+        self.state = match self.state {
+            ProcessState::Draining | ProcessState::Stopping => self.state,
+            _ if n_running > 0 => ProcessState::Running,
+            _ => ProcessState::Idle,
         };
         self.n_running = n_running;
 
-        let values = [
-            (ProcessKey::LastHeartbeat, self.last_heartbeat.to_string()),
-            (ProcessKey::State, self.state.to_string()),
-            (ProcessKey::NumRunning, self.n_running.to_string()),
-        ];
-        con.hset_multiple(&self.key, &values).await.map_err(RsrqError::RedisOpError)?;
-        Ok(())
+        let exists: bool = SCRIPT_HEARTBEAT
+            .key(&self.key)
+            .arg(ProcessKey::LastHeartbeat)
+            .arg(self.last_heartbeat)
+            .arg(ProcessKey::State)
+            .arg(self.state.to_string())
+            .arg(ProcessKey::NumRunning)
+            .arg(self.n_running)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(exists)
     }
 }
 
+#[test]
+fn test_is_stale() {
+    let mut proc = Process {
+        key: Process::get_key(1),
+        id: 1,
+        hostname: "localhost".to_string(),
+        pid: 1,
+        birth: 0,
+        last_heartbeat: 1000,
+        state: ProcessState::Idle,
+        n_running: 0,
+        queues: vec!["default".to_string()],
+        workers: 1,
+        max_duration_sec: None,
+        max_jobs: None,
+        burst: false,
+        poll_ms: 1000,
+        heartbeat_ms: 5000,
+    };
+    assert!(!proc.is_stale(1000, 60));
+    assert!(!proc.is_stale(1060, 60));
+    assert!(proc.is_stale(1061, 60));
+
+    // Clock skew between hosts should not cause a process to be stale
+    assert!(!proc.is_stale(900, 60));
+
+    // Slow polling processes are given at least three poll intervals
+    proc.poll_ms = 60000;
+    assert!(!proc.is_stale(1120, 60));
+    assert!(proc.is_stale(1181, 60));
+
+    // As are processes that send heartbeats infrequently
+    proc.poll_ms = 1000;
+    proc.heartbeat_ms = 90000;
+    assert!(!proc.is_stale(1270, 60));
+    assert!(proc.is_stale(1271, 60));
+
+    let json = proc.to_json(1000);
+    assert_eq!(json.as_object().unwrap().keys().collect::<Vec<&String>>(), Process::TSV_COLUMNS);
+}
@@ -20,6 +20,7 @@ pub enum ProcessKey {
     MaxJobs,
     Burst,
     PollMs,
+    HeartbeatMs,
 }
 
 impl ProcessKey {
@@ -38,6 +39,7 @@ impl ProcessKey {
             "max_jobs" => Ok(ProcessKey::MaxJobs),
             "burst" => Ok(ProcessKey::Burst),
             "poll_ms" => Ok(ProcessKey::PollMs),
+            "heartbeat_ms" => Ok(ProcessKey::HeartbeatMs),
             _ => Err(RsrqError::ParserError(value.to_string())),
         }
     }
@@ -59,6 +61,7 @@ impl fmt::Display for ProcessKey {
             ProcessKey::MaxJobs => write!(f, "max_jobs"),
             ProcessKey::Burst => write!(f, "burst"),
             ProcessKey::PollMs => write!(f, "poll_ms"),
+            ProcessKey::HeartbeatMs => write!(f, "heartbeat_ms"),
         }
     }
 }
@@ -5,9 +5,14 @@ use redis::{ErrorKind, FromRedisValue, RedisResult, RedisWrite, ToRedisArgs, Val
 use crate::model::error::RsrqError;
 use crate::model::types::RsrqResult;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessState {
     Idle,
     Running,
+    /// Finishing the running jobs without starting new ones.
+    Draining,
+    /// Killing the running jobs before exiting.
+    Stopping,
 }
 
 
@@ -16,6 +21,8 @@ impl ProcessState {
         match value {
             "idle" => Ok(ProcessState::Idle),
             "running" => Ok(ProcessState::Running),
+            "draining" => Ok(ProcessState::Draining),
+            "stopping" => Ok(ProcessState::Stopping),
             _ => Err(RsrqError::ParserError(value.to_string())),
         }
     }
@@ -26,6 +33,8 @@ impl fmt::Display for ProcessState {
         match self {
             ProcessState::Idle => write!(f, "idle"),
             ProcessState::Running => write!(f, "running"),
+            ProcessState::Draining => write!(f, "draining"),
+            ProcessState::Stopping => write!(f, "stopping"),
         }
     }
 }
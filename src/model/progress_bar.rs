@@ -25,11 +25,7 @@ pub struct RsrqProgressBar {
 
 
 fn format_remaining_secs_as_str(max_runtime_secs: u64, elapsed_secs: u64) -> String {
-    let remaining_secs: u64 = if elapsed_secs < max_runtime_secs {
-        max_runtime_secs - elapsed_secs
-    } else {
-        0
-    };
+    let remaining_secs: u64 = max_runtime_secs.saturating_sub(elapsed_secs);
     let hours = remaining_secs / 3600;
     let minutes = (remaining_secs % 3600) / 60;
     let seconds = remaining_secs % 60;
@@ -66,6 +62,11 @@ impl RsrqProgressBar {
         Ok(bar)
     }
 
+    /// Change the number of workers used to estimate the remaining time.
+    pub fn set_workers(&mut self, workers: u32) {
+        self.workers = workers;
+    }
+
     pub fn track_job_start(&mut self, job_id: usize) {
         self.job_start.insert(job_id, Instant::now());
     }
@@ -191,7 +192,7 @@ impl RsrqProgressBar {
             // These jobs will always contribute to the estimated running time
             for value in self.job_start.values() {
                 let elapsed = value.elapsed().as_millis();
-                let remaining = cmp::max(avg_duration - elapsed, 0);
+                let remaining = avg_duration.saturating_sub(elapsed);
                 total_ms += remaining;
             }
 
@@ -1,2 +1,5 @@
 pub mod rsrq_queue;
-pub mod queue_type;
\ No newline at end of file
+pub mod queue_type;
+pub mod queue_info;
+pub mod paused;
+pub mod status_group;
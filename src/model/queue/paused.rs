@@ -0,0 +1,32 @@
+use std::collections::HashSet;
+
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+use crate::config::PAUSED_KEY;
+use crate::model::error::RsrqError;
+use crate::model::queue::queue_type::QueueType;
+use crate::model::queue::rsrq_queue::Queue;
+use crate::model::types::RsrqResult;
+
+/// Stop workers from claiming new jobs from the queue, returns false if it was already paused.
+/// Jobs that are already running are not affected.
+pub async fn pause_queue(name: &str, con: &mut ConnectionManager) -> RsrqResult<bool> {
+    let added: usize = con.sadd(PAUSED_KEY, name).await.map_err(RsrqError::RedisOpError)?;
+    Ok(added > 0)
+}
+
+/// Allow workers to claim jobs from the queue again, returns false if it was not paused.
+/// Workers waiting on the queue are woken, as they are only notified of jobs as they are enqueued.
+pub async fn resume_queue(name: &str, con: &mut ConnectionManager) -> RsrqResult<bool> {
+    let removed: usize = con.srem(PAUSED_KEY, name).await.map_err(RsrqError::RedisOpError)?;
+    if removed > 0 {
+        let _: () = con.publish(Queue::new(QueueType::Queued, name).key, "").await.map_err(RsrqError::RedisOpError)?;
+    }
+    Ok(removed > 0)
+}
+
+/// The names of all queues that are paused.
+pub async fn get_paused_queues(con: &mut ConnectionManager) -> RsrqResult<HashSet<String>> {
+    con.smembers(PAUSED_KEY).await.map_err(RsrqError::RedisOpError)
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use redis::aio::ConnectionManager;
+use serde_json::{json, Value};
+
+use crate::model::error::RsrqError;
+use crate::model::job::key::JobKey;
+use crate::model::job::rsrq_job::Job;
+use crate::model::job::status::JobStatus;
+use crate::model::queue::paused::get_paused_queues;
+use crate::model::queue::queue_type::QueueType;
+use crate::model::queue::rsrq_queue::Queue;
+use crate::model::queue::status_group::StatusGroup;
+use crate::model::snakemake::properties::META_RULE;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_string_map;
+
+/// The status, queue, priority, metadata, and array of a job.
+type JobDetails = (Option<JobStatus>, Option<String>, Option<String>, Option<String>, Option<String>);
+
+/// The number of jobs in each state for a single queue (or Snakemake rule, or array).
+#[derive(Debug)]
+pub struct QueueInfo {
+    /// The name of the queue, the rule if grouped by rule, or the array id if grouped by array.
+    pub name: String,
+    /// Workers do not claim jobs from paused queues.
+    pub paused: bool,
+    pub n_finished: usize,
+    pub n_failed: usize,
+    pub n_queued: usize,
+    pub n_running: usize,
+    pub n_cancelled: usize,
+    pub n_timed_out: usize,
+    pub n_delayed: usize,
+    pub n_deferred: usize,
+    pub n_scheduled: usize,
+    /// The number of queued jobs for each priority.
+    pub n_queued_by_priority: BTreeMap<i16, usize>,
+}
+
+impl QueueInfo {
+    pub fn new(name: &str) -> QueueInfo {
+        QueueInfo {
+            name: name.to_string(),
+            paused: false,
+            n_finished: 0,
+            n_failed: 0,
+            n_queued: 0,
+            n_running: 0,
+            n_cancelled: 0,
+            n_timed_out: 0,
+            n_delayed: 0,
+            n_deferred: 0,
+            n_scheduled: 0,
+            n_queued_by_priority: BTreeMap::new(),
+        }
+    }
+
+    /// The columns of the TSV output, in the order of the JSON fields.
+    pub const TSV_COLUMNS: &'static [&'static str] = &["name", "paused", "deferred", "scheduled", "queued", "delayed",
+        "running", "finished", "failed", "cancelled", "timed_out", "queued_by_priority"];
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "paused": self.paused,
+            "deferred": self.n_deferred,
+            "scheduled": self.n_scheduled,
+            "queued": self.n_queued,
+            "delayed": self.n_delayed,
+            "running": self.n_running,
+            "finished": self.n_finished,
+            "failed": self.n_failed,
+            "cancelled": self.n_cancelled,
+            "timed_out": self.n_timed_out,
+            "queued_by_priority": self.format_queued_by_priority(),
+        })
+    }
+
+    /// The number of queued jobs for each priority, highest priority first (e.g. "10:2,0:5").
+    pub fn format_queued_by_priority(&self) -> String {
+        self.n_queued_by_priority.iter().rev()
+            .map(|(priority, count)| format!("{}:{}", priority, count))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// Increment the counter for the given job status.
+    pub fn add(&mut self, status: JobStatus, priority: i16) {
+        match status {
+            JobStatus::Queued => {
+                self.n_queued += 1;
+                *self.n_queued_by_priority.entry(priority).or_insert(0) += 1;
+            }
+            JobStatus::Running => self.n_running += 1,
+            JobStatus::Finished => self.n_finished += 1,
+            JobStatus::Failed => self.n_failed += 1,
+            JobStatus::Cancelled => self.n_cancelled += 1,
+            JobStatus::TimedOut => self.n_timed_out += 1,
+            JobStatus::Delayed => self.n_delayed += 1,
+            JobStatus::Deferred => self.n_deferred += 1,
+            JobStatus::Scheduled => self.n_scheduled += 1,
+        };
+    }
+
+    /// Collect the job counts for all queues (or those belonging to the specified queue), sorted by name.
+    /// If grouped by rule (or array), the counts are for each Snakemake rule (or array) in those queues instead,
+    /// with arrays sorted by id.
+    pub async fn load(queue_name: Option<&str>, group: StatusGroup, con: &mut ConnectionManager) -> RsrqResult<Vec<QueueInfo>> {
+
+        // Obtain either all queues, or those belonging to the specified
+        let queues = match queue_name {
+            None => {
+                Queue::find_all(con).await?
+            }
+            Some(queue) => {
+                let mut out = Vec::new();
+                for queue_type in QueueType::get_types() {
+                    let cur_queue = Queue::new(queue_type, queue);
+                    cur_queue.migrate(con).await?;
+                    out.push(cur_queue);
+                }
+                out
+            }
+        };
+
+        // Iterate over each queue and collect the job ids
+        let job_ids: Vec<Vec<usize>> = {
+            let mut pipe = redis::pipe();
+            for queue in &queues {
+                queue.pipe_job_ids(&mut pipe);
+            }
+            pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?
+        };
+
+        // Flatten the job ids into a single vector
+        let job_ids: Vec<usize> = job_ids.into_iter().flatten().collect();
+
+        // Obtain the status, queue, priority, metadata, and array of each job
+        let job_details: Vec<JobDetails> = {
+            let mut pipe = redis::pipe();
+            for job_id in job_ids {
+                let job_key = Job::get_redis_key(job_id);
+                pipe.cmd("HMGET").arg(&job_key).arg(JobKey::Status).arg(JobKey::Queue).arg(JobKey::Priority).arg(JobKey::Metadata).arg(JobKey::ArrayId);
+            }
+            pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?
+        };
+
+        // Count the jobs in each queue (or rule, or array)
+        let mut queue_infos: BTreeMap<String, QueueInfo> = BTreeMap::new();
+        for (status, queue, priority, metadata, array_id) in job_details {
+            let name = match group {
+                StatusGroup::Queue => queue,
+                StatusGroup::Rule => parse_string_map(&metadata.unwrap_or_default()).ok().and_then(|mut x| x.remove(META_RULE)),
+                StatusGroup::Array => array_id.filter(|x| !x.is_empty()),
+            };
+            if let (Some(status), Some(name)) = (status, name) {
+                let priority = priority.and_then(|x| x.parse::<i16>().ok()).unwrap_or(0);
+                let queue_info = queue_infos.entry(name.clone()).or_insert(QueueInfo::new(&name));
+                queue_info.add(status, priority);
+            }
+        }
+        match group {
+            StatusGroup::Queue => {}
+            StatusGroup::Rule => return Ok(queue_infos.into_values().collect()),
+            StatusGroup::Array => {
+                let mut out: Vec<QueueInfo> = queue_infos.into_values().collect();
+                out.sort_by_key(|x| x.name.parse::<usize>().unwrap_or(usize::MAX));
+                return Ok(out);
+            }
+        }
+
+        // Paused queues are included even if they have no jobs
+        for name in get_paused_queues(con).await? {
+            if queue_name.map_or(true, |x| x == name) {
+                queue_infos.entry(name.clone()).or_insert(QueueInfo::new(&name)).paused = true;
+            }
+        }
+
+        Ok(queue_infos.into_values().collect())
+    }
+}
+
+#[test]
+fn test_format_queued_by_priority() {
+    let mut info = QueueInfo::new("default");
+    assert_eq!(info.format_queued_by_priority(), "");
+    info.add(JobStatus::Queued, 0);
+    info.add(JobStatus::Queued, 0);
+    info.add(JobStatus::Queued, 10);
+    info.add(JobStatus::Queued, -5);
+    info.add(JobStatus::Running, 10);
+    assert_eq!(info.n_queued, 4);
+    assert_eq!(info.format_queued_by_priority(), "10:1,0:2,-5:1");
+
+    let json = info.to_json();
+    assert_eq!(json.as_object().unwrap().keys().collect::<Vec<&String>>(), QueueInfo::TSV_COLUMNS);
+}
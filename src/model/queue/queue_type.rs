@@ -1,15 +1,18 @@
 use std::fmt;
 
-use crate::config::{Q_FAILED, Q_FINISHED, Q_QUEUED, Q_RUNNING};
+use crate::config::{Q_DEFERRED, Q_DELAYED, Q_FAILED, Q_FINISHED, Q_QUEUED, Q_RUNNING, Q_SCHEDULED};
 use crate::model::error::RsrqError;
 use crate::model::types::RsrqResult;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum QueueType {
     Queued,
     Running,
     Finished,
     Failed,
+    Delayed,
+    Deferred,
+    Scheduled,
 }
 
 impl QueueType {
@@ -19,6 +22,9 @@ impl QueueType {
             QueueType::Running => Q_RUNNING,
             QueueType::Finished => Q_FINISHED,
             QueueType::Failed => Q_FAILED,
+            QueueType::Delayed => Q_DELAYED,
+            QueueType::Deferred => Q_DEFERRED,
+            QueueType::Scheduled => Q_SCHEDULED,
         }
     }
 
@@ -28,6 +34,9 @@ impl QueueType {
             Q_RUNNING => Ok(QueueType::Running),
             Q_FINISHED => Ok(QueueType::Finished),
             Q_FAILED => Ok(QueueType::Failed),
+            Q_DELAYED => Ok(QueueType::Delayed),
+            Q_DEFERRED => Ok(QueueType::Deferred),
+            Q_SCHEDULED => Ok(QueueType::Scheduled),
             _ => Err(RsrqError::ParserError(format!("Invalid queue type: {}", string))),
         }
     }
@@ -38,6 +47,9 @@ impl QueueType {
             QueueType::Running,
             QueueType::Finished,
             QueueType::Failed,
+            QueueType::Delayed,
+            QueueType::Deferred,
+            QueueType::Scheduled,
         ]
     }
 }
@@ -50,6 +62,9 @@ impl fmt::Display for QueueType {
             QueueType::Running => write!(f, "{}", Q_RUNNING),
             QueueType::Finished => write!(f, "{}", Q_FINISHED),
             QueueType::Failed => write!(f, "{}", Q_FAILED),
+            QueueType::Delayed => write!(f, "{}", Q_DELAYED),
+            QueueType::Deferred => write!(f, "{}", Q_DEFERRED),
+            QueueType::Scheduled => write!(f, "{}", Q_SCHEDULED),
         }
     }
 }
@@ -1,9 +1,140 @@
+use lazy_static::lazy_static;
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 
+use crate::config::JOB_KEY;
 use crate::model::error::RsrqError;
+use crate::model::job::key::JobKey;
+use crate::model::job::resources::JobResources;
+use crate::model::job::status::JobStatus;
 use crate::model::queue::queue_type::QueueType;
 use crate::model::types::{OptUsizeFuture, RsrqResult};
+use crate::util::time::get_timestamp_s;
+
+/// The number of low bits of the score of the queued sorted set that hold the job id, the priority is stored in
+/// the bits above them. Together they fit within the 53 bits that a double (the type of a score) holds exactly.
+const QUEUED_ID_BITS: u32 = 37;
+
+// The 16 bits of the priority and the bits of the job id must fit in the mantissa of a double
+const _: () = assert!(16 + QUEUED_ID_BITS <= f64::MANTISSA_DIGITS);
+
+/// The largest job id that can be queued (about 1.4e11), larger ids would not be ordered correctly.
+pub const MAX_QUEUED_JOB_ID: usize = (1 << QUEUED_ID_BITS) - 1;
+
+lazy_static! {
+    /// Defines enqueue(queued_key, job_key, id, priority_field) which reads the priority from the job, and
+    /// migrate_queued(queued_key, priority_field) which converts a queued list (used before jobs had priorities)
+    /// into a sorted set, the jobs are then claimed in the order of their ids (i.e. the order they were enqueued).
+    /// The score orders jobs by priority (highest first), then by job id (lowest first).
+    /// The job id is published on the channel named after the queued key to wake idle workers.
+    static ref LUA_ENQUEUE_FN: String = format!(r"
+        local function queued_score(job_key, id, priority_field)
+            local priority = tonumber(redis.call('HGET', job_key, priority_field)) or 0
+            return ({max_priority} - priority) * {id_factor} + tonumber(id)
+        end
+        local function migrate_queued(queued_key, priority_field)
+            if redis.call('TYPE', queued_key).ok ~= 'list' then
+                return
+            end
+            local ids = redis.call('LRANGE', queued_key, 0, -1)
+            redis.call('DEL', queued_key)
+            for _, id in ipairs(ids) do
+                redis.call('ZADD', queued_key, queued_score('{job_key}:' .. id, id, priority_field), id)
+            end
+        end
+        local function enqueue(queued_key, job_key, id, priority_field)
+            migrate_queued(queued_key, priority_field)
+            redis.call('ZADD', queued_key, queued_score(job_key, id, priority_field), id)
+            redis.call('PUBLISH', queued_key, id)
+        end
+    ", max_priority = i16::MAX, id_factor = 1u64 << QUEUED_ID_BITS, job_key = JOB_KEY);
+
+    /// Adds a job to the queued sorted set using the priority stored in the job hash.
+    static ref SCRIPT_ENQUEUE_SRC: String = format!(r"{}
+        enqueue(KEYS[1], KEYS[2], ARGV[1], ARGV[2])
+        return 1
+    ", *LUA_ENQUEUE_FN);
+
+    /// Converts a queued list into a sorted set, if it has not already been converted.
+    static ref SCRIPT_MIGRATE_QUEUED: redis::Script = redis::Script::new(&format!(r"{}
+        migrate_queued(KEYS[1], ARGV[1])
+        return 1
+    ", *LUA_ENQUEUE_FN));
+
+    /// Moves all jobs that are due (score <= ARGV[1]) from a sorted set into the queued set.
+    static ref SCRIPT_PROMOTE_DUE: redis::Script = redis::Script::new(&format!(r"{}
+        local ids = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+        for _, id in ipairs(ids) do
+            local job_key = ARGV[2] .. ':' .. id
+            redis.call('ZREM', KEYS[1], id)
+            enqueue(KEYS[2], job_key, id, ARGV[5])
+            redis.call('HSET', job_key, ARGV[3], ARGV[4])
+        end
+        return #ids
+    ", *LUA_ENQUEUE_FN));
+
+    /// Moves a single job from a set into the queued set, only if it is still a member of the set.
+    static ref SCRIPT_PROMOTE_JOB: redis::Script = redis::Script::new(&format!(r"{}
+        if redis.call('SREM', KEYS[1], ARGV[1]) == 1 then
+            local job_key = ARGV[2] .. ':' .. ARGV[1]
+            enqueue(KEYS[2], job_key, ARGV[1], ARGV[5])
+            redis.call('HSET', job_key, ARGV[3], ARGV[4])
+            return 1
+        end
+        return 0
+    ", *LUA_ENQUEUE_FN));
+
+    /// Moves a job from the failed set back into the queued set (or deferred set), clearing the result fields.
+    static ref SCRIPT_REQUEUE_JOB: redis::Script = redis::Script::new(&format!(r"{}
+        if redis.call('SREM', KEYS[1], ARGV[1]) == 0 then
+            return 0
+        end
+        if ARGV[2] == 'deferred' then
+            redis.call('SADD', KEYS[2], ARGV[1])
+        else
+            enqueue(KEYS[2], KEYS[3], ARGV[1], ARGV[4])
+        end
+        redis.call('HSET', KEYS[3], ARGV[3], ARGV[2])
+        for i = 5, #ARGV do
+            redis.call('HSET', KEYS[3], ARGV[i], '')
+        end
+        return 1
+    ", *LUA_ENQUEUE_FN));
+
+    /// Moves the job with the highest priority from the queued set into the running list.
+    static ref SCRIPT_CLAIM_JOB: redis::Script = redis::Script::new(&format!(r"{}
+        migrate_queued(KEYS[1], ARGV[1])
+        local popped = redis.call('ZPOPMIN', KEYS[1])
+        if #popped == 0 then
+            return false
+        end
+        redis.call('LPUSH', KEYS[2], popped[1])
+        return popped[1]
+    ", *LUA_ENQUEUE_FN));
+
+    /// Moves the job with the highest priority from the queued set into the running list, only if its CPUs
+    /// and memory are within those that are free (a negative value is unlimited).
+    /// Returns {claimed, id, cpus, mem_mb}, or false if the queue is empty.
+    static ref SCRIPT_CLAIM_JOB_IF_FITS: redis::Script = redis::Script::new(&format!(r"{}
+        migrate_queued(KEYS[1], ARGV[6])
+        local head = redis.call('ZRANGE', KEYS[1], 0, 0)
+        if #head == 0 then
+            return false
+        end
+        local id = head[1]
+        local resources = redis.call('HMGET', ARGV[1] .. ':' .. id, ARGV[2], ARGV[3])
+        local cpus = tonumber(resources[1]) or 1
+        local mem_mb = tonumber(resources[2]) or 0
+        local free_cpus = tonumber(ARGV[4])
+        local free_mem_mb = tonumber(ARGV[5])
+        if (free_cpus >= 0 and cpus > free_cpus) or (free_mem_mb >= 0 and mem_mb > free_mem_mb) then
+            return {{0, id, cpus, mem_mb}}
+        end
+        redis.call('ZREM', KEYS[1], id)
+        redis.call('LPUSH', KEYS[2], id)
+        return {{1, id, cpus, mem_mb}}
+    ", *LUA_ENQUEUE_FN));
+}
 
 pub struct Queue {
     pub key: String,
@@ -39,10 +170,38 @@ impl Queue {
         Ok(Queue::new(queue_type, name))
     }
 
+    /// Scan Redis for all keys that represent a queue, queued lists are converted into sorted sets.
+    pub async fn find_all(con: &mut ConnectionManager) -> RsrqResult<Vec<Queue>> {
+        let mut out = Vec::new();
+        {
+            let mut keys: redis::AsyncIter<String> = con.scan_match("rsrq:*").await.map_err(RsrqError::RedisOpError)?;
+            while let Some(key) = keys.next_item().await {
+                if let Ok(queue) = Queue::from_key(&key) {
+                    out.push(queue);
+                }
+            }
+        }
+        for queue in &out {
+            queue.migrate(con).await?;
+        }
+        Ok(out)
+    }
+
+    /// Convert a queued list (created before jobs had priorities) into a sorted set, other queues are unchanged.
+    pub async fn migrate(&self, con: &mut ConnectionManager) -> RsrqResult<()> {
+        if let QueueType::Queued = self.q_type {
+            let _: () = SCRIPT_MIGRATE_QUEUED
+                .key(&self.key)
+                .arg(JobKey::Priority)
+                .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        }
+        Ok(())
+    }
+
     pub async fn length(&self, con: &mut ConnectionManager) -> RsrqResult<usize> {
         match self.q_type {
             QueueType::Queued => {
-                con.llen(&self.key).await.map_err(RsrqError::RedisOpError)
+                con.zcard(&self.key).await.map_err(RsrqError::RedisOpError)
             }
             QueueType::Running => {
                 con.llen(&self.key).await.map_err(RsrqError::RedisOpError)
@@ -53,13 +212,35 @@ impl Queue {
             QueueType::Failed => {
                 con.scard(&self.key).await.map_err(RsrqError::RedisOpError)
             }
+            QueueType::Delayed => {
+                con.zcard(&self.key).await.map_err(RsrqError::RedisOpError)
+            }
+            QueueType::Deferred => {
+                con.scard(&self.key).await.map_err(RsrqError::RedisOpError)
+            }
+            QueueType::Scheduled => {
+                con.zcard(&self.key).await.map_err(RsrqError::RedisOpError)
+            }
         }
     }
 
+    /// Adds a command to the pipeline that returns the ids of all jobs in the queue.
+    pub fn pipe_job_ids(&self, pipe: &mut redis::Pipeline) {
+        match self.q_type {
+            QueueType::Queued => pipe.zrange(&self.key, 0, -1),
+            QueueType::Running => pipe.lrange(&self.key, 0, -1),
+            QueueType::Finished => pipe.smembers(&self.key),
+            QueueType::Failed => pipe.smembers(&self.key),
+            QueueType::Delayed => pipe.zrange(&self.key, 0, -1),
+            QueueType::Deferred => pipe.smembers(&self.key),
+            QueueType::Scheduled => pipe.zrange(&self.key, 0, -1),
+        };
+    }
+
     pub fn pipe_remove_job_id(&self, job_id: usize, pipe: &mut redis::Pipeline) {
         match self.q_type {
             QueueType::Queued => {
-                pipe.lrem(&self.key, 1, job_id);
+                pipe.zrem(&self.key, job_id);
             }
             QueueType::Running => {
                 pipe.lrem(&self.key, 1, job_id);
@@ -70,13 +251,25 @@ impl Queue {
             QueueType::Failed => {
                 pipe.srem(&self.key, job_id);
             }
+            QueueType::Delayed => {
+                pipe.zrem(&self.key, job_id);
+            }
+            QueueType::Deferred => {
+                pipe.srem(&self.key, job_id);
+            }
+            QueueType::Scheduled => {
+                pipe.zrem(&self.key, job_id);
+            }
         }
     }
 
     pub fn pipe_add_job_id(&self, job_id: usize, pipe: &mut redis::Pipeline) {
         match self.q_type {
             QueueType::Queued => {
-                pipe.lpush(&self.key, job_id);
+                // The job must already exist as its priority is read from Redis
+                pipe.cmd("EVAL").arg(SCRIPT_ENQUEUE_SRC.as_str()).arg(2)
+                    .arg(&self.key).arg(format!("{}:{}", JOB_KEY, job_id))
+                    .arg(job_id).arg(JobKey::Priority);
             }
             QueueType::Running => {
                 pipe.lpush(&self.key, job_id);
@@ -87,9 +280,100 @@ impl Queue {
             QueueType::Failed => {
                 pipe.sadd(&self.key, job_id);
             }
+            QueueType::Delayed | QueueType::Scheduled => {
+                // Without a timestamp the job is immediately available
+                pipe.zadd(&self.key, job_id, 0);
+            }
+            QueueType::Deferred => {
+                pipe.sadd(&self.key, job_id);
+            }
+        }
+    }
+
+    /// Adds a job to a delayed or scheduled queue that will become available at the specified timestamp.
+    pub fn pipe_add_job_id_at(&self, job_id: usize, timestamp: u64, pipe: &mut redis::Pipeline) {
+        match self.q_type {
+            QueueType::Delayed | QueueType::Scheduled => {
+                pipe.zadd(&self.key, job_id, timestamp);
+            }
+            _ => self.pipe_add_job_id(job_id, pipe),
         }
     }
 
+    /// Moves all jobs in a delayed or scheduled queue that are now due into the queued set, returning the number moved.
+    pub async fn promote_due(&self, con: &mut ConnectionManager) -> RsrqResult<usize> {
+        match self.q_type {
+            QueueType::Delayed | QueueType::Scheduled => {}
+            _ => {
+                return Err(RsrqError::ParserError(format!("Cannot promote jobs from queue type: {}", self.q_type)));
+            }
+        };
+        let q_target = Queue::new(QueueType::Queued, &self.name);
+        let n_promoted: usize = SCRIPT_PROMOTE_DUE
+            .key(&self.key)
+            .key(&q_target.key)
+            .arg(get_timestamp_s()?)
+            .arg(JOB_KEY)
+            .arg(JobKey::Status)
+            .arg(JobStatus::Queued)
+            .arg(JobKey::Priority)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(n_promoted)
+    }
+
+    /// Moves a job that is waiting on its dependencies into the queued set, returns false if it was already moved.
+    pub async fn promote_job(&self, job_id: usize, con: &mut ConnectionManager) -> RsrqResult<bool> {
+        match self.q_type {
+            QueueType::Deferred => {}
+            _ => {
+                return Err(RsrqError::ParserError(format!("Cannot promote a job from queue type: {}", self.q_type)));
+            }
+        };
+        let q_target = Queue::new(QueueType::Queued, &self.name);
+        let promoted: usize = SCRIPT_PROMOTE_JOB
+            .key(&self.key)
+            .key(&q_target.key)
+            .arg(job_id)
+            .arg(JOB_KEY)
+            .arg(JobKey::Status)
+            .arg(JobStatus::Queued)
+            .arg(JobKey::Priority)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(promoted == 1)
+    }
+
+    /// Moves a failed (or cancelled) job back into the queued set, or into the deferred set if it
+    /// has dependencies. The job keeps its id but its results are cleared, returns false if it was not in this queue.
+    pub async fn requeue_job(&self, job_id: usize, deferred: bool, con: &mut ConnectionManager) -> RsrqResult<bool> {
+        match self.q_type {
+            QueueType::Failed => {}
+            _ => {
+                return Err(RsrqError::ParserError(format!("Cannot requeue a job from queue type: {}", self.q_type)));
+            }
+        };
+        let (status, q_target) = if deferred {
+            (JobStatus::Deferred, Queue::new(QueueType::Deferred, &self.name))
+        } else {
+            (JobStatus::Queued, Queue::new(QueueType::Queued, &self.name))
+        };
+        let result_keys = [
+            JobKey::Started, JobKey::Finished, JobKey::Stdout, JobKey::Stderr, JobKey::ExitCode,
+            JobKey::DurationMs, JobKey::ProcessId, JobKey::Attempt, JobKey::Attempts,
+            JobKey::StdoutPath, JobKey::StderrPath,
+        ];
+        let requeued: usize = SCRIPT_REQUEUE_JOB
+            .key(&self.key)
+            .key(&q_target.key)
+            .key(format!("{}:{}", JOB_KEY, job_id))
+            .arg(job_id)
+            .arg(status)
+            .arg(JobKey::Status)
+            .arg(JobKey::Priority)
+            .arg(&result_keys)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(requeued == 1)
+    }
+
     pub async fn get_next_job_id(&self, con: &mut ConnectionManager) -> RsrqResult<Option<usize>> {
         match self.q_type {
             QueueType::Queued => {}
@@ -98,10 +382,37 @@ impl Queue {
             }
         };
         let q_target = Queue::new(QueueType::Running, &self.name);
-        let job_id: Option<usize> = con.lmove(&self.key, &q_target.key, redis::Direction::Right, redis::Direction::Left).await.map_err(RsrqError::RedisOpError)?;
+        let job_id: Option<usize> = SCRIPT_CLAIM_JOB
+            .key(&self.key)
+            .key(&q_target.key)
+            .arg(JobKey::Priority)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
         Ok(job_id)
     }
 
+    /// Claims the job with the highest priority if its resources are within those that are free (None is unlimited).
+    /// Returns the job id, its resources, and whether it was claimed, or None if the queue is empty.
+    pub async fn claim_next_job_if_fits(&self, free_cpus: Option<u32>, free_mem_mb: Option<u64>, con: &mut ConnectionManager) -> RsrqResult<Option<(usize, JobResources, bool)>> {
+        match self.q_type {
+            QueueType::Queued => {}
+            _ => {
+                return Err(RsrqError::ParserError(format!("Cannot get next job from queue type: {}", self.q_type)));
+            }
+        };
+        let q_target = Queue::new(QueueType::Running, &self.name);
+        let result: Option<(u8, usize, u32, u64)> = SCRIPT_CLAIM_JOB_IF_FITS
+            .key(&self.key)
+            .key(&q_target.key)
+            .arg(JOB_KEY)
+            .arg(JobKey::Cpus)
+            .arg(JobKey::MemMb)
+            .arg(free_cpus.map_or(-1, |x| x as i64))
+            .arg(free_mem_mb.map_or(-1, |x| x as i64))
+            .arg(JobKey::Priority)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(result.map(|(claimed, job_id, cpus, mem_mb)| (job_id, JobResources { cpus, mem_mb }, claimed == 1)))
+    }
+
     pub async fn get_n_next_job_ids(&self, n: usize, con: &mut ConnectionManager) -> RsrqResult<Vec<usize>> {
         let mut futures = vec![];
         for _ in 0..n {
@@ -117,15 +428,10 @@ impl Queue {
         // Await for the jobs to complete
         let mut out = vec![];
         for future in futures {
-            if let Ok(cur_future) = future.await {
-                if let Ok(result) = cur_future {
-                    if let Some(job_id) = result {
-                        out.push(job_id);
-                    }
-                }
+            if let Ok(Ok(Some(job_id))) = future.await {
+                out.push(job_id);
             }
         }
         Ok(out)
     }
 }
-
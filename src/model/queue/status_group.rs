@@ -0,0 +1,45 @@
+use std::fmt;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// How the job counts are grouped in the status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusGroup {
+    /// By the queue of the job.
+    #[default]
+    Queue,
+    /// By the Snakemake rule of the job (jobs without a rule are not counted).
+    Rule,
+    /// By the array of the job (jobs that are not part of an array are not counted).
+    Array,
+}
+
+impl StatusGroup {
+    pub fn from_string(value: &str) -> RsrqResult<StatusGroup> {
+        match value {
+            "queue" => Ok(StatusGroup::Queue),
+            "rule" => Ok(StatusGroup::Rule),
+            "array" => Ok(StatusGroup::Array),
+            _ => Err(RsrqError::ParserError(format!("Invalid status group: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for StatusGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StatusGroup::Queue => write!(f, "queue"),
+            StatusGroup::Rule => write!(f, "rule"),
+            StatusGroup::Array => write!(f, "array"),
+        }
+    }
+}
+
+impl std::str::FromStr for StatusGroup {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StatusGroup::from_string(s)
+    }
+}
@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use chrono::{Local, TimeZone};
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// Day of week names, indexed by the crontab number (0 and 7 are both Sunday).
+const DAY_NAMES: [&str; 8] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Parse a cron expression. Five field expressions (minute, hour, day of month, month, day of week) follow crontab,
+/// six or seven field expressions start with seconds (and may end with years), shortcuts such as "@daily" are accepted.
+pub fn parse_cron(expr: &str) -> RsrqResult<cron::Schedule> {
+    let expr = expr.trim();
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let expanded = if fields.len() == 5 {
+        format!("0 {} {} {} {} {}", fields[0], fields[1], fields[2], fields[3], crontab_day_of_week(fields[4])?)
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&expanded).map_err(|e| RsrqError::ParserError(format!("Invalid cron expression \"{}\": {}", expr, e)))
+}
+
+/// The cron crate numbers the days of the week from 1 (Sunday), crontab numbers are replaced by names to keep their meaning.
+fn crontab_day_of_week(field: &str) -> RsrqResult<String> {
+    let day_name = |value: &str| -> RsrqResult<String> {
+        match value.parse::<usize>() {
+            Ok(day) if day < DAY_NAMES.len() => Ok(DAY_NAMES[day].to_string()),
+            Ok(_) => Err(RsrqError::ParserError(format!("Invalid day of week: {}", value))),
+            Err(_) => Ok(value.to_string()),
+        }
+    };
+
+    let mut items: Vec<String> = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (item, None),
+        };
+        let range = match (range.split_once('-'), step) {
+            // Ranges ending on Sunday (e.g. 5-7) would otherwise wrap around the end of the week
+            (Some((start, "7")), None) if start != "0" => format!("{}-Sat,Sun", day_name(start)?),
+            (Some((start, end)), _) => format!("{}-{}", day_name(start)?, day_name(end)?),
+            (None, _) => day_name(range)?,
+        };
+        match step {
+            Some(step) => items.push(format!("{}/{}", range, step)),
+            None => items.push(range),
+        }
+    }
+    Ok(items.join(","))
+}
+
+/// The first occurrence of the schedule after the UNIX timestamp (in seconds).
+pub fn next_occurrence(schedule: &cron::Schedule, after: u64) -> Option<u64> {
+    let after = Local.timestamp_opt(after as i64, 0).single()?;
+    schedule.after(&after).next().map(|x| x.timestamp() as u64)
+}
+
+/// The latest occurrence of the schedule after the UNIX timestamp `after`, that is not after `now`.
+pub fn last_occurrence(schedule: &cron::Schedule, after: u64, now: u64) -> Option<u64> {
+    let after = Local.timestamp_opt(after as i64, 0).single()?;
+    schedule.after(&after)
+        .map(|x| x.timestamp() as u64)
+        .take_while(|x| *x <= now)
+        .last()
+}
+
+#[test]
+fn test_crontab_day_of_week() {
+    assert_eq!(crontab_day_of_week("*").unwrap(), "*");
+    assert_eq!(crontab_day_of_week("0").unwrap(), "Sun");
+    assert_eq!(crontab_day_of_week("1-5").unwrap(), "Mon-Fri");
+    assert_eq!(crontab_day_of_week("5-7").unwrap(), "Fri-Sat,Sun");
+    assert_eq!(crontab_day_of_week("0,6").unwrap(), "Sun,Sat");
+    assert_eq!(crontab_day_of_week("1-5/2").unwrap(), "Mon-Fri/2");
+    assert_eq!(crontab_day_of_week("Mon-Fri").unwrap(), "Mon-Fri");
+    assert!(crontab_day_of_week("8").is_err());
+}
+
+#[test]
+fn test_parse_cron_occurrences() {
+    let ts = |d: u32, h: u32, m: u32| Local.with_ymd_and_hms(2024, 1, d, h, m, 0).unwrap().timestamp() as u64;
+
+    // 2024-01-06 is a Saturday, weekday schedules next run on Monday
+    let weekdays = parse_cron("0 2 * * 1-5").unwrap();
+    assert_eq!(next_occurrence(&weekdays, ts(6, 12, 0)), Some(ts(8, 2, 0)));
+
+    let quarterly = parse_cron("*/15 * * * *").unwrap();
+    assert_eq!(next_occurrence(&quarterly, ts(8, 10, 0)), Some(ts(8, 10, 15)));
+    assert_eq!(last_occurrence(&quarterly, ts(8, 10, 0), ts(8, 10, 50)), Some(ts(8, 10, 45)));
+    assert_eq!(last_occurrence(&quarterly, ts(8, 10, 0), ts(8, 10, 14)), None);
+
+    let daily = parse_cron("@daily").unwrap();
+    assert_eq!(next_occurrence(&daily, ts(8, 10, 0)), Some(ts(9, 0, 0)));
+
+    let with_seconds = parse_cron("30 0 12 * * *").unwrap();
+    assert_eq!(next_occurrence(&with_seconds, ts(8, 10, 0)), Some(ts(8, 12, 0) + 30));
+
+    assert!(parse_cron("not a schedule").is_err());
+    assert!(parse_cron("61 * * * *").is_err());
+}
@@ -0,0 +1,87 @@
+use std::fmt;
+
+use redis::{ErrorKind, FromRedisValue, RedisResult, RedisWrite, ToRedisArgs, Value};
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+#[derive(Debug)]
+pub enum ScheduleKey {
+    Id,
+    Queue,
+    Cron,
+    Cmd,
+    Created,
+    LastRun,
+    LastJobId,
+    Timeout,
+    MaxRetries,
+    RetryDelay,
+    RetryBackoff,
+    Mode,
+    Priority,
+    Cpus,
+    MemMb,
+}
+
+impl ScheduleKey {
+    pub fn from_string(value: &str) -> RsrqResult<ScheduleKey> {
+        match value {
+            "id" => Ok(ScheduleKey::Id),
+            "queue" => Ok(ScheduleKey::Queue),
+            "cron" => Ok(ScheduleKey::Cron),
+            "cmd" => Ok(ScheduleKey::Cmd),
+            "created" => Ok(ScheduleKey::Created),
+            "last_run" => Ok(ScheduleKey::LastRun),
+            "last_job_id" => Ok(ScheduleKey::LastJobId),
+            "timeout" => Ok(ScheduleKey::Timeout),
+            "max_retries" => Ok(ScheduleKey::MaxRetries),
+            "retry_delay" => Ok(ScheduleKey::RetryDelay),
+            "retry_backoff" => Ok(ScheduleKey::RetryBackoff),
+            "mode" => Ok(ScheduleKey::Mode),
+            "priority" => Ok(ScheduleKey::Priority),
+            "cpus" => Ok(ScheduleKey::Cpus),
+            "mem_mb" => Ok(ScheduleKey::MemMb),
+            _ => Err(RsrqError::ParserError(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ScheduleKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleKey::Id => write!(f, "id"),
+            ScheduleKey::Queue => write!(f, "queue"),
+            ScheduleKey::Cron => write!(f, "cron"),
+            ScheduleKey::Cmd => write!(f, "cmd"),
+            ScheduleKey::Created => write!(f, "created"),
+            ScheduleKey::LastRun => write!(f, "last_run"),
+            ScheduleKey::LastJobId => write!(f, "last_job_id"),
+            ScheduleKey::Timeout => write!(f, "timeout"),
+            ScheduleKey::MaxRetries => write!(f, "max_retries"),
+            ScheduleKey::RetryDelay => write!(f, "retry_delay"),
+            ScheduleKey::RetryBackoff => write!(f, "retry_backoff"),
+            ScheduleKey::Mode => write!(f, "mode"),
+            ScheduleKey::Priority => write!(f, "priority"),
+            ScheduleKey::Cpus => write!(f, "cpus"),
+            ScheduleKey::MemMb => write!(f, "mem_mb"),
+        }
+    }
+}
+
+impl ToRedisArgs for ScheduleKey {
+    fn write_redis_args<W>(&self, out: &mut W) where W: ?Sized + RedisWrite {
+        ToRedisArgs::write_redis_args(&self.to_string(), out);
+    }
+}
+
+impl FromRedisValue for ScheduleKey {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        let string_value: String = FromRedisValue::from_redis_value(v)?;
+        let res = ScheduleKey::from_string(&string_value);
+        match res {
+            Ok(schedule_key) => Ok(schedule_key),
+            Err(_) => Err((ErrorKind::TypeError, "Unable to convert value.").into())
+        }
+    }
+}
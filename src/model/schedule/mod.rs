@@ -0,0 +1,3 @@
+pub mod cron_expr;
+pub mod key;
+pub mod rsrq_schedule;
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
+use log::warn;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, RedisResult};
+use serde_json::{json, Value};
+
+use crate::config::{SCHEDULES_KEY, SCHEDULE_KEY, SCHEDULE_LOCK_KEY, UID_KEY_SCHEDULE};
+use crate::model::error::RsrqError;
+use crate::model::job::options::JobOptions;
+use crate::model::job::rsrq_job::Job;
+use crate::model::schedule::cron_expr::{last_occurrence, next_occurrence, parse_cron};
+use crate::model::schedule::key::ScheduleKey;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::{btree_get, btree_get_opt};
+use crate::util::redis::get_next_uid;
+use crate::util::time::get_timestamp_s;
+
+/// Occurrences that were missed (e.g. no workers were running) are only caught up on within this many seconds,
+/// and only the most recent of those is enqueued.
+const MAX_CATCH_UP_SECS: u64 = 60 * 60 * 24;
+
+/// How long the lock on each occurrence is held, this must be longer than the catch-up window.
+const OCCURRENCE_LOCK_SECS: u64 = 2 * MAX_CATCH_UP_SECS;
+
+lazy_static! {
+    /// Records the last occurrence that was enqueued, unless the schedule has since been removed.
+    static ref SCRIPT_SET_LAST_RUN: redis::Script = redis::Script::new(r"
+        if redis.call('EXISTS', KEYS[1]) == 0 then
+            return 0
+        end
+        redis.call('HSET', KEYS[1], ARGV[1], ARGV[2], ARGV[3], ARGV[4])
+        return 1
+    ");
+}
+
+/// A recurring schedule that enqueues a command each time the cron expression is due.
+#[derive(Debug)]
+pub struct Schedule {
+    pub key: String,
+
+    pub id: usize,
+    pub queue: String,
+    pub cron: String,
+    pub cmd: String,
+    pub created: u64,
+    pub last_run: Option<u64>,
+    pub last_job_id: Option<usize>,
+
+    /// Applied to each job that is enqueued (dependencies and run at are not used).
+    pub options: JobOptions,
+}
+
+impl Schedule {
+    pub fn get_key(id: usize) -> String {
+        format!("{}:{}", SCHEDULE_KEY, id)
+    }
+
+    pub async fn new(queue: &str, cron: &str, cmd: &str, options: &JobOptions, con: &mut ConnectionManager) -> RsrqResult<Schedule> {
+        // Validate the expression before anything is written
+        parse_cron(cron)?;
+
+        let id = get_next_uid(UID_KEY_SCHEDULE, con).await?;
+        let schedule = Schedule {
+            key: Schedule::get_key(id),
+            id,
+            queue: queue.to_string(),
+            cron: cron.trim().to_string(),
+            cmd: cmd.to_string(),
+            created: get_timestamp_s()?,
+            last_run: None,
+            last_job_id: None,
+            options: JobOptions {
+                depends_on: Vec::new(),
+                run_at: None,
+                ..options.clone()
+            },
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.hset_multiple(&schedule.key, &schedule.to_array());
+        pipe.sadd(SCHEDULES_KEY, id);
+        let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+
+        Ok(schedule)
+    }
+
+    pub fn to_array(&self) -> [(ScheduleKey, String); 15] {
+        let opt_to_string = |x: Option<String>| x.unwrap_or("".to_string());
+        [
+            (ScheduleKey::Id, self.id.to_string()),
+            (ScheduleKey::Queue, self.queue.clone()),
+            (ScheduleKey::Cron, self.cron.clone()),
+            (ScheduleKey::Cmd, self.cmd.clone()),
+            (ScheduleKey::Created, self.created.to_string()),
+            (ScheduleKey::LastRun, opt_to_string(self.last_run.map(|x| x.to_string()))),
+            (ScheduleKey::LastJobId, opt_to_string(self.last_job_id.map(|x| x.to_string()))),
+            (ScheduleKey::Timeout, opt_to_string(self.options.timeout.map(|x| x.to_string()))),
+            (ScheduleKey::MaxRetries, self.options.max_retries.to_string()),
+            (ScheduleKey::RetryDelay, self.options.retry_delay.to_string()),
+            (ScheduleKey::RetryBackoff, self.options.retry_backoff.to_string()),
+            (ScheduleKey::Mode, opt_to_string(self.options.mode.map(|x| x.to_string()))),
+            (ScheduleKey::Priority, self.options.priority.to_string()),
+            (ScheduleKey::Cpus, opt_to_string(self.options.cpus.map(|x| x.to_string()))),
+            (ScheduleKey::MemMb, opt_to_string(self.options.mem_mb.map(|x| x.to_string()))),
+        ]
+    }
+
+    /// Load a schedule from Redis, returns None if it does not exist.
+    pub async fn load(id: usize, con: &mut ConnectionManager) -> RsrqResult<Option<Schedule>> {
+        let key = Schedule::get_key(id);
+        let map: BTreeMap<String, String> = con.hgetall(&key).await.map_err(RsrqError::RedisOpError)?;
+        if map.is_empty() {
+            return Ok(None);
+        }
+        let schedule = Schedule {
+            key,
+            id: btree_get(&map, ScheduleKey::Id)?,
+            queue: btree_get(&map, ScheduleKey::Queue)?,
+            cron: btree_get(&map, ScheduleKey::Cron)?,
+            cmd: btree_get(&map, ScheduleKey::Cmd)?,
+            created: btree_get(&map, ScheduleKey::Created)?,
+            last_run: btree_get_opt(&map, ScheduleKey::LastRun)?,
+            last_job_id: btree_get_opt(&map, ScheduleKey::LastJobId)?,
+            options: JobOptions {
+                timeout: btree_get_opt(&map, ScheduleKey::Timeout)?,
+                max_retries: btree_get_opt(&map, ScheduleKey::MaxRetries)?.unwrap_or(0),
+                retry_delay: btree_get_opt(&map, ScheduleKey::RetryDelay)?.unwrap_or(0),
+                retry_backoff: btree_get_opt(&map, ScheduleKey::RetryBackoff)?.unwrap_or_default(),
+                mode: btree_get_opt(&map, ScheduleKey::Mode)?,
+                priority: btree_get_opt(&map, ScheduleKey::Priority)?.unwrap_or(0),
+                cpus: btree_get_opt(&map, ScheduleKey::Cpus)?,
+                mem_mb: btree_get_opt(&map, ScheduleKey::MemMb)?,
+                ..JobOptions::default()
+            },
+        };
+        Ok(Some(schedule))
+    }
+
+    /// Load all schedules, sorted by id.
+    pub async fn load_all(con: &mut ConnectionManager) -> RsrqResult<Vec<Schedule>> {
+        let mut ids: Vec<usize> = con.smembers(SCHEDULES_KEY).await.map_err(RsrqError::RedisOpError)?;
+        ids.sort();
+
+        let mut out = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(schedule) = Schedule::load(id, con).await? {
+                out.push(schedule);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Remove a schedule, returns false if it does not exist. Jobs that were already enqueued are not affected.
+    pub async fn remove(id: usize, con: &mut ConnectionManager) -> RsrqResult<bool> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.del(Schedule::get_key(id));
+        pipe.srem(SCHEDULES_KEY, id);
+        let (_, removed): (usize, usize) = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(removed > 0)
+    }
+
+    /// The next time the schedule is due after the UNIX timestamp (in seconds).
+    pub fn next_run(&self, now: u64) -> Option<u64> {
+        let schedule = parse_cron(&self.cron).ok()?;
+        next_occurrence(&schedule, now)
+    }
+
+    /// Enqueue a job if an occurrence of the schedule has passed since it last ran. A lock is taken on each
+    /// occurrence so that only one worker enqueues it.
+    pub async fn enqueue_if_due(&mut self, now: u64, con: &mut ConnectionManager) -> RsrqResult<Option<Job>> {
+        let schedule = parse_cron(&self.cron)?;
+        let after = self.last_run.unwrap_or(self.created).max(now.saturating_sub(MAX_CATCH_UP_SECS));
+        let due = match last_occurrence(&schedule, after, now) {
+            Some(due) => due,
+            None => return Ok(None),
+        };
+
+        let lock_key = format!("{}:{}:{}", SCHEDULE_LOCK_KEY, self.id, due);
+        let acquired: bool = redis::cmd("SET").arg(&lock_key).arg(1).arg("NX").arg("EX").arg(OCCURRENCE_LOCK_SECS)
+            .query_async::<_, Option<String>>(con).await.map_err(RsrqError::RedisOpError)?
+            .is_some();
+        if !acquired {
+            return Ok(None);
+        }
+
+        // Release the lock if the job could not be created, so that the occurrence is retried
+        let job = match Job::new(&self.queue, &self.cmd, &self.options, con).await {
+            Ok(job) => job,
+            Err(e) => {
+                let _: RedisResult<()> = con.del(&lock_key).await;
+                return Err(e);
+            }
+        };
+        let _: () = SCRIPT_SET_LAST_RUN
+            .key(&self.key)
+            .arg(ScheduleKey::LastRun)
+            .arg(due)
+            .arg(ScheduleKey::LastJobId)
+            .arg(job.id)
+            .invoke_async(con).await.map_err(RsrqError::RedisOpError)?;
+        self.last_run = Some(due);
+        self.last_job_id = Some(job.id);
+        Ok(Some(job))
+    }
+
+    /// The columns of the TSV output, in the order of the JSON fields.
+    pub const TSV_COLUMNS: &'static [&'static str] = &["id", "queue", "cron", "cmd", "created", "last_run", "last_job_id",
+        "next_run", "timeout", "max_retries", "priority", "cpus", "mem_mb", "mode"];
+
+    /// The attributes of the schedule as JSON, including the next time it is due.
+    pub fn to_json(&self, now: u64) -> Value {
+        json!({
+            "id": self.id,
+            "queue": self.queue,
+            "cron": self.cron,
+            "cmd": self.cmd,
+            "created": self.created,
+            "last_run": self.last_run,
+            "last_job_id": self.last_job_id,
+            "next_run": self.next_run(now),
+            "timeout": self.options.timeout,
+            "max_retries": self.options.max_retries,
+            "priority": self.options.priority,
+            "cpus": self.options.cpus,
+            "mem_mb": self.options.mem_mb,
+            "mode": self.options.mode.map(|x| x.to_string()),
+        })
+    }
+}
+
+/// Enqueue a job for every schedule with an occurrence that is due, returning the jobs that were enqueued.
+/// Schedules that cannot be loaded or enqueued (e.g. an invalid cron expression) are logged and skipped.
+pub async fn enqueue_due_schedules(con: &mut ConnectionManager) -> RsrqResult<Vec<Job>> {
+    let now = get_timestamp_s()?;
+    let mut ids: Vec<usize> = con.smembers(SCHEDULES_KEY).await.map_err(RsrqError::RedisOpError)?;
+    ids.sort();
+
+    let mut jobs = Vec::new();
+    for id in ids {
+        let result = match Schedule::load(id, con).await {
+            Ok(Some(mut schedule)) => schedule.enqueue_if_due(now, con).await,
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(Some(job)) => jobs.push(job),
+            Ok(None) => {}
+            Err(e) => warn!("Skipping schedule {}: {}", id, e),
+        }
+    }
+    Ok(jobs)
+}
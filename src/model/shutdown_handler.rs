@@ -1,4 +1,5 @@
 use log::error;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
@@ -11,7 +12,7 @@ pub struct ShutdownHandler<'a> {
 }
 
 impl<'a> ShutdownHandler<'a> {
-    pub fn new(tx: &mpsc::Sender<WorkerMessage>) -> ShutdownHandler {
+    pub fn new(tx: &mpsc::Sender<WorkerMessage>) -> ShutdownHandler<'_> {
         ShutdownHandler {
             tx,
             thread_sigint: None,
@@ -20,15 +21,32 @@ impl<'a> ShutdownHandler<'a> {
     }
 
 
+    /// Send a message each time SIGINT, SIGTERM, or SIGHUP is received.
     pub fn start(&mut self) {
         let tx = self.tx.clone();
         let thread = tokio::spawn(async move {
-            match tokio::signal::ctrl_c().await {
-                Ok(()) => {
-                    tx.send(WorkerMessage::exit_sigint()).await.unwrap();
+            let signals = (signal(SignalKind::terminate()), signal(SignalKind::hangup()));
+            let (mut sigterm, mut sighup) = match signals {
+                (Ok(sigterm), Ok(sighup)) => (sigterm, sighup),
+                _ => {
+                    error!("Unable to create SIGTERM and SIGHUP handlers.");
+                    return;
                 }
-                Err(_) => {
-                    error!("Unable to create SIGINT handler.");
+            };
+            loop {
+                let message = tokio::select! {
+                    res = tokio::signal::ctrl_c() => match res {
+                        Ok(()) => WorkerMessage::exit_sigint(),
+                        Err(_) => {
+                            error!("Unable to create SIGINT handler.");
+                            return;
+                        }
+                    },
+                    _ = sigterm.recv() => WorkerMessage::exit_sigterm(),
+                    _ = sighup.recv() => WorkerMessage::exit_sighup(),
+                };
+                if tx.send(message).await.is_err() {
+                    return;
                 }
             }
         });
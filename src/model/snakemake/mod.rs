@@ -0,0 +1,3 @@
+pub mod properties;
+pub mod version;
+pub mod status_cache;
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::model::job::options::JobOptions;
+use crate::model::types::RsrqResult;
+use crate::util::parsing::parse_memory_mb;
+use crate::util::time::parse_duration;
+
+/// The metadata key of the Snakemake rule (or group) that the job runs.
+pub const META_RULE: &str = "rule";
+
+/// The metadata key of the wildcards of the job (e.g. "sample=A,lane=1").
+pub const META_WILDCARDS: &str = "wildcards";
+
+/// The metadata key of the Snakemake job id.
+pub const META_SNAKEMAKE_JOBID: &str = "snakemake_jobid";
+
+/// The attributes of a Snakemake job, read from the "# properties =" line of its job script.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SnakemakeProperties {
+    pub queue: Option<String>,
+    pub rule: Option<String>,
+    pub wildcards: BTreeMap<String, String>,
+    pub jobid: Option<String>,
+    pub threads: Option<u32>,
+    pub mem_mb: Option<u64>,
+    /// The runtime resource, in seconds.
+    pub runtime_secs: Option<u64>,
+}
+
+/// Snakemake values can be numbers or strings (e.g. "16G", or "2h" for the runtime).
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+impl SnakemakeProperties {
+    pub fn from_json(properties: &Value) -> RsrqResult<SnakemakeProperties> {
+        let resources = &properties["resources"];
+
+        // Group jobs don't have a rule, they are identified by their group id
+        let rule = match (&properties["rule"], &properties["groupid"]) {
+            (Value::String(rule), _) => Some(rule.clone()),
+            (_, Value::String(group)) => Some(format!("group:{}", group)),
+            _ => None,
+        };
+        let wildcards = properties["wildcards"].as_object()
+            .map(|x| x.iter().filter_map(|(k, v)| value_to_string(v).map(|v| (k.clone(), v))).collect())
+            .unwrap_or_default();
+        let threads = properties["threads"].as_u64().and_then(|x| u32::try_from(x).ok()).filter(|x| *x > 0);
+
+        // The memory is in MB unless a unit is given, the runtime is in minutes unless a unit is given
+        let mem_mb = match &resources["mem_mb"] {
+            Value::Number(n) => n.as_f64().map(|x| x.ceil() as u64),
+            Value::String(s) => Some(parse_memory_mb(s)?),
+            _ => None,
+        };
+        let runtime_secs = match &resources["runtime"] {
+            Value::Number(n) => n.as_f64().map(|x| (x * 60.0).ceil() as u64),
+            Value::String(s) => match s.parse::<f64>() {
+                Ok(mins) => Some((mins * 60.0).ceil() as u64),
+                Err(_) => Some(parse_duration(s)?),
+            },
+            _ => None,
+        };
+
+        Ok(SnakemakeProperties {
+            queue: resources["queue"].as_str().map(|x| x.to_string()),
+            rule,
+            wildcards,
+            jobid: value_to_string(&properties["jobid"]),
+            threads,
+            mem_mb: mem_mb.filter(|x| *x > 0),
+            runtime_secs: runtime_secs.filter(|x| *x > 0),
+        })
+    }
+
+    /// The wildcards as a comma separated list (e.g. "sample=A,lane=1").
+    pub fn format_wildcards(&self) -> String {
+        self.wildcards.iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// The job options, the threads and memory are used for capacity scheduling, and the runtime as the timeout.
+    pub fn to_options(&self) -> JobOptions {
+        let mut metadata = BTreeMap::new();
+        if let Some(rule) = &self.rule {
+            metadata.insert(META_RULE.to_string(), rule.clone());
+        }
+        if !self.wildcards.is_empty() {
+            metadata.insert(META_WILDCARDS.to_string(), self.format_wildcards());
+        }
+        if let Some(jobid) = &self.jobid {
+            metadata.insert(META_SNAKEMAKE_JOBID.to_string(), jobid.clone());
+        }
+        JobOptions {
+            timeout: self.runtime_secs,
+            cpus: self.threads,
+            mem_mb: self.mem_mb,
+            name: self.rule.clone(),
+            metadata,
+            ..JobOptions::default()
+        }
+    }
+}
+
+#[test]
+fn test_snakemake_properties() {
+    let json = serde_json::json!({
+        "type": "single", "rule": "align", "local": false, "threads": 8, "jobid": 3,
+        "wildcards": {"sample": "A", "lane": 1},
+        "resources": {"mem_mb": 16000, "disk_mb": 1000, "runtime": 90, "queue": "high", "tmpdir": "/tmp"},
+    });
+    let properties = SnakemakeProperties::from_json(&json).unwrap();
+    assert_eq!(properties.queue.as_deref(), Some("high"));
+    assert_eq!(properties.rule.as_deref(), Some("align"));
+    assert_eq!(properties.format_wildcards(), "lane=1,sample=A");
+    assert_eq!(properties.jobid.as_deref(), Some("3"));
+    assert_eq!(properties.threads, Some(8));
+    assert_eq!(properties.mem_mb, Some(16000));
+    assert_eq!(properties.runtime_secs, Some(5400));
+
+    let options = properties.to_options();
+    assert_eq!(options.cpus, Some(8));
+    assert_eq!(options.timeout, Some(5400));
+    assert_eq!(options.metadata.get(META_RULE).unwrap(), "align");
+    assert_eq!(options.metadata.get(META_WILDCARDS).unwrap(), "lane=1,sample=A");
+
+    // Group jobs, and resources given with units
+    let json = serde_json::json!({
+        "type": "group", "groupid": "g1", "jobid": "0b1c", "threads": 1,
+        "resources": {"mem_mb": "2G", "runtime": "1h"},
+    });
+    let properties = SnakemakeProperties::from_json(&json).unwrap();
+    assert_eq!(properties.rule.as_deref(), Some("group:g1"));
+    assert_eq!(properties.queue, None);
+    assert_eq!(properties.mem_mb, Some(2048));
+    assert_eq!(properties.runtime_secs, Some(3600));
+
+    // Nothing is required
+    let properties = SnakemakeProperties::from_json(&serde_json::json!({})).unwrap();
+    assert_eq!(properties, SnakemakeProperties::default());
+    assert!(properties.to_options().metadata.is_empty());
+}
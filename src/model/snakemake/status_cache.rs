@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use crate::model::job::status::JobStatus;
+
+/// The last known status of each job that Snakemake has checked, kept by the sidecar.
+#[derive(Debug, Default)]
+pub struct StatusCache {
+    statuses: HashMap<usize, JobStatus>,
+}
+
+impl StatusCache {
+    pub fn get(&self, job_id: usize) -> Option<JobStatus> {
+        self.statuses.get(&job_id).copied()
+    }
+
+    pub fn insert(&mut self, job_id: usize, status: JobStatus) {
+        self.statuses.insert(job_id, status);
+    }
+
+    /// The jobs whose status may still change (i.e. those that can be cancelled), sorted by id.
+    pub fn pending(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.statuses.iter()
+            .filter(|(_, status)| status.is_cancellable())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Replace the statuses of the jobs, those that no longer exist are forgotten.
+    pub fn update(&mut self, job_ids: &[usize], statuses: Vec<Option<JobStatus>>) {
+        for (job_id, status) in job_ids.iter().zip(statuses) {
+            match status {
+                Some(status) => self.insert(*job_id, status),
+                None => {
+                    self.statuses.remove(job_id);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_status_cache() {
+    let mut cache = StatusCache::default();
+    cache.insert(3, JobStatus::Running);
+    cache.insert(1, JobStatus::Queued);
+    cache.insert(2, JobStatus::Finished);
+    assert_eq!(cache.pending(), vec![1, 3]);
+
+    cache.update(&[1, 3], vec![Some(JobStatus::Running), None]);
+    assert_eq!(cache.get(1), Some(JobStatus::Running));
+    assert_eq!(cache.get(2), Some(JobStatus::Finished));
+    assert_eq!(cache.get(3), None);
+
+    cache.update(&[1], vec![Some(JobStatus::Failed)]);
+    assert!(cache.pending().is_empty());
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// The major version of Snakemake that a cluster profile is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnakemakeVersion {
+    /// The "cluster", "cluster-status", and "cluster-cancel" settings.
+    #[default]
+    V7,
+    /// The cluster-generic executor plugin (snakemake-executor-plugin-cluster-generic).
+    V8,
+}
+
+impl SnakemakeVersion {
+    pub fn from_string(value: &str) -> RsrqResult<SnakemakeVersion> {
+        match value {
+            "7" => Ok(SnakemakeVersion::V7),
+            "8" => Ok(SnakemakeVersion::V8),
+            _ => Err(RsrqError::ParserError(format!("Unsupported Snakemake version: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for SnakemakeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnakemakeVersion::V7 => write!(f, "7"),
+            SnakemakeVersion::V8 => write!(f, "8"),
+        }
+    }
+}
+
+impl std::str::FromStr for SnakemakeVersion {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SnakemakeVersion::from_string(s)
+    }
+}
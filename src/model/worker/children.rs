@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// The PIDs of the commands that are running, so that signals can be forwarded to them when the worker shuts down.
+/// Each command is the leader of its own process group, so its PID is also the id of the group.
+#[derive(Debug, Clone, Default)]
+pub struct ChildProcesses {
+    pids: Arc<Mutex<HashSet<u32>>>,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl ChildProcesses {
+    /// Mark the worker as shutting down, commands that exit after this may have been interrupted by the signal.
+    pub fn set_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    pub fn insert(&self, pid: u32) {
+        if let Ok(mut pids) = self.pids.lock() {
+            pids.insert(pid);
+        }
+    }
+
+    pub fn remove(&self, pid: u32) {
+        if let Ok(mut pids) = self.pids.lock() {
+            pids.remove(&pid);
+        }
+    }
+
+    /// Send the signal to the process group of all running commands (i.e. including the processes started by a shell),
+    /// returning the number of commands that were signalled.
+    pub fn signal_all(&self, signal: Signal) -> usize {
+        let pids: Vec<u32> = match self.pids.lock() {
+            Ok(pids) => pids.iter().copied().collect(),
+            Err(_) => return 0,
+        };
+        let mut n_signalled = 0;
+        for pid in pids {
+            match killpg(Pid::from_raw(pid as i32), signal) {
+                Ok(()) => n_signalled += 1,
+                Err(e) => warn!("Unable to send {} to process {}: {}", signal, pid, e),
+            }
+        }
+        n_signalled
+    }
+}
+
+/// Kills the process group of a command when dropped, unless it was disarmed once the command finished.
+/// This ensures that the processes started by a command are killed when its job is aborted (e.g. cancelled or stopped).
+pub struct ProcessGroupGuard<'a> {
+    pid: Option<u32>,
+    children: Option<&'a ChildProcesses>,
+}
+
+impl<'a> ProcessGroupGuard<'a> {
+    /// Record the PID in the child processes (if given) while the command is running.
+    pub fn new(pid: Option<u32>, children: Option<&'a ChildProcesses>) -> ProcessGroupGuard<'a> {
+        if let (Some(children), Some(pid)) = (children, pid) {
+            children.insert(pid);
+        }
+        ProcessGroupGuard { pid, children }
+    }
+
+    /// Kill the process group now, the guard is left armed.
+    pub fn kill(&self) {
+        if let Some(pid) = self.pid {
+            let _ = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL);
+        }
+    }
+
+    /// The command has finished, remove it from the child processes without killing its process group.
+    pub fn disarm(mut self) {
+        self.release();
+    }
+
+    fn release(&mut self) {
+        if let (Some(children), Some(pid)) = (self.children, self.pid.take()) {
+            children.remove(pid);
+        }
+    }
+}
+
+impl Drop for ProcessGroupGuard<'_> {
+    fn drop(&mut self) {
+        self.kill();
+        self.release();
+    }
+}
+
+/// Parse a signal name, with or without the "SIG" prefix (e.g. "SIGTERM", "term").
+pub fn parse_signal(value: &str) -> RsrqResult<Signal> {
+    let name = value.trim().to_uppercase();
+    let name = if name.starts_with("SIG") { name } else { format!("SIG{}", name) };
+    Signal::from_str(&name).map_err(|_| RsrqError::ParserError(format!("Invalid signal: {}", value)))
+}
+
+#[test]
+fn test_parse_signal() {
+    assert_eq!(parse_signal("SIGTERM").unwrap(), Signal::SIGTERM);
+    assert_eq!(parse_signal("int").unwrap(), Signal::SIGINT);
+    assert_eq!(parse_signal("SigHup").unwrap(), Signal::SIGHUP);
+    assert!(parse_signal("SIGNOPE").is_err());
+}
+
+#[tokio::test]
+async fn test_signal_all() {
+    use std::os::unix::process::CommandExt;
+    use tokio::io::AsyncReadExt;
+
+    // The shell does not forward the signal, so the sleep would keep the pipe open unless the group is signalled
+    let children = ChildProcesses::default();
+    let mut command = std::process::Command::new("/bin/sh");
+    command.args(["-c", "sleep 30; echo x"]).stdout(std::process::Stdio::piped()).process_group(0);
+    let mut child = tokio::process::Command::from(command).spawn().unwrap();
+    children.insert(child.id().unwrap());
+    assert_eq!(children.signal_all(Signal::SIGTERM), 1);
+
+    let timeout = std::time::Duration::from_secs(5);
+    let mut stdout = String::new();
+    tokio::time::timeout(timeout, child.stdout.take().unwrap().read_to_string(&mut stdout)).await.unwrap().unwrap();
+    assert_eq!(stdout, "");
+    let status = tokio::time::timeout(timeout, child.wait()).await.unwrap().unwrap();
+    assert!(!status.success());
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+
+use log::warn;
+use redis::aio::ConnectionManager;
+use redis::{RedisWrite, ToRedisArgs};
+
+use crate::config::CTL_KEY;
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// Unread control commands are removed if the process does not pick them up within this many seconds.
+const CTL_TTL_SECS: u64 = 60 * 60 * 24;
+
+/// A command sent to a running worker process through Redis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerControl {
+    /// Finish the running jobs without starting new ones, then exit.
+    Drain,
+    /// Kill the running jobs (releasing them according to the shutdown policy), then exit.
+    Stop,
+    /// Change the number of jobs that are run at the same time.
+    Resize(u32),
+}
+
+impl WorkerControl {
+    pub fn from_string(value: &str) -> RsrqResult<WorkerControl> {
+        match value.split_once(':') {
+            None if value == "drain" => Ok(WorkerControl::Drain),
+            None if value == "stop" => Ok(WorkerControl::Stop),
+            Some(("resize", workers)) => match workers.parse::<u32>() {
+                Ok(workers) if workers > 0 => Ok(WorkerControl::Resize(workers)),
+                _ => Err(RsrqError::ParserError(format!("Invalid number of workers: {}", workers))),
+            },
+            _ => Err(RsrqError::ParserError(format!("Invalid worker control: {}", value))),
+        }
+    }
+
+    pub fn get_key(proc_id: usize) -> String {
+        format!("{}:{}", CTL_KEY, proc_id)
+    }
+
+    /// Send the command to a worker process, it is picked up the next time the process polls.
+    pub async fn send(&self, proc_id: usize, con: &mut ConnectionManager) -> RsrqResult<()> {
+        let key = WorkerControl::get_key(proc_id);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.rpush(&key, self);
+        pipe.expire(&key, CTL_TTL_SECS as usize);
+        let _: () = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+        Ok(())
+    }
+
+    /// Remove and return all commands that were sent to the worker process, in the order they were sent.
+    pub async fn receive_all(proc_id: usize, con: &mut ConnectionManager) -> RsrqResult<Vec<WorkerControl>> {
+        let key = WorkerControl::get_key(proc_id);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        pipe.lrange(&key, 0, -1);
+        pipe.del(&key);
+        let (values, _): (Vec<String>, usize) = pipe.query_async(con).await.map_err(RsrqError::RedisOpError)?;
+
+        let mut out = Vec::with_capacity(values.len());
+        for value in values {
+            match WorkerControl::from_string(&value) {
+                Ok(control) => out.push(control),
+                Err(e) => warn!("Ignoring worker control command: {}", e),
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for WorkerControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkerControl::Drain => write!(f, "drain"),
+            WorkerControl::Stop => write!(f, "stop"),
+            WorkerControl::Resize(workers) => write!(f, "resize:{}", workers),
+        }
+    }
+}
+
+impl std::str::FromStr for WorkerControl {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WorkerControl::from_string(s)
+    }
+}
+
+impl ToRedisArgs for WorkerControl {
+    fn write_redis_args<W>(&self, out: &mut W) where W: ?Sized + RedisWrite {
+        ToRedisArgs::write_redis_args(&self.to_string(), out);
+    }
+}
+
+#[test]
+fn test_worker_control() {
+    for control in [WorkerControl::Drain, WorkerControl::Stop, WorkerControl::Resize(8)] {
+        assert_eq!(WorkerControl::from_string(&control.to_string()).unwrap(), control);
+    }
+    assert!(WorkerControl::from_string("resize:0").is_err());
+    assert!(WorkerControl::from_string("resize").is_err());
+    assert!(WorkerControl::from_string("pause").is_err());
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use crate::model::error::RsrqError;
+use crate::model::job::exec_mode::ExecMode;
+use crate::model::types::RsrqResult;
+
+/// Settings used by the worker when running jobs, the job's own settings take precedence.
+#[derive(Debug, Clone)]
+pub struct JobDefaults {
+    /// Kill jobs that run for longer than this many seconds.
+    pub timeout: Option<u64>,
+    /// How commands are run.
+    pub mode: ExecMode,
+    /// How commands are run for specific queues, taking precedence over the mode of the worker.
+    pub queue_modes: BTreeMap<String, ExecMode>,
+    /// The shell used to run commands in shell mode (e.g. "/bin/sh").
+    pub shell: String,
+    /// Write the stdout and stderr of each job to files in this directory (instead of Redis).
+    pub log_dir: Option<String>,
+    /// The name of the log files relative to the log directory (see `OutputFiles::new`).
+    pub log_template: String,
+    /// The number of bytes from the end of each log file that are also stored in Redis.
+    pub log_tail: usize,
+}
+
+impl JobDefaults {
+    /// How commands are run for jobs in the queue that do not specify a mode.
+    pub fn mode_for(&self, queue: &str) -> ExecMode {
+        self.queue_modes.get(queue).copied().unwrap_or(self.mode)
+    }
+}
+
+/// Parse the mode of each queue (e.g. "high=shell"), the queues must be processed by the worker.
+pub fn parse_queue_modes(values: &[String], queues: &[String]) -> RsrqResult<BTreeMap<String, ExecMode>> {
+    let mut out = BTreeMap::new();
+    for value in values {
+        let (queue, mode) = value.split_once('=')
+            .ok_or(RsrqError::ParserError(format!("Invalid queue mode (expected <queue>=<mode>): {}", value)))?;
+        if !queues.iter().any(|x| x == queue) {
+            return Err(RsrqError::ParserError(format!("The worker does not process the queue: {}", queue)));
+        }
+        if out.insert(queue.to_string(), ExecMode::from_string(mode)?).is_some() {
+            return Err(RsrqError::ParserError(format!("Duplicate queue mode: {}", queue)));
+        }
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_parse_queue_modes() {
+    let queues = vec!["high".to_string(), "low".to_string()];
+    let modes = parse_queue_modes(&["high=shell".to_string()], &queues).unwrap();
+    let defaults = JobDefaults {
+        timeout: None,
+        mode: ExecMode::Exec,
+        queue_modes: modes,
+        shell: "/bin/sh".to_string(),
+        log_dir: None,
+        log_template: "{queue}/{id}.{stream}".to_string(),
+        log_tail: 0,
+    };
+    assert_eq!(defaults.mode_for("high"), ExecMode::Shell);
+    assert_eq!(defaults.mode_for("low"), ExecMode::Exec);
+
+    assert!(parse_queue_modes(&["other=shell".to_string()], &queues).is_err());
+    assert!(parse_queue_modes(&["high".to_string()], &queues).is_err());
+    assert!(parse_queue_modes(&["high=sh".to_string()], &queues).is_err());
+    assert!(parse_queue_modes(&["high=shell".to_string(), "high=exec".to_string()], &queues).is_err());
+}
@@ -1,10 +1,18 @@
+use crate::model::worker::control::WorkerControl;
+
 #[derive(Debug)]
 pub enum WorkerMessageReason {
     Sigint,
+    Sigterm,
+    Sighup,
     TimeExceeded,
     CheckForJobs,
+    PromoteDue,
+    CheckCancelled,
+    Heartbeat,
     MaxJobs,
     BurstNoJobs,
+    Control,
 }
 
 /// Wraps messages sent through tokio channels.
@@ -12,19 +20,38 @@ pub enum WorkerMessageReason {
 pub struct WorkerMessage {
     pub job_id: Option<usize>,
     pub reason: WorkerMessageReason,
+    pub control: Option<WorkerControl>,
 }
 
 impl WorkerMessage {
     pub fn exit_sigint() -> WorkerMessage {
         WorkerMessage {
             job_id: None,
+            control: None,
             reason: WorkerMessageReason::Sigint,
         }
     }
 
+    pub fn exit_sigterm() -> WorkerMessage {
+        WorkerMessage {
+            job_id: None,
+            control: None,
+            reason: WorkerMessageReason::Sigterm,
+        }
+    }
+
+    pub fn exit_sighup() -> WorkerMessage {
+        WorkerMessage {
+            job_id: None,
+            control: None,
+            reason: WorkerMessageReason::Sighup,
+        }
+    }
+
     pub fn exit_time() -> WorkerMessage {
         WorkerMessage {
             job_id: None,
+            control: None,
             reason: WorkerMessageReason::TimeExceeded,
         }
     }
@@ -32,14 +59,39 @@ impl WorkerMessage {
     pub fn check_for_jobs() -> WorkerMessage {
         WorkerMessage {
             job_id: None,
+            control: None,
             reason: WorkerMessageReason::CheckForJobs,
         }
     }
 
+    pub fn promote_due() -> WorkerMessage {
+        WorkerMessage {
+            job_id: None,
+            control: None,
+            reason: WorkerMessageReason::PromoteDue,
+        }
+    }
+
+    pub fn check_cancelled() -> WorkerMessage {
+        WorkerMessage {
+            job_id: None,
+            control: None,
+            reason: WorkerMessageReason::CheckCancelled,
+        }
+    }
+
+    pub fn heartbeat() -> WorkerMessage {
+        WorkerMessage {
+            job_id: None,
+            control: None,
+            reason: WorkerMessageReason::Heartbeat,
+        }
+    }
 
     pub fn finished_job(job_id: usize) -> WorkerMessage {
         WorkerMessage {
             job_id: Some(job_id),
+            control: None,
             reason: WorkerMessageReason::CheckForJobs,
         }
     }
@@ -47,6 +99,7 @@ impl WorkerMessage {
     pub fn exit_max_jobs() -> WorkerMessage {
         WorkerMessage {
             job_id: None,
+            control: None,
             reason: WorkerMessageReason::MaxJobs,
         }
     }
@@ -54,7 +107,16 @@ impl WorkerMessage {
     pub fn burst_no_jobs() -> WorkerMessage {
         WorkerMessage {
             job_id: None,
+            control: None,
             reason: WorkerMessageReason::BurstNoJobs,
         }
     }
-}
\ No newline at end of file
+
+    pub fn control(control: WorkerControl) -> WorkerMessage {
+        WorkerMessage {
+            job_id: None,
+            control: Some(control),
+            reason: WorkerMessageReason::Control,
+        }
+    }
+}
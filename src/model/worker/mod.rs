@@ -1,3 +1,9 @@
 pub mod result;
 pub mod message;
 pub mod pool;
+pub mod job_defaults;
+pub mod queue_order;
+pub mod queue_selector;
+pub mod control;
+pub mod children;
+pub mod resources;
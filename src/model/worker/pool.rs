@@ -8,34 +8,58 @@ use crate::command::worker::run_on_job::worker_async_on_job_id;
 use crate::model::job::rsrq_job::Job;
 use crate::model::job::status::JobStatus;
 use crate::model::progress_bar::RsrqProgressBar;
+use crate::model::queue::paused::get_paused_queues;
 use crate::model::queue::queue_type::QueueType;
 use crate::model::queue::rsrq_queue::Queue;
 use crate::model::types::{JobFuture, RsrqResult, WorkerMsgSend};
+use crate::model::worker::children::ChildProcesses;
+use crate::model::worker::job_defaults::JobDefaults;
 use crate::model::worker::message::WorkerMessage;
+use crate::model::worker::queue_order::QueueOrder;
+use crate::model::worker::queue_selector::{QueueSelector, QueueWeight};
+use crate::model::worker::resources::ResourcePool;
 
 pub struct WorkerPool {
     pub proc_id: usize,
-    pub last_check_time: std::time::Instant,
     pub futures: HashMap<usize, JobFuture>,
     pub tx: WorkerMsgSend,
     pub con: ConnectionManager,
-    pub poll_ms: u128,
     pub n_jobs_started: usize,
     pub max_workers: usize,
     pub max_jobs: Option<usize>,
-    pub queue: Queue,
+    /// The queues to claim jobs from, in order of priority.
+    pub queues: Vec<Queue>,
+    pub delayed: Vec<Queue>,
+    pub scheduled: Vec<Queue>,
+    pub selector: QueueSelector,
     pub progress: RsrqProgressBar,
     pub burst: bool,
-    has_run_once: bool,
+    pub defaults: JobDefaults,
+    /// The PIDs of the running commands.
+    pub children: ChildProcesses,
+    /// No new jobs are started while draining.
+    pub draining: bool,
+    /// The CPUs and memory available to jobs, and those in use.
+    pub resources: ResourcePool,
+    /// The queue whose next job did not fit within the free resources, it is tried first once resources are released.
+    waiting_queue: Option<usize>,
 }
 
 impl WorkerPool {
-    pub async fn new(proc_id: usize, queue: &str, max_jobs: Option<u32>, max_runtime_secs: Option<u64>, max_workers: u32, poll_ms: u64, burst: bool, tx: &WorkerMsgSend, con: &ConnectionManager) -> RsrqResult<WorkerPool> {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(proc_id: usize, queues: &[QueueWeight], order: QueueOrder, max_jobs: Option<u32>, max_runtime_secs: Option<u64>, max_workers: u32, resources: ResourcePool, burst: bool, defaults: JobDefaults, tx: &WorkerMsgSend, con: &ConnectionManager) -> RsrqResult<WorkerPool> {
         let mut con = con.clone();
-        let q = Queue::new(QueueType::Queued, queue);
+        let new_queues = |q_type: QueueType| -> Vec<Queue> {
+            queues.iter().map(|x| Queue::new(q_type, &x.name)).collect()
+        };
+        let queued = new_queues(QueueType::Queued);
 
         let progress = {
-            let n_queued = q.length(&mut con).await?;
+            let mut n_queued = 0;
+            for queue in &queued {
+                queue.migrate(&mut con).await?;
+                n_queued += queue.length(&mut con).await?;
+            }
             let init_qty: u64 = match max_jobs {
                 None => n_queued as u64,
                 Some(max_jobs) => cmp::min(max_jobs as u64, n_queued as u64)
@@ -49,35 +73,61 @@ impl WorkerPool {
 
         Ok(WorkerPool {
             proc_id,
-            last_check_time: std::time::Instant::now(),
             futures: HashMap::new(),
             tx: tx.clone(),
             con,
-            poll_ms: poll_ms as u128,
             n_jobs_started: 0,
             max_workers: max_workers as usize,
             max_jobs: max_jobs.map(|x| x as usize),
-            queue: q,
+            queues: queued,
+            delayed: new_queues(QueueType::Delayed),
+            scheduled: new_queues(QueueType::Scheduled),
+            selector: QueueSelector::new(order, queues),
             progress,
             burst,
-            has_run_once: false,
+            defaults,
+            children: ChildProcesses::default(),
+            draining: false,
+            resources,
+            waiting_queue: None,
         })
     }
 
+    /// Move the jobs waiting to be retried (or scheduled to run later) that are now due into their queue, returning
+    /// the number moved. Each job that is moved wakes the workers of its queue, as if it had just been enqueued.
+    pub async fn promote_due(&mut self) -> RsrqResult<usize> {
+        let mut n_promoted = 0;
+        for queue in self.delayed.iter().chain(self.scheduled.iter()) {
+            n_promoted += queue.promote_due(&mut self.con).await?;
+        }
+        Ok(n_promoted)
+    }
+
     pub async fn maybe_start_new_jobs(&mut self) -> RsrqResult<()> {
-        if self.has_run_once {
-            let ms_since_last_check = self.last_check_time.elapsed().as_millis();
-            if ms_since_last_check < self.poll_ms {
-                return Ok(());
-            }
-        } else {
-            self.has_run_once = true
+        if self.draining {
+            return Ok(());
         }
 
-        let n_queued = self.queue.length(&mut self.con).await?;
+        // Nothing can be started until a running job finishes
+        if self.futures.len() >= self.max_workers {
+            return Ok(());
+        }
+
+        let mut available: Vec<usize> = Vec::with_capacity(self.queues.len());
+        for queue in &self.queues {
+            available.push(queue.length(&mut self.con).await?);
+        }
+        let n_queued: usize = available.iter().sum();
+
+        // Nothing is claimed from paused queues (their jobs still count towards the remaining jobs)
+        let paused = get_paused_queues(&mut self.con).await?;
+        let claimable: Vec<usize> = self.queues.iter().zip(&available)
+            .map(|(queue, n)| if paused.contains(&queue.name) { 0 } else { *n })
+            .collect();
+        let n_claimable: usize = claimable.iter().sum();
 
         let n_jobs_to_add = {
-            let max_workers_to_add = cmp::min(self.max_workers - self.futures.len(), n_queued);
+            let max_workers_to_add = cmp::min(self.max_workers.saturating_sub(self.futures.len()), n_claimable);
             if let Some(max_jobs) = self.max_jobs {
                 cmp::min(max_workers_to_add, max_jobs - self.n_jobs_started)
             } else {
@@ -85,12 +135,26 @@ impl WorkerPool {
             }
         };
 
-        let new_job_ids = self.queue.get_n_next_job_ids(n_jobs_to_add, &mut self.con).await?;
-        let n_new_jobs_added = new_job_ids.len();
-        for cur_job_id in new_job_ids {
-            // Here the actual method thread is spawned
-            self.run(cur_job_id);
-        }
+        // Decide how many jobs to claim from each queue (according to the queue order)
+        let n_new_jobs_added = if self.resources.is_limited() {
+            self.claim_within_resources(claimable, n_jobs_to_add).await?
+        } else {
+            let allocation = self.selector.allocate(&claimable, n_jobs_to_add);
+            let mut n_claimed = 0;
+            for (idx, n_to_claim) in allocation.into_iter().enumerate() {
+                if n_to_claim == 0 {
+                    continue;
+                }
+                let queue_name = self.queues[idx].name.clone();
+                let new_job_ids = self.queues[idx].get_n_next_job_ids(n_to_claim, &mut self.con).await?;
+                n_claimed += new_job_ids.len();
+                for cur_job_id in new_job_ids {
+                    // Here the actual method thread is spawned
+                    self.run(cur_job_id, &queue_name);
+                }
+            }
+            n_claimed
+        };
         self.n_jobs_started += n_new_jobs_added;
 
         // Update the progress bar
@@ -103,18 +167,61 @@ impl WorkerPool {
                 let _ = self.tx.send(WorkerMessage::exit_max_jobs()).await;
             }
         }
-
-        // Reset the last check time
-        self.last_check_time = std::time::Instant::now();
         Ok(())
     }
 
+    /// Claim up to n jobs one at a time, while the next job fits within the free CPUs and memory. Once the next job
+    /// does not fit, no other jobs are started until it does, so that large jobs are not starved by smaller ones.
+    /// Jobs that need more than the total capacity are run once no other jobs are running.
+    async fn claim_within_resources(&mut self, mut claimable: Vec<usize>, n: usize) -> RsrqResult<usize> {
+        let mut n_claimed = 0;
+        while n_claimed < n {
+            let idx = match self.waiting_queue.take().filter(|x| claimable[*x] > 0) {
+                Some(idx) => idx,
+                None => match self.selector.allocate(&claimable, 1).iter().position(|x| *x > 0) {
+                    Some(idx) => idx,
+                    None => break,
+                },
+            };
+            let (free_cpus, free_mem_mb) = if self.futures.is_empty() {
+                (None, None)
+            } else {
+                (self.resources.free_cpus(), self.resources.free_mem_mb())
+            };
+            match self.queues[idx].claim_next_job_if_fits(free_cpus, free_mem_mb, &mut self.con).await? {
+                // Another worker claimed the remaining jobs
+                None => claimable[idx] = 0,
+                Some((job_id, resources, true)) => {
+                    if self.resources.exceeds_capacity(&resources) {
+                        warn!("Job {} needs {} CPU(s) and {} MB, more than this worker has, running it on its own.", job_id, resources.cpus, resources.mem_mb);
+                    }
+                    self.resources.take(job_id, resources);
+                    let queue_name = self.queues[idx].name.clone();
+                    self.run(job_id, &queue_name);
+                    claimable[idx] = claimable[idx].saturating_sub(1);
+                    n_claimed += 1;
+                }
+                Some((job_id, resources, false)) => {
+                    debug!("Job {} is waiting for {} CPU(s) and {} MB to be free.", job_id, resources.cpus, resources.mem_mb);
+                    self.waiting_queue = Some(idx);
+                    break;
+                }
+            }
+        }
+        Ok(n_claimed)
+    }
 
     pub async fn update_remaining_tasks(&mut self, queue_len: usize, running_tasks: usize) -> RsrqResult<()> {
         let remaining_tasks = queue_len + running_tasks;
         if remaining_tasks == 0 {
-            // Exit if we are running in burst mode
+            // Exit if we are running in burst mode (and no failed jobs are waiting to be retried)
+            let mut n_delayed = 0;
             if self.burst {
+                for queue in &self.delayed {
+                    n_delayed += queue.length(&mut self.con).await?;
+                }
+            }
+            if self.burst && n_delayed == 0 {
                 let _ = self.tx.send(WorkerMessage::burst_no_jobs()).await;
             } else {
                 debug!("start tick waiting");
@@ -127,13 +234,15 @@ impl WorkerPool {
         Ok(())
     }
 
-    pub fn run(&mut self, job_id: usize) {
-        let queue_clone = self.queue.name.to_string();
+    pub fn run(&mut self, job_id: usize, queue_name: &str) {
+        let queue_clone = queue_name.to_string();
         let mut manager_copy = self.con.clone();
         let tx = self.tx.clone();
         let proc_id = self.proc_id;
+        let defaults = self.defaults.clone();
+        let children = self.children.clone();
         let thread = tokio::spawn(async move {
-            let res = worker_async_on_job_id(proc_id, job_id, queue_clone, &mut manager_copy).await;
+            let res = worker_async_on_job_id(proc_id, job_id, queue_clone, &defaults, &children, &mut manager_copy).await;
             let _ = tx.send(WorkerMessage::finished_job(job_id)).await;
             res
         });
@@ -141,8 +250,28 @@ impl WorkerPool {
         self.progress.track_job_start(job_id);
     }
 
+    /// Change the number of jobs that are run at the same time, running jobs are not interrupted.
+    pub fn resize(&mut self, max_workers: u32) {
+        self.max_workers = max_workers as usize;
+        self.progress.set_workers(max_workers);
+    }
+
+    /// Abort all running jobs (killing their commands), returning their ids.
+    pub fn abort_all(&mut self) -> Vec<usize> {
+        let mut job_ids: Vec<usize> = self.futures.keys().copied().collect();
+        job_ids.sort();
+        for job_id in &job_ids {
+            if let Some(future) = self.futures.get(job_id) {
+                future.abort();
+            }
+            self.remove_job(*job_id);
+        }
+        job_ids
+    }
+
     pub fn remove_job(&mut self, job_id: usize) {
         self.futures.remove(&job_id);
+        self.resources.release(job_id);
         self.progress.track_job_end(job_id);
     }
 
@@ -164,7 +293,11 @@ impl WorkerPool {
                 JobStatus::Running => false,
                 JobStatus::Finished => false,
                 JobStatus::Failed => false,
-                JobStatus::Cancelled => true
+                JobStatus::Cancelled => true,
+                JobStatus::TimedOut => false,
+                JobStatus::Delayed => false,
+                JobStatus::Deferred => false,
+                JobStatus::Scheduled => false,
             };
             debug!("Job ID {} (status={}) is marked as cancelled: {}", cur_job_id, status, to_cancel);
             if to_cancel {
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
+/// How a worker that consumes multiple queues chooses which queue to claim jobs from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueOrder {
+    /// Only claim jobs from a queue once all queues before it are empty.
+    #[default]
+    Strict,
+    /// Share the workers between queues in proportion to their weights (e.g. "high:3,low:1").
+    Weighted,
+}
+
+impl QueueOrder {
+    pub fn from_string(value: &str) -> RsrqResult<QueueOrder> {
+        match value {
+            "strict" => Ok(QueueOrder::Strict),
+            "weighted" => Ok(QueueOrder::Weighted),
+            _ => Err(RsrqError::ParserError(format!("Invalid queue order: {}", value))),
+        }
+    }
+}
+
+impl fmt::Display for QueueOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueueOrder::Strict => write!(f, "strict"),
+            QueueOrder::Weighted => write!(f, "weighted"),
+        }
+    }
+}
+
+impl std::str::FromStr for QueueOrder {
+    type Err = RsrqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        QueueOrder::from_string(s)
+    }
+}
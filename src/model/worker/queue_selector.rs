@@ -0,0 +1,125 @@
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+use crate::model::worker::queue_order::QueueOrder;
+
+/// A queue consumed by a worker, and its share of the workers in weighted order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueWeight {
+    pub name: String,
+    pub weight: u32,
+}
+
+/// Parse a comma separated list of queue names, each optionally followed by a weight (e.g. "high:3,low").
+pub fn parse_queue_list(value: &str) -> RsrqResult<Vec<QueueWeight>> {
+    let mut out: Vec<QueueWeight> = Vec::new();
+    for item in value.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        let (name, weight) = match item.split_once(':') {
+            Some((name, weight)) => {
+                let weight = weight.parse::<u32>().ok().filter(|x| *x > 0)
+                    .ok_or(RsrqError::ParserError(format!("Invalid queue weight: {}", item)))?;
+                (name, weight)
+            }
+            None => (item, 1),
+        };
+        if out.iter().any(|x| x.name == name) {
+            return Err(RsrqError::ParserError(format!("Duplicate queue: {}", name)));
+        }
+        out.push(QueueWeight { name: name.to_string(), weight });
+    }
+    if out.is_empty() {
+        return Err(RsrqError::ParserError("At least one queue is required.".to_string()));
+    }
+    Ok(out)
+}
+
+/// Decides how many jobs to claim from each queue, weighted order uses smooth weighted round-robin
+/// so that the share of each queue is kept between calls.
+#[derive(Debug)]
+pub struct QueueSelector {
+    order: QueueOrder,
+    weights: Vec<i64>,
+    current: Vec<i64>,
+}
+
+impl QueueSelector {
+    pub fn new(order: QueueOrder, queues: &[QueueWeight]) -> QueueSelector {
+        QueueSelector {
+            order,
+            weights: queues.iter().map(|x| x.weight as i64).collect(),
+            current: vec![0; queues.len()],
+        }
+    }
+
+    /// The number of jobs to claim from each queue, given the number of jobs available in each queue
+    /// and the number of free workers.
+    pub fn allocate(&mut self, available: &[usize], n: usize) -> Vec<usize> {
+        let mut out = vec![0; available.len()];
+        let mut remaining = n;
+        match self.order {
+            QueueOrder::Strict => {
+                for (idx, n_available) in available.iter().enumerate() {
+                    out[idx] = remaining.min(*n_available);
+                    remaining -= out[idx];
+                }
+            }
+            QueueOrder::Weighted => {
+                while remaining > 0 {
+                    let candidates: Vec<usize> = (0..available.len()).filter(|x| out[*x] < available[*x]).collect();
+                    if candidates.is_empty() {
+                        break;
+                    }
+                    let total: i64 = candidates.iter().map(|x| self.weights[*x]).sum();
+                    for idx in &candidates {
+                        self.current[*idx] += self.weights[*idx];
+                    }
+                    let selected = *candidates.iter().max_by_key(|x| (self.current[**x], -(**x as i64))).unwrap_or(&candidates[0]);
+                    self.current[selected] -= total;
+                    out[selected] += 1;
+                    remaining -= 1;
+                }
+            }
+        }
+        out
+    }
+}
+
+#[test]
+fn test_parse_queue_list() {
+    let queues = parse_queue_list("high:3, low").unwrap();
+    assert_eq!(queues, vec![
+        QueueWeight { name: "high".to_string(), weight: 3 },
+        QueueWeight { name: "low".to_string(), weight: 1 },
+    ]);
+    assert_eq!(parse_queue_list("default").unwrap().len(), 1);
+    assert!(parse_queue_list("").is_err());
+    assert!(parse_queue_list("high:0").is_err());
+    assert!(parse_queue_list("high:x").is_err());
+    assert!(parse_queue_list("high,high").is_err());
+}
+
+#[test]
+fn test_queue_selector() {
+    let queues = parse_queue_list("high:3,low:1").unwrap();
+
+    let mut strict = QueueSelector::new(QueueOrder::Strict, &queues);
+    assert_eq!(strict.allocate(&[2, 10], 4), vec![2, 2]);
+    assert_eq!(strict.allocate(&[10, 10], 4), vec![4, 0]);
+    assert_eq!(strict.allocate(&[0, 1], 4), vec![0, 1]);
+
+    let mut weighted = QueueSelector::new(QueueOrder::Weighted, &queues);
+    assert_eq!(weighted.allocate(&[10, 10], 4), vec![3, 1]);
+    assert_eq!(weighted.allocate(&[10, 10], 8), vec![6, 2]);
+
+    // Shares are kept between calls that claim one job at a time
+    let mut claimed = vec![0, 0];
+    for _ in 0..8 {
+        let out = weighted.allocate(&[10, 10], 1);
+        claimed[0] += out[0];
+        claimed[1] += out[1];
+    }
+    assert_eq!(claimed, vec![6, 2]);
+
+    // Empty queues give their share to the others
+    assert_eq!(weighted.allocate(&[0, 10], 4), vec![0, 4]);
+    assert_eq!(weighted.allocate(&[1, 10], 4), vec![1, 3]);
+}
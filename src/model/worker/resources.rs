@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use crate::model::job::resources::JobResources;
+
+/// The CPUs and memory available to a worker (unlimited if not set), and the resources of the jobs it is running.
+#[derive(Debug, Default)]
+pub struct ResourcePool {
+    pub cpus: Option<u32>,
+    pub mem_mb: Option<u64>,
+    in_use: HashMap<usize, JobResources>,
+}
+
+impl ResourcePool {
+    pub fn new(cpus: Option<u32>, mem_mb: Option<u64>) -> ResourcePool {
+        ResourcePool {
+            cpus,
+            mem_mb,
+            in_use: HashMap::new(),
+        }
+    }
+
+    /// True if the CPUs or memory of the worker are limited.
+    pub fn is_limited(&self) -> bool {
+        self.cpus.is_some() || self.mem_mb.is_some()
+    }
+
+    /// The resources used by the running jobs.
+    pub fn used(&self) -> JobResources {
+        JobResources {
+            cpus: self.in_use.values().map(|x| x.cpus).sum(),
+            mem_mb: self.in_use.values().map(|x| x.mem_mb).sum(),
+        }
+    }
+
+    /// The CPUs that are not used by running jobs (None if unlimited).
+    pub fn free_cpus(&self) -> Option<u32> {
+        self.cpus.map(|x| x.saturating_sub(self.used().cpus))
+    }
+
+    /// The memory (in MB) that is not used by running jobs (None if unlimited).
+    pub fn free_mem_mb(&self) -> Option<u64> {
+        self.mem_mb.map(|x| x.saturating_sub(self.used().mem_mb))
+    }
+
+    /// True if the job needs more than the total capacity of the worker (it can only be run on its own).
+    pub fn exceeds_capacity(&self, job: &JobResources) -> bool {
+        self.cpus.is_some_and(|x| job.cpus > x) || self.mem_mb.is_some_and(|x| job.mem_mb > x)
+    }
+
+    /// Reserve the resources of a job that was started.
+    pub fn take(&mut self, job_id: usize, job: JobResources) {
+        self.in_use.insert(job_id, job);
+    }
+
+    /// Release the resources of a job that has finished (or was aborted).
+    pub fn release(&mut self, job_id: usize) {
+        self.in_use.remove(&job_id);
+    }
+}
+
+#[test]
+fn test_resource_pool() {
+    let mut pool = ResourcePool::new(Some(8), Some(16000));
+    assert!(pool.is_limited());
+    pool.take(1, JobResources { cpus: 4, mem_mb: 1000 });
+    pool.take(2, JobResources { cpus: 2, mem_mb: 12000 });
+    assert_eq!(pool.free_cpus(), Some(2));
+    assert_eq!(pool.free_mem_mb(), Some(3000));
+
+    pool.release(2);
+    pool.release(3);
+    assert_eq!(pool.free_cpus(), Some(4));
+    assert_eq!(pool.free_mem_mb(), Some(15000));
+
+    assert!(pool.exceeds_capacity(&JobResources { cpus: 16, mem_mb: 0 }));
+    assert!(!pool.exceeds_capacity(&JobResources { cpus: 8, mem_mb: 16000 }));
+
+    // Only the CPUs are limited
+    let pool = ResourcePool::new(Some(4), None);
+    assert_eq!(pool.free_mem_mb(), None);
+    assert!(!pool.exceeds_capacity(&JobResources { cpus: 1, mem_mb: 1_000_000 }));
+    assert!(!ResourcePool::default().is_limited());
+}
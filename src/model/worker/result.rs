@@ -12,21 +12,23 @@ pub struct WorkerResult {
 }
 
 impl WorkerResult {
-    pub fn from_failed() -> WorkerResult {
+    /// The command could not be run (e.g. it could not be parsed), the reason is kept as its stderr.
+    pub fn from_failed(reason: &str) -> WorkerResult {
         WorkerResult {
             q_target: QueueType::Failed,
             job_status: JobStatus::Failed,
             stdout: "".to_string(),
-            stderr: "Unable to parse command.".to_string(),
+            stderr: reason.to_string(),
             duration_ms: 0,
             exit_code: 1,
         }
     }
 
     pub fn from_command(result: &RsrqCommandResult) -> WorkerResult {
-        let (job_status, q_target) = match result.exit_code {
-            0 => (JobStatus::Finished, QueueType::Finished),
-            _ => (JobStatus::Failed, QueueType::Failed),
+        let (job_status, q_target) = match (result.timed_out, result.exit_code) {
+            (true, _) => (JobStatus::TimedOut, QueueType::Failed),
+            (false, 0) => (JobStatus::Finished, QueueType::Finished),
+            (false, _) => (JobStatus::Failed, QueueType::Failed),
         };
         WorkerResult {
             q_target,
@@ -64,3 +64,114 @@ pub fn btree_get_opt<T: std::str::FromStr, K: std::fmt::Display>(map: &BTreeMap<
         None => Ok(None)
     }
 }
+
+/// Parse a comma separated list of ids (e.g. "1,2,3"), an empty string is an empty list.
+pub fn parse_id_list(input: &str) -> RsrqResult<Vec<usize>> {
+    input.split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(parse_string)
+        .collect()
+}
+
+/// Format a list of ids as a comma separated string (e.g. "1,2,3").
+pub fn format_id_list(ids: &[usize]) -> String {
+    ids.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")
+}
+
+#[test]
+fn test_parse_id_list() {
+    assert_eq!(parse_id_list("1,2,3").unwrap(), vec![1, 2, 3]);
+    assert_eq!(parse_id_list(" 1, 2 ,3 ").unwrap(), vec![1, 2, 3]);
+    assert_eq!(parse_id_list("5").unwrap(), vec![5]);
+    assert!(parse_id_list("").unwrap().is_empty());
+    assert!(parse_id_list("1,a").is_err());
+    assert_eq!(format_id_list(&[1, 2, 3]), "1,2,3");
+    assert_eq!(format_id_list(&[]), "");
+}
+
+/// Parse a JSON object of strings (e.g. environment variables), an empty string is an empty map.
+pub fn parse_string_map(input: &str) -> RsrqResult<BTreeMap<String, String>> {
+    if input.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    serde_json::from_str(input).map_err(|e| RsrqError::ParserError(format!("Invalid JSON object of strings: {}", e)))
+}
+
+/// Format a map as a JSON object of strings, an empty map is an empty string.
+pub fn format_string_map(map: &BTreeMap<String, String>) -> String {
+    if map.is_empty() {
+        return "".to_string();
+    }
+    serde_json::to_string(map).unwrap_or_default()
+}
+
+/// Parse a comma separated list of tags (e.g. "a,b"), an empty string is an empty list.
+pub fn parse_tag_list(input: &str) -> Vec<String> {
+    input.split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Format a list of tags as a comma separated string (e.g. "a,b").
+pub fn format_tag_list(tags: &[String]) -> String {
+    tags.join(",")
+}
+
+#[test]
+fn test_parse_string_map_and_tags() {
+    let env = parse_string_map(r#"{"A":"1","B":"x y"}"#).unwrap();
+    assert_eq!(env.get("B").unwrap(), "x y");
+    assert_eq!(parse_string_map(&format_string_map(&env)).unwrap(), env);
+    assert!(parse_string_map("").unwrap().is_empty());
+    assert_eq!(format_string_map(&BTreeMap::new()), "");
+    assert!(parse_string_map("{\"A\": 1}").is_err());
+
+    assert_eq!(parse_tag_list("a, b,,c"), vec!["a", "b", "c"]);
+    assert!(parse_tag_list("").is_empty());
+    assert_eq!(format_tag_list(&["a".to_string(), "b".to_string()]), "a,b");
+}
+
+/// Parse an amount of memory into MB, the unit is optional (K, M, G, T, e.g. "512", "500K", "512M", "16G", "1.5T").
+pub fn parse_memory_mb(input: &str) -> RsrqResult<u64> {
+    parse_memory_with_unit(input, 1.0)
+}
+
+/// Parse an amount of memory into MB, a value without a unit is in the given unit (in MB, e.g. 1/1024 for KB).
+pub fn parse_memory_with_unit(input: &str, unit_mb: f64) -> RsrqResult<u64> {
+    let invalid = || RsrqError::ParserError(format!("Invalid amount of memory: {}", input));
+    let value = input.trim().to_uppercase();
+    let value = value.strip_suffix('B').unwrap_or(&value);
+    let (number, multiplier) = match value.chars().last() {
+        Some('K') => (&value[..value.len() - 1], 1.0 / 1024.0),
+        Some('M') => (&value[..value.len() - 1], 1.0),
+        Some('G') => (&value[..value.len() - 1], 1024.0),
+        Some('T') => (&value[..value.len() - 1], 1024.0 * 1024.0),
+        _ => (value, unit_mb),
+    };
+    let number: f64 = number.trim().parse().map_err(|_| invalid())?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(invalid());
+    }
+    Ok((number * multiplier).ceil() as u64)
+}
+
+#[test]
+fn test_parse_memory_mb() {
+    assert_eq!(parse_memory_mb("512").unwrap(), 512);
+    assert_eq!(parse_memory_mb("512M").unwrap(), 512);
+    assert_eq!(parse_memory_mb("16G").unwrap(), 16384);
+    assert_eq!(parse_memory_mb("16gb").unwrap(), 16384);
+    assert_eq!(parse_memory_mb("1.5G").unwrap(), 1536);
+    assert_eq!(parse_memory_mb("1T").unwrap(), 1048576);
+    assert_eq!(parse_memory_mb("2048K").unwrap(), 2);
+    assert_eq!(parse_memory_mb("500K").unwrap(), 1);
+    assert_eq!(parse_memory_with_unit("1073741824", 1.0 / (1024.0 * 1024.0)).unwrap(), 1024);
+    assert_eq!(parse_memory_with_unit("2G", 1.0 / (1024.0 * 1024.0)).unwrap(), 2048);
+    assert!(parse_memory_mb("").is_err());
+    assert!(parse_memory_mb("G").is_err());
+    assert!(parse_memory_mb("-1G").is_err());
+    assert!(parse_memory_mb("16X").is_err());
+}
@@ -2,7 +2,7 @@ use std::env;
 
 use log::error;
 use redis::Connection;
-use redis::aio::ConnectionManager;
+use redis::aio::{ConnectionManager, PubSub};
 
 use crate::config::REDIS_ENV_URL;
 use crate::model::error::RsrqError;
@@ -28,11 +28,26 @@ pub fn redis_conn() -> RsrqResult<Connection> {
 
 pub async fn redis_con_manager() -> RsrqResult<ConnectionManager> {
     let conn_string = get_redis_conn_string()?;
+    redis_con_manager_from_url(&conn_string).await
+}
+
+pub async fn redis_con_manager_from_url(conn_string: &str) -> RsrqResult<ConnectionManager> {
     let client = redis::Client::open(conn_string).map_err(RsrqError::RedisConnError)?;
     let manager = client.get_tokio_connection_manager_with_backoff(2, 1, 5).await.map_err(RsrqError::RedisConnError)?;
     Ok(manager)
 }
 
+/// Open a dedicated connection that is subscribed to the channels.
+pub async fn redis_pubsub(channels: &[String]) -> RsrqResult<PubSub> {
+    let conn_string = get_redis_conn_string()?;
+    let client = redis::Client::open(conn_string).map_err(RsrqError::RedisConnError)?;
+    let mut pubsub = client.get_async_connection().await.map_err(RsrqError::RedisConnError)?.into_pubsub();
+    for channel in channels {
+        pubsub.subscribe(channel).await.map_err(RsrqError::RedisOpError)?;
+    }
+    Ok(pubsub)
+}
+
 // pub async fn get_con_async() -> RsrqResult<redis::aio::Connection> {
 //     let conn_string = get_redis_conn_string()?;
 //     let client = redis::Client::open(conn_string).map_err(RsrqError::RedisConnError)?;
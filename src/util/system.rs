@@ -1,3 +1,6 @@
+use crate::model::error::RsrqError;
+use crate::model::types::RsrqResult;
+
 /// Return the hostname of the machine, will return "N/A" if not possible.
 pub fn get_hostname() -> String {
     let hostname = hostname::get();
@@ -12,3 +15,13 @@ pub fn get_pid() -> u32 {
     std::process::id()
 }
 
+
+/// Resolve a path relative to the current working directory (the path doesn't need to exist).
+pub fn get_absolute_path(path: &str) -> RsrqResult<String> {
+    let path = std::path::Path::new(path);
+    if path.is_absolute() {
+        return Ok(path.to_string_lossy().to_string());
+    }
+    let cwd = std::env::current_dir().map_err(RsrqError::IOError)?;
+    Ok(cwd.join(path).to_string_lossy().to_string())
+}
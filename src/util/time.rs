@@ -1,6 +1,14 @@
+use chrono::TimeZone;
+use lazy_static::lazy_static;
+use regex::Regex;
+
 use crate::model::error::RsrqError;
 use crate::model::types::RsrqResult;
 
+lazy_static! {
+    static ref RE_DURATION: Regex =  Regex::new(r"(\d+)(\w)").unwrap();
+}
+
 pub fn get_timestamp_s() -> RsrqResult<u64> {
     let now = std::time::SystemTime::now();
     let delta = now.duration_since(std::time::UNIX_EPOCH).
@@ -14,7 +22,189 @@ pub fn get_ms_since(start_time: &std::time::Instant) -> u128 {
     current_time.duration_since(*start_time).as_millis()
 }
 
-// pub fn get_timestamp_string() -> String {
-//     let local_time = chrono::Local::now();
-//     format!("{}", local_time.format("%Y-%m-%d %H:%M:%S"))
-// }
\ No newline at end of file
+/// Parses a date and time into a UNIX timestamp (in seconds). Either RFC 3339 (e.g. 2024-01-31T02:00:00+10:00),
+/// a local date and time (e.g. "2024-01-31 02:00"), or a local time (e.g. "02:00") which is the next occurrence of that time.
+pub fn parse_datetime(value: &str) -> RsrqResult<u64> {
+    parse_datetime_from(value, chrono::Local::now())
+}
+
+fn parse_datetime_from(value: &str, now: chrono::DateTime<chrono::Local>) -> RsrqResult<u64> {
+    let value = value.trim();
+    let invalid = || RsrqError::ParserError(format!("Invalid date/time provided: {}", value));
+
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(value) {
+        return u64::try_from(datetime.timestamp()).map_err(|_| invalid());
+    }
+
+    // A date and time in the local timezone
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| chrono::NaiveDateTime::parse_from_str(value, fmt).ok());
+
+    // A time today (or tomorrow if it has already passed)
+    let naive = match naive {
+        Some(naive) => naive,
+        None => {
+            let time = ["%H:%M:%S", "%H:%M"]
+                .iter()
+                .find_map(|fmt| chrono::NaiveTime::parse_from_str(value, fmt).ok())
+                .ok_or_else(invalid)?;
+            let today = now.date_naive().and_time(time);
+            if today > now.naive_local() {
+                today
+            } else {
+                today + chrono::Duration::days(1)
+            }
+        }
+    };
+    let local = chrono::Local.from_local_datetime(&naive).earliest().ok_or_else(invalid)?;
+    u64::try_from(local.timestamp()).map_err(|_| invalid())
+}
+
+/// Format a UNIX timestamp (in seconds) as a local date and time.
+pub fn format_timestamp(timestamp: u64) -> String {
+    match chrono::Local.timestamp_opt(timestamp as i64, 0).single() {
+        Some(local_time) => local_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+/// Format a duration in milliseconds as HH:MM:SS.mmm
+pub fn format_duration_ms(ms: u128) -> String {
+    let hours = ms / 3_600_000;
+    let minutes = (ms % 3_600_000) / 60_000;
+    let seconds = (ms % 60_000) / 1000;
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, ms % 1000)
+}
+
+fn parse_time_unit(unit: &str, num: &str) -> RsrqResult<usize> {
+    let num_res = num.parse::<usize>();
+    if num_res.is_err() {
+        return Err(RsrqError::CmdParserError(format!("Invalid number format: {}", num)));
+    }
+    let num = num_res.unwrap();
+
+    match unit {
+        "h" => Ok(num * 60 * 60),
+        "m" => Ok(num * 60),
+        "s" => Ok(num),
+        _ => Err(RsrqError::CmdParserError(format!("Invalid time unit provided: {}", unit)))
+    }
+}
+
+/// Parses a duration in (h)ours (m)inutes (s)econds (eg: 1h30m, 30m, 1h5s) into seconds.
+pub fn parse_duration(duration: &str) -> RsrqResult<u64> {
+    let hits = RE_DURATION.captures_iter(duration).map(|hit| {
+        let (_, [num, unit]) = hit.extract();
+        parse_time_unit(unit, num)
+    });
+    let mut total_seconds = 0;
+    let mut hits_seen = 0;
+    for hit in hits {
+        total_seconds += hit?;
+        hits_seen += 1;
+    }
+    if hits_seen == 0 {
+        return Err(RsrqError::CmdParserError(format!("Invalid duration provided: {}", duration)));
+    }
+    Ok(total_seconds as u64)
+}
+
+
+#[test]
+#[allow(clippy::identity_op)]
+fn test_parse_duration() {
+    fn h2s(hours: u64) -> u64 {
+        hours * 60 * 60
+    }
+
+    fn m2s(minutes: u64) -> u64 {
+        minutes * 60
+    }
+
+    assert_eq!(parse_duration("1h2m3s").unwrap(), h2s(1) + m2s(2) + 3);
+    assert_eq!(parse_duration("10h20m30s").unwrap(), h2s(10) + m2s(20) + 30);
+    assert_eq!(parse_duration("100h200m300s").unwrap(), h2s(100) + m2s(200) + 300);
+
+    // Test only seconds
+    assert_eq!(parse_duration("1s").unwrap(), h2s(0) + m2s(0) + 1);
+
+    // Test only minutes
+    assert_eq!(parse_duration("2m").unwrap(), h2s(0) + m2s(2) + 0);
+
+    // Test only hours
+    assert_eq!(parse_duration("1h").unwrap(), h2s(1) + m2s(0) + 0);
+
+    // Test hours and minutes
+    assert_eq!(parse_duration("1h2m").unwrap(), h2s(1) + m2s(2) + 0);
+
+    // Test minutes and seconds
+    assert_eq!(parse_duration("2m3s").unwrap(), h2s(0) + m2s(2) + 3);
+
+    // Test hours and seconds
+    assert_eq!(parse_duration("1h3s").unwrap(), h2s(1) + m2s(0) + 3);
+
+    // Test hours, minutes, and seconds
+    assert_eq!(parse_duration("1h2m3s").unwrap(), h2s(1) + m2s(2) + 3);
+
+    // Test with leading zeros
+    assert_eq!(parse_duration("01h02m03s").unwrap(), h2s(1) + m2s(2) + 3);
+
+    // Test with different orders
+    assert_eq!(parse_duration("2m1h").unwrap(), h2s(1) + m2s(2) + 0);
+    assert_eq!(parse_duration("3s1h").unwrap(), h2s(1) + m2s(0) + 3);
+    assert_eq!(parse_duration("3s2m").unwrap(), h2s(0) + m2s(2) + 3);
+
+    // Test with invalid inputs
+    assert!(parse_duration("1h2m3x").is_err());
+    assert!(parse_duration("1j2k3l").is_err());
+    assert!(parse_duration("").is_err());
+
+    assert_eq!(parse_duration("1h2m3s").unwrap(), h2s(1) + m2s(2) + 3);
+    assert_eq!(parse_duration("1h3s2m").unwrap(), h2s(1) + m2s(2) + 3);
+    assert_eq!(parse_duration("2m1h3s").unwrap(), h2s(1) + m2s(2) + 3);
+    assert_eq!(parse_duration("2m3s1h").unwrap(), h2s(1) + m2s(2) + 3);
+    assert_eq!(parse_duration("2m3s1h").unwrap(), h2s(1) + m2s(2) + 3);
+
+    assert_eq!(parse_duration("2m1h3s").unwrap(), h2s(1) + m2s(2) + 3);
+
+    assert_eq!(parse_duration("2m3s").unwrap(), h2s(0) + m2s(2) + 3);
+    assert_eq!(parse_duration("3s2m").unwrap(), h2s(0) + m2s(2) + 3);
+
+    assert_eq!(parse_duration("1h2m").unwrap(), h2s(1) + m2s(2) + 0);
+    assert_eq!(parse_duration("1h2s").unwrap(), h2s(1) + m2s(0) + 2);
+    assert_eq!(parse_duration("1m2s").unwrap(), h2s(0) + m2s(1) + 2);
+
+    assert_eq!(parse_duration("1s2m3h").unwrap(), h2s(3) + m2s(2) + 1);
+    assert_eq!(parse_duration("2m3h").unwrap(), h2s(3) + m2s(2) + 0);
+    assert_eq!(parse_duration("2m3h").unwrap(), h2s(3) + m2s(2) + 0);
+}
+
+#[test]
+fn test_format_duration_ms() {
+    assert_eq!(format_duration_ms(0), "00:00:00.000");
+    assert_eq!(format_duration_ms(1234), "00:00:01.234");
+    assert_eq!(format_duration_ms(61_000), "00:01:01.000");
+    assert_eq!(format_duration_ms(90_061_001), "25:01:01.001");
+}
+
+#[test]
+fn test_parse_datetime() {
+    let now = chrono::Local.timestamp_opt(1_700_000_000, 0).unwrap();
+    assert_eq!(parse_datetime_from("2023-11-14T22:13:20+00:00", now).unwrap(), 1_700_000_000);
+    assert_eq!(parse_datetime_from("2023-11-15T08:13:20+10:00", now).unwrap(), 1_700_000_000);
+
+    // Local times are converted using the local timezone
+    let local = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    assert_eq!(parse_datetime_from(&local, now).unwrap(), 1_700_000_000);
+    let local = now.format("%Y-%m-%dT%H:%M").to_string();
+    assert_eq!(parse_datetime_from(&local, now).unwrap(), 1_700_000_000 - 20);
+
+    // Times of day are the next occurrence
+    let later = (now + chrono::Duration::minutes(5)).format("%H:%M:%S").to_string();
+    assert_eq!(parse_datetime_from(&later, now).unwrap(), 1_700_000_000 + 300);
+    let earlier = (now - chrono::Duration::minutes(5)).format("%H:%M:%S").to_string();
+    assert_eq!(parse_datetime_from(&earlier, now).unwrap(), 1_700_000_000 - 300 + 86400);
+
+    assert!(parse_datetime_from("tomorrow", now).is_err());
+}